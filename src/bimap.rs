@@ -0,0 +1,231 @@
+use std::borrow::Borrow;
+use std::iter::FusedIterator;
+
+use crate::{Compare, Iter, Natural, NodeId, SplayTreeBy};
+
+/// One-to-one map between left and right values, which can be searched from
+/// both sides.
+///
+/// The map is made of two cross-linked trees: the node of every left value holds
+/// the handle of the node of its right value and the other way round, so each
+/// value is stored once and neither side needs to be `Clone`. A lookup splays
+/// the tree of the searched side, then follows the handle in *O*(1) time.
+pub struct SplayBiMap<L, R, CL = Natural, CR = Natural> {
+    left: SplayTreeBy<L, NodeId, CL>,
+    right: SplayTreeBy<R, NodeId, CR>,
+}
+
+impl<L, R, CL: Compare<L> + Default, CR: Compare<R> + Default> SplayBiMap<L, R, CL, CR> {
+    /// Creates an empty `SplayBiMap`.
+    #[inline]
+    pub fn new() -> Self {
+        SplayBiMap { left: SplayTreeBy::new(), right: SplayTreeBy::new() }
+    }
+}
+
+impl<L, R, CL: Compare<L>, CR: Compare<R>> SplayBiMap<L, R, CL, CR> {
+    /// Creates an empty `SplayBiMap` ordering left values by `left_cmp`
+    /// and right values by `right_cmp`.
+    #[inline]
+    pub fn with_comparators(left_cmp: CL, right_cmp: CR) -> Self {
+        SplayBiMap {
+            left: SplayTreeBy::with_comparator(left_cmp),
+            right: SplayTreeBy::with_comparator(right_cmp),
+        }
+    }
+
+    /// Pairs `left` with `right`. Pairs which already contain `left` or `right`
+    /// are removed first and returned.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn insert(&mut self, left: L, right: R) -> Displaced<L, R> {
+        let by_left = self.remove_by_left(&left);
+        let by_right = self.remove_by_right(&right);
+
+        // Both positions are found before anything is linked, so a panicking
+        // comparator can't leave a node without its partner.
+        let left_entry = self.left.entry(left);
+        let mut right_entry = self.right.entry(right).insert_entry(NodeId::DANGLING);
+        let right_id = right_entry.id();
+        let left_id = left_entry.insert_entry(right_id).id();
+        right_entry.insert(left_id);
+        Displaced { by_left, by_right }
+    }
+
+    /// Returns the right value paired with `left`, or `None` if the map doesn't
+    /// contain `left`.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_by_left<Q>(&mut self, left: &Q) -> Option<&R>
+        where L: Borrow<Q>, Q: ?Sized, CL: Compare<Q>
+    {
+        let right_id = *self.left.get(left)?.value();
+        self.right.get_by_id(right_id).map(|node| node.key())
+    }
+
+    /// Returns the left value paired with `right`, or `None` if the map doesn't
+    /// contain `right`.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_by_right<Q>(&mut self, right: &Q) -> Option<&L>
+        where R: Borrow<Q>, Q: ?Sized, CR: Compare<Q>
+    {
+        let left_id = *self.right.get(right)?.value();
+        self.left.get_by_id(left_id).map(|node| node.key())
+    }
+
+    /// Returns `true` if the map contains the left value `left`.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn contains_left<Q>(&mut self, left: &Q) -> bool
+        where L: Borrow<Q>, Q: ?Sized, CL: Compare<Q>
+    {
+        self.left.contains_key(left)
+    }
+
+    /// Returns `true` if the map contains the right value `right`.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn contains_right<Q>(&mut self, right: &Q) -> bool
+        where R: Borrow<Q>, Q: ?Sized, CR: Compare<Q>
+    {
+        self.right.contains_key(right)
+    }
+
+    /// Removes the pair of `left` and returns it, or `None` if the map doesn't
+    /// contain `left`.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+        where L: Borrow<Q>, Q: ?Sized, CL: Compare<Q>
+    {
+        let (left, right_id) = self.left.remove(left)?.into_key_value();
+        let right_node = self.right.remove_node(right_id).expect("left value without a right value");
+        Some((left, right_node.into_key_value().0))
+    }
+
+    /// Removes the pair of `right` and returns it, or `None` if the map doesn't
+    /// contain `right`.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(L, R)>
+        where R: Borrow<Q>, Q: ?Sized, CR: Compare<Q>
+    {
+        let (right, left_id) = self.right.remove(right)?.into_key_value();
+        let left_node = self.left.remove_node(left_id).expect("right value without a left value");
+        Some((left_node.into_key_value().0, right))
+    }
+
+    /// Returns the number of pairs in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    /// Returns `true` if the map contains no pairs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    /// Gets an iterator over the pairs of the map, sorted by left value.
+    #[inline]
+    pub fn iter_by_left(&self) -> BiIter<'_, L, R, CR> {
+        BiIter { inner: self.left.iter(), other: &self.right }
+    }
+
+    /// Gets an iterator over the pairs of the map as `(right, left)`, sorted by
+    /// right value.
+    #[inline]
+    pub fn iter_by_right(&self) -> BiIter<'_, R, L, CL> {
+        BiIter { inner: self.right.iter(), other: &self.left }
+    }
+}
+
+impl<L, R, CL, CR> SplayBiMap<L, R, CL, CR> {
+    /// Removes all pairs from the map.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.left.clear();
+        self.right.clear();
+    }
+}
+
+impl<L, R, CL: Compare<L> + Default, CR: Compare<R> + Default> Default for SplayBiMap<L, R, CL, CR> {
+    #[inline]
+    fn default() -> Self {
+        SplayBiMap::new()
+    }
+}
+
+impl<L, R, CL: Compare<L>, CR: Compare<R>> Extend<(L, R)> for SplayBiMap<L, R, CL, CR> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (L, R)>>(&mut self, iter: I) {
+        for (left, right) in iter {
+            self.insert(left, right);
+        }
+    }
+}
+
+impl<L, R, CL: Compare<L> + Default, CR: Compare<R> + Default> FromIterator<(L, R)> for SplayBiMap<L, R, CL, CR> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (L, R)>>(iter: I) -> Self {
+        let mut map = SplayBiMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Pairs removed by [`SplayBiMap::insert`] because they contained one of the
+/// inserted values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Displaced<L, R> {
+    /// Former pair of the inserted left value.
+    pub by_left: Option<(L, R)>,
+    /// Former pair of the inserted right value, unless it was the same pair.
+    pub by_right: Option<(L, R)>,
+}
+
+/// An iterator over the pairs of a `SplayBiMap`, sorted by the values of one side
+/// and yielding them first.
+pub struct BiIter<'a, A, B, C> {
+    inner: Iter<'a, A, NodeId>,
+    other: &'a SplayTreeBy<B, NodeId, C>,
+}
+
+impl<'a, A, B, C: Compare<B>> BiIter<'a, A, B, C> {
+    #[inline]
+    fn pair(&self, (key, id): (&'a A, &'a NodeId)) -> (&'a A, &'a B) {
+        let other = self.other.get_by_id(*id).expect("value without a partner");
+        (key, other.key())
+    }
+}
+
+impl<'a, A, B, C: Compare<B>> Iterator for BiIter<'a, A, B, C> {
+    type Item = (&'a A, &'a B);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a A, &'a B)> {
+        self.inner.next().map(|entry| self.pair(entry))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, A, B, C: Compare<B>> DoubleEndedIterator for BiIter<'a, A, B, C> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(&'a A, &'a B)> {
+        self.inner.next_back().map(|entry| self.pair(entry))
+    }
+}
+
+impl<'a, A, B, C: Compare<B>> ExactSizeIterator for BiIter<'a, A, B, C> {}
+
+impl<'a, A, B, C: Compare<B>> FusedIterator for BiIter<'a, A, B, C> {}
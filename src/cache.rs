@@ -0,0 +1,171 @@
+use std::mem;
+
+use crate::{NodeMut, SplayTree};
+
+/// Fixed-capacity cache built on top of `SplayTree`.
+///
+/// Every access splays the entry to the root, so the working set stays near the top
+/// of the tree. When an insertion overflows the capacity, a leaf reached through the
+/// larger subtree of every node is evicted, which approximates the least recently
+/// used entry without restructuring the tree.
+pub struct SplayCache<K: Ord, V> {
+    tree: SplayTree<K, V>,
+    capacity: usize,
+    on_evict: Option<Box<dyn FnMut(K, V)>>,
+}
+
+impl<K: Ord, V> SplayCache<K, V> {
+    /// Creates an empty `SplayCache` holding at most `capacity` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "SplayCache capacity must be non-zero");
+        SplayCache {
+            tree: SplayTree::new(),
+            capacity,
+            on_evict: None,
+        }
+    }
+
+    /// Creates an empty `SplayCache` holding at most `capacity` entries, which
+    /// passes every evicted entry to `on_evict`. It's useful for write-back caches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[inline]
+    pub fn with_eviction_callback<F>(capacity: usize, on_evict: F) -> Self
+        where F: FnMut(K, V) + 'static
+    {
+        let mut cache = SplayCache::new(capacity);
+        cache.on_evict = Some(Box::new(on_evict));
+        cache
+    }
+
+    /// Returns a reference to the value by a key, or `None` if the cache doesn't
+    /// contain that key. The entry becomes the most recently used one.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.tree.get(key).map(|node| node.value())
+    }
+
+    /// Returns a mutable reference to the value by a key, or `None` if the cache
+    /// doesn't contain that key. The entry becomes the most recently used one.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.tree.get_mut(key).map(NodeMut::into_value_mut)
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    /// The entry becomes the most recently used one.
+    #[inline]
+    pub fn contains_key(&mut self, key: &K) -> bool {
+        self.tree.contains_key(key)
+    }
+
+    /// Inserts a value to the cache with a key and returns the replaced value,
+    /// if any. If the cache overflows its capacity, an entry is evicted.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(mut node) = self.tree.get_mut(&key) {
+            return Some(mem::replace(node.value_mut(), value));
+        }
+
+        self.tree.insert(key, value);
+        self.shrink_to(self.capacity);
+        None
+    }
+
+    /// Removes an entry with a given key and returns its value, or `None` if
+    /// the cache doesn't contain that key. The eviction callback isn't called.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.tree.remove(key).map(|node| node.value)
+    }
+
+    /// Evicts one entry and returns it, or `None` if the cache is empty.
+    /// The eviction callback isn't called.
+    #[inline]
+    pub fn evict(&mut self) -> Option<(K, V)> {
+        self.tree.remove_deep_leaf().map(|node| (node.key, node.value))
+    }
+
+    /// Evicts all entries, passing each of them to the eviction callback.
+    ///
+    /// Entries which are still in the cache when it's dropped are not passed to
+    /// the callback, so a write-back cache should call this method beforehand.
+    pub fn evict_all(&mut self) {
+        self.shrink_to(0);
+    }
+
+    /// Changes the capacity of the cache, evicting entries if it holds more
+    /// than `capacity` of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0, "SplayCache capacity must be non-zero");
+        self.capacity = capacity;
+        self.shrink_to(capacity);
+    }
+
+    /// Returns the maximum number of entries the cache can hold.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns `true` if the cache contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Returns an approximate number of bytes of heap memory held by the entries
+    /// of the cache. Heap memory owned by keys and values themselves isn't counted.
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.tree.memory_usage()
+    }
+
+    fn shrink_to(&mut self, len: usize) {
+        while self.tree.len() > len {
+            let node = match self.tree.remove_deep_leaf() {
+                Some(node) => node,
+                None => break,
+            };
+
+            if let Some(on_evict) = self.on_evict.as_mut() {
+                on_evict(node.key, node.value);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "deepsize")]
+impl<K, V> deepsize::DeepSizeOf for SplayCache<K, V>
+    where K: Ord + deepsize::DeepSizeOf, V: deepsize::DeepSizeOf
+{
+    /// Doesn't include the captures of the eviction callback.
+    #[inline]
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.tree.deep_size_of_children(context)
+    }
+}
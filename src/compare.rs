@@ -0,0 +1,142 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// Total order on keys used by `SplayTreeBy`.
+///
+/// It's implemented by [`Natural`], which delegates to `Ord`, and by every closure
+/// `Fn(&K, &K) -> Ordering`, so trees can be ordered in descending order,
+/// case-insensitively or by a locale collation without wrapping the keys.
+///
+/// The order must be consistent while the tree is alive: changing it for keys
+/// already stored in the tree doesn't cause undefined behavior, but lookups may
+/// stop finding them.
+pub trait Compare<K: ?Sized> {
+    /// Compares two keys.
+    fn compare(&self, a: &K, b: &K) -> Ordering;
+}
+
+/// Query which can be compared with keys of type `K` without being converted to
+/// one, the ordered counterpart of `hashbrown::Equivalent`.
+///
+/// It's implemented for every `Ord` type `Q` such that `K: Borrow<Q>`, and can be
+/// implemented for query types which aren't borrowed forms of the key, like
+/// a struct of a `u64` and a `&str` looking up `(u64, String)` keys. Queries are
+/// used by the `*_equiv` methods of [`SplayTree`](crate::SplayTree), which orders
+/// keys by `Ord`, so `compare` must agree with `Ord` for `K`.
+pub trait Equivalent<K: ?Sized> {
+    /// Compares the query with a key.
+    fn compare(&self, key: &K) -> Ordering;
+}
+
+impl<Q: Ord + ?Sized, K: Borrow<Q> + ?Sized> Equivalent<K> for Q {
+    #[inline]
+    fn compare(&self, key: &K) -> Ordering {
+        self.cmp(key.borrow())
+    }
+}
+
+/// Comparator ordering keys by their `Ord` implementation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Natural;
+
+impl<K: Ord + ?Sized> Compare<K> for Natural {
+    #[inline]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Comparator ordering keys by a value derived from them, like `slice::sort_by_key`.
+///
+/// Keys with equal derived values are considered equal, so a tree keeps only one of them.
+#[derive(Debug, Clone, Copy)]
+pub struct ByKey<F> {
+    key: F,
+}
+
+impl<F> ByKey<F> {
+    /// Creates a comparator ordering keys by the result of `key`.
+    #[inline]
+    pub fn new(key: F) -> Self {
+        ByKey { key }
+    }
+}
+
+impl<K: ?Sized, P: Ord, F: Fn(&K) -> P> Compare<K> for ByKey<F> {
+    #[inline]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        (self.key)(a).cmp(&(self.key)(b))
+    }
+}
+
+impl<K: ?Sized, F: Fn(&K, &K) -> Ordering> Compare<K> for F {
+    #[inline]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        self(a, b)
+    }
+}
+
+/// Comparator ordering floats by the IEEE 754 `totalOrder` predicate.
+///
+/// Negative zero is less than positive zero, and NaNs are ordered by their sign
+/// and payload at the ends of the order, so every float can be used as a key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TotalOrder;
+
+/// Float wrapper implementing `Ord` by the IEEE 754 `totalOrder` predicate,
+/// for keys of trees using the [`Natural`] comparator and other ordered collections.
+///
+/// Two wrapped floats are equal only if their bits are equal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TotalOrd<T>(pub T);
+
+macro_rules! impl_total_order {
+    ($($float:ty),*) => {$(
+        impl Compare<$float> for TotalOrder {
+            #[inline]
+            fn compare(&self, a: &$float, b: &$float) -> Ordering {
+                a.total_cmp(b)
+            }
+        }
+
+        impl PartialEq for TotalOrd<$float> {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                self.0.to_bits() == other.0.to_bits()
+            }
+        }
+
+        impl Eq for TotalOrd<$float> {}
+
+        impl PartialOrd for TotalOrd<$float> {
+            #[inline]
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for TotalOrd<$float> {
+            #[inline]
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+
+        impl Hash for TotalOrd<$float> {
+            #[inline]
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_bits().hash(state);
+            }
+        }
+
+        impl From<$float> for TotalOrd<$float> {
+            #[inline]
+            fn from(value: $float) -> Self {
+                TotalOrd(value)
+            }
+        }
+    )*};
+}
+
+impl_total_order!(f32, f64);
@@ -0,0 +1,206 @@
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crossbeam_epoch::{self as epoch, Atomic, Guard, Owned};
+
+use crate::PersistentTree;
+
+/// Ordered map with lock-free readers, which can be shared between threads.
+///
+/// The map publishes immutable versions of a [`PersistentTree`]. Readers pin
+/// the current epoch and read the latest version without taking any lock or
+/// writing to shared memory, so they scale with the number of threads. Lookups
+/// don't splay. Writers are serialized by a lock: each one copies the path it
+/// changes into a new version, swaps it in atomically, and the old version is
+/// freed once no reader can still see it.
+pub struct ConcurrentTree<K: Ord, V> {
+    current: Atomic<PersistentTree<K, V>>,
+    writer: Mutex<()>,
+}
+
+impl<K: Ord, V> ConcurrentTree<K, V> {
+    /// Creates an empty `ConcurrentTree`.
+    #[inline]
+    pub fn new() -> Self {
+        ConcurrentTree::from(PersistentTree::new())
+    }
+
+    /// Returns a guard giving shared access to the latest version of the tree.
+    /// Versions published while the guard is alive aren't visible through it.
+    ///
+    /// Holding a guard for a long time delays freeing the versions replaced
+    /// in the meantime.
+    #[inline]
+    pub fn read(&self) -> ReadGuard<'_, K, V> {
+        let guard = epoch::pin();
+        let tree = self.current.load(Ordering::Acquire, &guard).as_raw();
+        ReadGuard { tree, _guard: guard, marker: PhantomData }
+    }
+
+    /// Calls `f` with a reference to the value by a key and returns its result,
+    /// or `None` if the tree doesn't contain that key.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    #[inline]
+    pub fn get_with<R, F: FnOnce(&V) -> R>(&self, key: &K, f: F) -> Option<R> {
+        self.read().get(key).map(f)
+    }
+
+    /// Returns `true` if the tree contains a value for the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.read().contains_key(key)
+    }
+
+    /// Returns the length of the latest version of the tree.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns `true` if the latest version of the tree contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Returns the latest version of the tree, sharing all nodes with it.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn snapshot(&self) -> PersistentTree<K, V> {
+        self.read().clone()
+    }
+
+    /// Consumes the wrapper and returns the latest version of the tree.
+    #[inline]
+    pub fn into_inner(self) -> PersistentTree<K, V> {
+        let mut tree = ManuallyDrop::new(self);
+        // SAFETY: the wrapper is never used again and its destructor doesn't run,
+        // so the latest version is taken and the lock is dropped only once.
+        unsafe {
+            let current = tree.take_current();
+            ptr::drop_in_place(&mut tree.writer);
+            current
+        }
+    }
+
+    /// Takes ownership of the latest version, leaving a dangling pointer behind.
+    ///
+    /// # Safety
+    ///
+    /// The latest version must not be accessed afterwards, so this must be called
+    /// only once, right before the wrapper goes away.
+    unsafe fn take_current(&mut self) -> PersistentTree<K, V> {
+        // SAFETY: `&mut self` means no reader is pinned on this tree, and the
+        // pointer is never null.
+        let current = self.current.load(Ordering::Relaxed, epoch::unprotected());
+        *current.into_owned().into_box()
+    }
+}
+
+// Replaced versions may be freed later by any thread pinning the epoch, even after
+// the tree is dropped, so writing needs entries which can outlive it on any thread.
+impl<K, V> ConcurrentTree<K, V>
+    where K: Ord + Clone + Send + Sync + 'static, V: Clone + Send + Sync + 'static
+{
+    /// Inserts a value to the tree with a key and returns the replaced value, if any.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    #[inline]
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.update(|tree| tree.insert(key, value))
+    }
+
+    /// Removes an entry with a given key and returns its value, or `None` if the tree
+    /// doesn't contain that key.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    #[inline]
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.update(|tree| tree.remove(key))
+    }
+
+    /// Calls `f` with a new version of the tree and publishes it once `f` returns,
+    /// so readers see all changes made by `f` at once or none of them. If `f`
+    /// panics, nothing is published.
+    ///
+    /// The new version shares the nodes which `f` doesn't change with the old one.
+    pub fn update<R, F: FnOnce(&mut PersistentTree<K, V>) -> R>(&self, f: F) -> R {
+        let _writer = self.lock();
+        let guard = epoch::pin();
+        let current = self.current.load(Ordering::Acquire, &guard);
+        // SAFETY: the pointer is never null, and versions are only freed by writers,
+        // which are serialized by the lock.
+        let mut tree = unsafe { current.deref() }.clone();
+        let result = f(&mut tree);
+
+        self.current.store(Owned::new(tree), Ordering::Release);
+        // SAFETY: the old version is unreachable for new readers, and the current
+        // ones are pinned, so it's freed after all of them are gone.
+        unsafe { guard.defer_destroy(current) };
+        result
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        // The lock guards no data, and a panicking writer publishes nothing,
+        // so poisoning is ignored.
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K: Ord, V: Clone> ConcurrentTree<K, V> {
+    /// Returns a clone of the value by a key, or `None` if the tree doesn't
+    /// contain that key.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_with(key, V::clone)
+    }
+}
+
+impl<K: Ord, V> From<PersistentTree<K, V>> for ConcurrentTree<K, V> {
+    #[inline]
+    fn from(tree: PersistentTree<K, V>) -> Self {
+        ConcurrentTree { current: Atomic::new(tree), writer: Mutex::new(()) }
+    }
+}
+
+impl<K: Ord, V> Default for ConcurrentTree<K, V> {
+    #[inline]
+    fn default() -> Self {
+        ConcurrentTree::new()
+    }
+}
+
+impl<K: Ord, V> Drop for ConcurrentTree<K, V> {
+    #[inline]
+    fn drop(&mut self) {
+        drop(unsafe { self.take_current() });
+    }
+}
+
+/// Shared access to a version of a `ConcurrentTree`, returned by
+/// [`ConcurrentTree::read`]. The version stays alive while the guard does.
+pub struct ReadGuard<'a, K: Ord, V> {
+    tree: *const PersistentTree<K, V>,
+    _guard: Guard,
+    marker: PhantomData<&'a PersistentTree<K, V>>,
+}
+
+impl<'a, K: Ord, V> Deref for ReadGuard<'a, K, V> {
+    type Target = PersistentTree<K, V>;
+
+    #[inline]
+    fn deref(&self) -> &PersistentTree<K, V> {
+        // SAFETY: the version was loaded under the pinned guard, so it isn't
+        // freed before the guard is dropped.
+        unsafe { &*self.tree }
+    }
+}
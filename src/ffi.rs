@@ -0,0 +1,127 @@
+//! C interface to a `SplayTree` with `int64_t` keys and `void *` values.
+//!
+//! A tree is an opaque `SplayTreeHandle` created by [`splay_tree_new`] and freed by
+//! [`splay_tree_free`]. Values are pointers owned by the caller: the tree never
+//! dereferences or frees them. Functions which return a value write it through
+//! an out pointer, which may be null if the value isn't needed, and return whether
+//! the key was found, so null values can be stored too.
+//!
+//! The declarations can be generated with `cbindgen`. A handle must not be used
+//! from several threads at once, since lookups mutate the tree.
+
+use std::ffi::c_void;
+use std::mem;
+
+use crate::SplayTree;
+
+/// Opaque handle of a tree, only used through pointers.
+pub struct SplayTreeHandle {
+    tree: SplayTree<i64, *mut c_void>,
+}
+
+/// Creates an empty tree. The handle must be freed with [`splay_tree_free`].
+#[no_mangle]
+pub extern "C" fn splay_tree_new() -> *mut SplayTreeHandle {
+    Box::into_raw(Box::new(SplayTreeHandle { tree: SplayTree::new() }))
+}
+
+/// Frees a tree. Stored values aren't freed. Does nothing if `tree` is null.
+///
+/// # Safety
+///
+/// `tree` must be null or a handle returned by [`splay_tree_new`], which isn't
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn splay_tree_free(tree: *mut SplayTreeHandle) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Inserts a value with a key. Returns `true` and writes the replaced value to
+/// `old` if the key was already in the tree.
+///
+/// # Safety
+///
+/// `tree` must be a live handle returned by [`splay_tree_new`], and `old` must be
+/// null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn splay_tree_insert(
+    tree: *mut SplayTreeHandle,
+    key: i64,
+    value: *mut c_void,
+    old: *mut *mut c_void,
+) -> bool {
+    let tree = &mut (*tree).tree;
+    match tree.get_mut(&key) {
+        Some(mut node) => {
+            write_out(old, mem::replace(node.value_mut(), value));
+            true
+        },
+        None => {
+            tree.insert(key, value);
+            false
+        },
+    }
+}
+
+/// Looks up a value by a key. Returns `true` and writes the value to `value`
+/// if the key is in the tree.
+///
+/// # Safety
+///
+/// `tree` must be a live handle returned by [`splay_tree_new`], and `value` must
+/// be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn splay_tree_get(
+    tree: *mut SplayTreeHandle,
+    key: i64,
+    value: *mut *mut c_void,
+) -> bool {
+    match (*tree).tree.get(&key) {
+        Some(node) => {
+            write_out(value, *node.value());
+            true
+        },
+        None => false,
+    }
+}
+
+/// Removes an entry by a key. Returns `true` and writes the removed value to
+/// `value` if the key was in the tree.
+///
+/// # Safety
+///
+/// `tree` must be a live handle returned by [`splay_tree_new`], and `value` must
+/// be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn splay_tree_remove(
+    tree: *mut SplayTreeHandle,
+    key: i64,
+    value: *mut *mut c_void,
+) -> bool {
+    match (*tree).tree.remove(&key) {
+        Some(node) => {
+            write_out(value, node.value);
+            true
+        },
+        None => false,
+    }
+}
+
+/// Returns the number of entries in a tree.
+///
+/// # Safety
+///
+/// `tree` must be a live handle returned by [`splay_tree_new`].
+#[no_mangle]
+pub unsafe extern "C" fn splay_tree_len(tree: *const SplayTreeHandle) -> usize {
+    (*tree).tree.len()
+}
+
+#[inline]
+unsafe fn write_out(out: *mut *mut c_void, value: *mut c_void) {
+    if !out.is_null() {
+        *out = value;
+    }
+}
@@ -0,0 +1,155 @@
+use std::iter::FusedIterator;
+use std::mem;
+use std::slice;
+
+use crate::{SplayTree, Natural};
+
+/// Immutable snapshot of a `SplayTree`.
+///
+/// Entries are stored in a sorted array, so lookups don't mutate anything and take
+/// a shared reference. The snapshot is `Send` and `Sync` whenever keys and values are,
+/// which makes it suitable for multi-reader phases. It can be turned back into a
+/// `SplayTree` with [`FrozenTree::thaw`].
+pub struct FrozenTree<K: Ord, V> {
+    entries: Vec<(K, V)>,
+}
+
+impl<K: Ord, V> FrozenTree<K, V> {
+    /// Returns a reference to the value by a key,
+    /// or `None` if the snapshot doesn't contain that key.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries
+            .binary_search_by(|(k, _)| k.cmp(key))
+            .ok()
+            .map(|idx| &self.entries[idx].1)
+    }
+
+    /// Returns `true` if the snapshot contains a value for the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the entry with a minimum key, or `None` if the snapshot is empty.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn get_min(&self) -> Option<(&K, &V)> {
+        self.entries.first().map(|(k, v)| (k, v))
+    }
+
+    /// Returns the entry with a maximum key, or `None` if the snapshot is empty.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn get_max(&self) -> Option<(&K, &V)> {
+        self.entries.last().map(|(k, v)| (k, v))
+    }
+
+    /// Returns an iterator over the entries of the snapshot, sorted by key.
+    #[inline]
+    pub fn iter(&self) -> FrozenIter<'_, K, V> {
+        FrozenIter { inner: self.entries.iter() }
+    }
+
+    /// Returns the entries of the snapshot as a slice sorted by key.
+    #[inline]
+    pub fn as_slice(&self) -> &[(K, V)] {
+        &self.entries
+    }
+
+    /// Returns an approximate number of bytes of heap memory held by the snapshot,
+    /// including spare capacity. Heap memory owned by keys and values themselves
+    /// isn't counted.
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.entries.capacity() * mem::size_of::<(K, V)>()
+    }
+
+    /// Returns the length of the snapshot.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the snapshot contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Converts the snapshot back into a balanced `SplayTree`.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    #[inline]
+    pub fn thaw(self) -> SplayTree<K, V> {
+        SplayTree::from_sorted_iter(self.entries.into_iter(), Natural)
+    }
+}
+
+impl<K: Ord, V> From<SplayTree<K, V>> for FrozenTree<K, V> {
+    #[inline]
+    fn from(tree: SplayTree<K, V>) -> Self {
+        FrozenTree { entries: tree.into_iter().collect() }
+    }
+}
+
+impl<K: Ord, V> From<FrozenTree<K, V>> for SplayTree<K, V> {
+    #[inline]
+    fn from(frozen: FrozenTree<K, V>) -> Self {
+        frozen.thaw()
+    }
+}
+
+#[cfg(feature = "deepsize")]
+impl<K, V> deepsize::DeepSizeOf for FrozenTree<K, V>
+    where K: Ord + deepsize::DeepSizeOf, V: deepsize::DeepSizeOf
+{
+    #[inline]
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.entries.deep_size_of_children(context)
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a FrozenTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = FrozenIter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of a `FrozenTree`, sorted by key.
+pub struct FrozenIter<'a, K, V> {
+    inner: slice::Iter<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for FrozenIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (k, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for FrozenIter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for FrozenIter<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for FrozenIter<'a, K, V> {}
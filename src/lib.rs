@@ -1,5 +1,55 @@
 pub mod node;
+pub mod compare;
 pub mod tree;
+pub mod cache;
+pub mod map;
+pub mod bimap;
+pub mod tombstone;
+pub mod small;
+pub mod persistent;
+pub mod frozen;
+pub mod sync;
+pub mod sharded;
+pub mod observer;
+#[cfg(feature = "rayon")]
+pub mod par;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "shadow")]
+pub mod shadow;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(feature = "concurrent")]
+pub mod concurrent;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use crate::node::*;
+pub use crate::compare::*;
 pub use crate::tree::*;
+pub use crate::cache::*;
+pub use crate::map::*;
+pub use crate::bimap::*;
+pub use crate::tombstone::*;
+pub use crate::small::*;
+pub use crate::persistent::*;
+pub use crate::frozen::*;
+pub use crate::sync::*;
+pub use crate::sharded::*;
+pub use crate::observer::*;
+#[cfg(feature = "rayon")]
+pub use crate::par::*;
+#[cfg(feature = "stats")]
+pub use crate::stats::*;
+#[cfg(feature = "proptest")]
+pub use crate::strategy::*;
+#[cfg(feature = "shadow")]
+pub use crate::shadow::*;
+#[cfg(feature = "async")]
+pub use crate::stream::*;
+#[cfg(feature = "concurrent")]
+pub use crate::concurrent::*;
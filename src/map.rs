@@ -0,0 +1,311 @@
+use std::borrow::Borrow;
+use std::iter::FusedIterator;
+
+use crate::{Compare, IntoIter, Iter, Natural, NodeMut, SplayTreeBy};
+
+/// Ordered map with the value-centric API of `BTreeMap`, built on top of `SplayTreeBy`.
+///
+/// Methods take and return keys and values instead of nodes, so the map can
+/// replace a `BTreeMap` with few changes. Unlike `BTreeMap`, lookups splay the
+/// tree and take `&mut self`. The underlying tree is available through
+/// [`SplayMap::as_tree`] and [`SplayMap::as_tree_mut`].
+pub struct SplayMap<K, V, C = Natural> {
+    tree: SplayTreeBy<K, V, C>,
+}
+
+impl<K, V, C: Compare<K> + Default> SplayMap<K, V, C> {
+    /// Creates an empty `SplayMap`.
+    #[inline]
+    pub fn new() -> Self {
+        SplayMap { tree: SplayTreeBy::new() }
+    }
+}
+
+impl<K, V, C: Compare<K>> SplayMap<K, V, C> {
+    /// Creates an empty `SplayMap` ordering keys by `cmp`.
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        SplayMap { tree: SplayTreeBy::with_comparator(cmp) }
+    }
+
+    /// Returns a reference to the value by a key, or `None` if the map doesn't
+    /// contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.tree.get(key).map(|node| node.value())
+    }
+
+    /// Returns a mutable reference to the value by a key, or `None` if the map
+    /// doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.tree.get_mut(key).map(NodeMut::into_value_mut)
+    }
+
+    /// Returns the key and the value by a key, or `None` if the map doesn't
+    /// contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_key_value<Q>(&mut self, key: &Q) -> Option<(&K, &V)>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.tree.get(key).map(|node| node.key_value())
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn contains_key<Q>(&mut self, key: &Q) -> bool
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.tree.contains_key(key)
+    }
+
+    /// Inserts a value to the map with a key and returns the replaced value, if any.
+    /// The key isn't updated if it's already in the map.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.tree.entry(key) {
+            crate::Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            crate::Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            },
+        }
+    }
+
+    /// Removes an entry with a given key and returns its value, or `None` if
+    /// the map doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes an entry with a given key and returns it, or `None` if the map
+    /// doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.tree.remove(key).map(|node| node.into_key_value())
+    }
+
+    /// Returns the entry with a minimum key, or `None` if the map is empty.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn first_key_value(&mut self) -> Option<(&K, &V)> {
+        self.tree.get_min().map(|node| node.key_value())
+    }
+
+    /// Returns the entry with a maximum key, or `None` if the map is empty.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn last_key_value(&mut self) -> Option<(&K, &V)> {
+        self.tree.get_max().map(|node| node.key_value())
+    }
+
+    /// Removes the entry with a minimum key and returns it, or `None` if the map is empty.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        self.tree.get_min()?;
+        self.tree.remove_root().map(|node| node.into_key_value())
+    }
+
+    /// Removes the entry with a maximum key and returns it, or `None` if the map is empty.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        self.tree.get_max()?;
+        self.tree.remove_root().map(|node| node.into_key_value())
+    }
+
+    /// Returns the number of entries in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Gets an iterator over the entries of the map, sorted by key.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.tree.iter()
+    }
+
+    /// Gets an iterator over the keys of the map, in sorted order.
+    #[inline]
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.tree.iter() }
+    }
+
+    /// Gets an iterator over the values of the map, in order by key.
+    #[inline]
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.tree.iter() }
+    }
+}
+
+impl<K, V, C> SplayMap<K, V, C> {
+    /// Removes all entries from the map.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    /// Returns a reference to the underlying tree.
+    #[inline]
+    pub fn as_tree(&self) -> &SplayTreeBy<K, V, C> {
+        &self.tree
+    }
+
+    /// Returns a mutable reference to the underlying tree.
+    #[inline]
+    pub fn as_tree_mut(&mut self) -> &mut SplayTreeBy<K, V, C> {
+        &mut self.tree
+    }
+
+    /// Consumes the map, returning the underlying tree.
+    #[inline]
+    pub fn into_tree(self) -> SplayTreeBy<K, V, C> {
+        self.tree
+    }
+}
+
+impl<K, V, C: Compare<K> + Default> Default for SplayMap<K, V, C> {
+    #[inline]
+    fn default() -> Self {
+        SplayMap::new()
+    }
+}
+
+impl<K, V, C> From<SplayTreeBy<K, V, C>> for SplayMap<K, V, C> {
+    #[inline]
+    fn from(tree: SplayTreeBy<K, V, C>) -> Self {
+        SplayMap { tree }
+    }
+}
+
+impl<K, V, C: Compare<K>> Extend<(K, V)> for SplayMap<K, V, C> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, C: Compare<K> + Default> FromIterator<(K, V)> for SplayMap<K, V, C> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = SplayMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, K, V, C: Compare<K>> IntoIterator for &'a SplayMap<K, V, C> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<K, V, C> IntoIterator for SplayMap<K, V, C> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    #[inline]
+    fn into_iter(self) -> IntoIter<K, V> {
+        self.tree.into_iter()
+    }
+}
+
+/// An iterator over the keys of a `SplayMap`, in sorted order.
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Keys<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for Keys<'a, K, V> {}
+
+/// An iterator over the values of a `SplayMap`, in order by key.
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a V> {
+        self.inner.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Values<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for Values<'a, K, V> {}
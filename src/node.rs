@@ -1,83 +1,127 @@
-#[cfg(feature = "recursive_debug")]
 use std::fmt;
 
-use std::mem;
+use std::ptr;
 use std::fmt::Debug;
+use std::ops::Deref;
 use std::ptr::NonNull;
-use std::cmp::Ordering;
+use std::marker::PhantomData;
 
 pub(crate) type NodePtr<K, V> = Option<NonNull<Node<K, V>>>;
 
 /// Splay tree's node.
 #[cfg_attr(not(feature = "recursive_debug"), derive(Debug))]
-pub struct Node<K: Ord, V> {
-    key: K,
-    value: V,
+pub struct Node<K, V> {
+    pub(crate) key: K,
+    /// Value of the entry, stored inline.
+    pub(crate) value: V,
     pub(crate) left: NodePtr<K, V>,
     pub(crate) right: NodePtr<K, V>,
+    /// Link to the parent node, or `None` at the root.
     pub(crate) parent: NodePtr<K, V>,
+    /// Number of nodes in the subtree at this node, including itself.
+    pub(crate) size: usize,
+    /// Previous node in the order of insertion.
+    #[cfg(feature = "insertion_order")]
+    pub(crate) older: NodePtr<K, V>,
+    /// Next node in the order of insertion.
+    #[cfg(feature = "insertion_order")]
+    pub(crate) newer: NodePtr<K, V>,
+    /// Sequence number of the insertion, increasing in the order of insertion.
+    #[cfg(feature = "insertion_order")]
+    pub(crate) seq: u64,
+    /// Number of searches which have found the node.
+    #[cfg(feature = "stats")]
+    pub(crate) hits: u64,
 }
 
-#[cfg(feature = "recursive_debug")]
-impl<K: Ord + Debug, V: Debug> Debug for Node<K, V> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        self.print_tree_prefix(f, String::from(""), String::from(""))
-    }
-}
+// SAFETY: a node is either owned by a tree, which is `Send` under the same bounds,
+// or detached, in which case its links are cleared and it's owned by its box.
+unsafe impl<K: Send, V: Send> Send for Node<K, V> {}
+
+// SAFETY: `&Node` only gives out shared references to itself and its neighbours.
+unsafe impl<K: Sync, V: Sync> Sync for Node<K, V> {}
 
 #[cfg(feature = "recursive_debug")]
-impl<K: Ord + Debug, V: Debug> Node<K, V> {
-    fn print_tree_prefix(
-        &self,
-        f: &mut fmt::Formatter<'_>,
-        prefix: String,
-        c_prefix: String
-    ) -> Result<(), fmt::Error> {
-        f.write_fmt(format_args!("{}{:?}({:?})\n", prefix, self.key, self.value))?;
-        let right_c_prefix;
-
-        if let Some(left) = self.left() {
-            right_c_prefix = c_prefix.clone();
-            let (new_prefix, new_c_prefix) = if self.right.is_some() {
-                (c_prefix.clone() + "├── ", c_prefix + "│   ")
-            } else {
-                (c_prefix.clone() + "└── ", c_prefix + "    ")
-            };
-            left.print_tree_prefix(f, new_prefix, new_c_prefix)?;
-        } else {
-            right_c_prefix = c_prefix;
-        }
+impl<K: Debug, V: Debug> Debug for Node<K, V> {
+    /// Prints the subtree at the node, one node per line.
+    ///
+    /// The traversal uses an explicit stack and a single prefix buffer which is
+    /// truncated back to the parent's prefix, so deep trees don't overflow the
+    /// call stack.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let mut prefix = String::new();
+        let mut stack = vec![PrintFrame {
+            node: self,
+            prefix_len: 0,
+            branch: "",
+            indent: "",
+        }];
+
+        while let Some(frame) = stack.pop() {
+            prefix.truncate(frame.prefix_len);
+            f.write_fmt(format_args!(
+                "{}{}{:?}({:?})\n",
+                prefix, frame.branch, frame.node.key, frame.node.value
+            ))?;
+            prefix.push_str(frame.indent);
+
+            if let Some(right) = frame.node.right() {
+                stack.push(PrintFrame {
+                    node: right,
+                    prefix_len: prefix.len(),
+                    branch: "└── ",
+                    indent: "    ",
+                });
+            }
 
-        if let Some(right) = self.right() {
-            let new_prefix = right_c_prefix.clone() + "└── ";
-            let new_c_prefix = right_c_prefix + "    ";
-            right.print_tree_prefix(f, new_prefix, new_c_prefix)?;
+            if let Some(left) = frame.node.left() {
+                let (branch, indent) = if frame.node.right.is_some() {
+                    ("├── ", "│   ")
+                } else {
+                    ("└── ", "    ")
+                };
+                stack.push(PrintFrame {
+                    node: left,
+                    prefix_len: prefix.len(),
+                    branch,
+                    indent,
+                });
+            }
         }
 
         Ok(())
     }
 }
 
-impl<K: Ord, V> Node<K, V> {
+#[cfg(feature = "recursive_debug")]
+struct PrintFrame<'a, K, V> {
+    node: &'a Node<K, V>,
+    prefix_len: usize,
+    branch: &'static str,
+    indent: &'static str,
+}
+
+impl<K, V> Node<K, V> {
     #[inline]
     pub(crate) fn new(key: K, value: V) -> Self {
         Node {
-            key: key,
-            value: value,
+            key,
+            value,
             left: None,
             right: None,
             parent: None,
+            size: 1,
+            #[cfg(feature = "insertion_order")]
+            older: None,
+            #[cfg(feature = "insertion_order")]
+            newer: None,
+            #[cfg(feature = "insertion_order")]
+            seq: 0,
+            #[cfg(feature = "stats")]
+            hits: 0,
         }
     }
 
-    /// Returns a mutable reference to the node's parent, or `None` if the node is a root.
-    ///
-    /// This operation should compute in *O*(1) time.
-    #[inline]
-    pub fn parent_mut(&mut self) -> Option<&mut Self> {
-        self.parent.map(|mut p| unsafe { p.as_mut() })
-    }
-
     /// Returns a reference to the node's parent, or `None` if the node is a root.
     ///
     /// This operation should compute in *O*(1) time.
@@ -86,40 +130,53 @@ impl<K: Ord, V> Node<K, V> {
         self.parent.map(|p| unsafe { p.as_ref() })
     }
 
-    /// Returns a mutable reference to the node's left child, or `None`
+    /// Returns a reference to the node's left child, or `None`
     /// if the node doesn't have one.
     ///
     /// This operation should compute in *O*(1) time.
     #[inline]
-    pub fn left_mut(&mut self) -> Option<&mut Self> {
-        self.left.map(|mut l| unsafe { l.as_mut() })
+    pub fn left(&self) -> Option<&Self> {
+        self.left.map(|l| unsafe { l.as_ref() })
     }
 
-    /// Returns a reference to the node's left child, or `None`
+    /// Returns a reference to the node's right child, or `None`
     /// if the node doesn't have one.
     ///
     /// This operation should compute in *O*(1) time.
     #[inline]
-    pub fn left(&self) -> Option<&Self> {
-        self.left.map(|l| unsafe { l.as_ref() })
+    pub fn right(&self) -> Option<&Self> {
+        self.right.map(|r| unsafe { r.as_ref() })
     }
 
-    /// Returns a mutable reference to the node's right child, or `None`
-    /// if the node doesn't have one.
+    /// Returns a reference to the node with the next key in the tree,
+    /// or `None` if the node has a maximum key.
     ///
-    /// This operation should compute in *O*(1) time.
+    /// The tree isn't splayed. This operation should compute in *O*(1) amortized
+    /// time when walking through the whole tree, and in *O*(*h*) time in the worst case,
+    /// where *h* is the height of the tree.
     #[inline]
-    pub fn right_mut(&mut self) -> Option<&mut Self> {
-        self.right.map(|mut r| unsafe { r.as_mut() })
+    pub fn next(&self) -> Option<&Self> {
+        unsafe { Node::successor(NonNull::from(self)).map(|n| n.as_ref()) }
     }
 
-    /// Returns a reference to the node's right child, or `None`
-    /// if the node doesn't have one.
+    /// Returns a reference to the node with the previous key in the tree,
+    /// or `None` if the node has a minimum key.
+    ///
+    /// The tree isn't splayed. This operation should compute in *O*(1) amortized
+    /// time when walking through the whole tree, and in *O*(*h*) time in the worst case,
+    /// where *h* is the height of the tree.
+    #[inline]
+    pub fn prev(&self) -> Option<&Self> {
+        unsafe { Node::predecessor(NonNull::from(self)).map(|p| p.as_ref()) }
+    }
+
+    /// Returns a reference to the other child of the node's parent,
+    /// or `None` if the node is a root or an only child.
     ///
     /// This operation should compute in *O*(1) time.
     #[inline]
-    pub fn right(&self) -> Option<&Self> {
-        self.right.map(|r| unsafe { r.as_ref() })
+    pub fn sibling(&self) -> Option<&Self> {
+        unsafe { Node::sibling_ptr(NonNull::from(self)).map(|s| s.as_ref()) }
     }
 
     /// Returns a reference to the node's key.
@@ -140,6 +197,40 @@ impl<K: Ord, V> Node<K, V> {
         &self.value
     }
 
+    /// Returns references to the node's key and value.
+    #[inline]
+    pub fn key_value(&self) -> (&K, &V) {
+        (&self.key, &self.value)
+    }
+
+    /// Returns a reference to the node's key and a mutable reference to its value.
+    #[inline]
+    pub fn key_value_mut(&mut self) -> (&K, &mut V) {
+        (&self.key, &mut self.value)
+    }
+
+    /// Returns the entry of another node of the same tree. Only the value is
+    /// borrowed mutably, so no link of that node can be reached through it.
+    #[inline]
+    fn key_value_mut_at<'a>(ptr: NodePtr<K, V>) -> Option<(&'a K, &'a mut V)> {
+        let node = ptr?.as_ptr();
+        Some(unsafe { (&(*node).key, &mut (*node).value) })
+    }
+
+    /// Consumes a node removed from a tree and returns its key and value.
+    #[inline]
+    pub fn into_key_value(self) -> (K, V) {
+        (self.key, self.value)
+    }
+
+    /// Returns the number of searches which have found the node since it was
+    /// inserted, counting lookups, updates and occupied entries alike.
+    #[cfg(feature = "stats")]
+    #[inline]
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
     /// Returns `true` if the node is a root.
     #[inline]
     pub fn is_root(&self) -> bool {
@@ -149,255 +240,427 @@ impl<K: Ord, V> Node<K, V> {
     /// Returns `true` if the node is a left child.
     #[inline]
     pub fn is_left(&self) -> bool {
-        if let Some(parent) = self.parent() {
-            parent.left.map(|l| unsafe {
-                mem::transmute::<*mut Self, *const Self>(l.as_ptr()) == self
-            }).unwrap_or(false)
-        } else {
-            false
-        }
+        self.parent()
+            .and_then(|parent| parent.left)
+            .map(|l| ptr::eq(l.as_ptr(), self))
+            .unwrap_or(false)
     }
 
     /// Returns `true` if the node is a right child.
     #[inline]
     pub fn is_right(&self) -> bool {
-        if let Some(parent) = self.parent() {
-            parent.right.map(|l| unsafe {
-                mem::transmute::<*mut Self, *const Self>(l.as_ptr()) == self
-            }).unwrap_or(false)
-        } else {
-            false
-        }
+        self.parent()
+            .and_then(|parent| parent.right)
+            .map(|r| ptr::eq(r.as_ptr(), self))
+            .unwrap_or(false)
     }
 
+    /// Returns `true` if the node has no children.
     #[inline]
-    pub(crate) unsafe fn ref_into_box(&mut self) -> Box<Self> {
-        Box::from_raw(self)
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
     }
 
+    /// Returns the number of nodes in the subtree at the node, including itself.
+    ///
+    /// This operation should compute in *O*(1) time.
     #[inline]
-    pub(crate) fn insert_child<'a>(
-        &'a mut self,
-        key: K,
-        value: V
-    ) -> Option<&'a mut Self> {
-        match key.cmp(&self.key) {
-            Ordering::Less if self.left.is_none() => {
-                let node = Node {
-                    key: key,
-                    value: value,
-                    parent: Some(self.into()),
-                    left: None,
-                    right: None,
-                };
-                let node_ptr = Box::leak(Box::new(node)).into();
-                self.left = Some(node_ptr);
-                self.left_mut()
-            },
-            Ordering::Equal => {
-                self.value = value;
-                Some(self)
-            },
-            Ordering::Greater if self.right.is_none() => {
-                let node = Node {
-                    key: key,
-                    value: value,
-                    parent: Some(self.into()),
-                    left: None,
-                    right: None,
-                };
-                let node_ptr = Box::leak(Box::new(node)).into();
-                self.right = Some(node_ptr);
-                self.right_mut()
-            },
-            _ => None,
-        }
+    pub fn subtree_len(&self) -> usize {
+        self.size
     }
+}
+
+/// Mutable access to a node of a tree, returned by lookups and insertions.
+///
+/// It dereferences to the node for reading, but only gives out mutable
+/// references to values: the links and the subtree size of a node in a tree
+/// can't be overwritten, e.g. swapped with the ones of a node of another tree.
+///
+/// ```compile_fail
+/// use splay_tree::SplayTree;
+///
+/// let mut a = SplayTree::new();
+/// let mut b = SplayTree::new();
+/// a.insert(10, "a");
+/// b.insert(20, "b");
+/// std::mem::swap(&mut *a.root_mut().unwrap(), &mut *b.root_mut().unwrap());
+/// ```
+pub struct NodeMut<'a, K, V> {
+    ptr: NonNull<Node<K, V>>,
+    marker: PhantomData<&'a mut Node<K, V>>,
+}
 
+// SAFETY: the wrapper gives out shared references to the nodes of the tree
+// and a mutable reference to the value of its node only.
+unsafe impl<'a, K: Sync, V: Send + Sync> Send for NodeMut<'a, K, V> {}
+
+// SAFETY: `&NodeMut` only gives out shared references to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for NodeMut<'a, K, V> {}
+
+impl<'a, K, V> NodeMut<'a, K, V> {
+    /// Wraps the node at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a node of a tree which is mutably borrowed for `'a`.
     #[inline]
-    pub(crate) fn splay(&mut self) -> NodePtr<K, V> {
-        loop {
-            if let Some(new_root) = self.splay_step() {
-                return Some(new_root)
-            }
-        }
+    pub(crate) unsafe fn new(ptr: NonNull<Node<K, V>>) -> Self {
+        NodeMut { ptr, marker: PhantomData }
     }
 
-    fn splay_step(&mut self) -> NodePtr<K, V> {
-        let self_ptr = self.into();
-        let is_left = self.is_left();
+    /// Returns a mutable reference to the node's value.
+    #[inline]
+    pub fn value_mut(&mut self) -> &mut V {
+        unsafe { &mut (*self.ptr.as_ptr()).value }
+    }
 
-        if let Some(splay_type) = self.splay_type() {
-            match splay_type {
-                SplayType::Zig => {
-                    self.parent_mut().map(|p|
-                        if is_left {
-                            p.rotate_right();
-                        } else {
-                            p.rotate_left();
-                        }
-                    );
-                },
-                SplayType::ZigZig => {
-                    self.parent_mut().map(|p|
-                        if is_left {
-                            p.parent_mut().map(|g| g.rotate_right());
-                            p.rotate_right();
-                        } else {
-                            p.parent_mut().map(|g| g.rotate_left());
-                            p.rotate_left();
-                        }
-                    );
-                },
-                SplayType::ZigZag => {
-                    if is_left {
-                        self.parent_mut().map(|p| p.rotate_right());
-                        self.parent_mut().map(|g| g.rotate_left());
-                    } else {
-                        self.parent_mut().map(|p| p.rotate_left());
-                        self.parent_mut().map(|g| g.rotate_right());
-                    }
-                },
-            }
+    /// Returns a reference to the node's key and a mutable reference to its value.
+    #[inline]
+    pub fn key_value_mut(&mut self) -> (&K, &mut V) {
+        let node = self.ptr.as_ptr();
+        unsafe { (&(*node).key, &mut (*node).value) }
+    }
+
+    /// Converts the wrapper into a mutable reference to the node's value,
+    /// which lives as long as the borrow of the tree.
+    #[inline]
+    pub fn into_value_mut(self) -> &'a mut V {
+        unsafe { &mut (*self.ptr.as_ptr()).value }
+    }
+
+    /// Converts the wrapper into a reference to the node's key and a mutable
+    /// reference to its value, which live as long as the borrow of the tree.
+    #[inline]
+    pub fn into_key_value_mut(self) -> (&'a K, &'a mut V) {
+        let node = self.ptr.as_ptr();
+        unsafe { (&(*node).key, &mut (*node).value) }
+    }
+
+    /// Returns a reference to the key and a mutable reference to the value of the
+    /// node's parent, or `None` if the node is a root.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn parent_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        Node::key_value_mut_at(self.parent)
+    }
+
+    /// Returns a reference to the key and a mutable reference to the value of the
+    /// node's left child, or `None` if the node doesn't have one.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn left_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        Node::key_value_mut_at(self.left)
+    }
+
+    /// Returns a reference to the key and a mutable reference to the value of the
+    /// node's right child, or `None` if the node doesn't have one.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn right_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        Node::key_value_mut_at(self.right)
+    }
+
+    /// Returns a reference to the key and a mutable reference to the value of the
+    /// other child of the node's parent, or `None` if there is no such node.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn sibling_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        Node::key_value_mut_at(unsafe { Node::sibling_ptr(self.ptr) })
+    }
+
+    /// Returns a reference to the key and a mutable reference to the value of the
+    /// node with the next key in the tree, or `None` if the node has a maximum key.
+    ///
+    /// The tree isn't splayed. This operation should compute in *O*(*h*) time,
+    /// where *h* is the height of the tree.
+    #[inline]
+    pub fn next_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        Node::key_value_mut_at(unsafe { Node::successor(self.ptr) })
+    }
+
+    /// Returns a reference to the key and a mutable reference to the value of the
+    /// node with the previous key in the tree, or `None` if the node has a minimum key.
+    ///
+    /// The tree isn't splayed. This operation should compute in *O*(*h*) time,
+    /// where *h* is the height of the tree.
+    #[inline]
+    pub fn prev_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        Node::key_value_mut_at(unsafe { Node::predecessor(self.ptr) })
+    }
+}
+
+impl<'a, K, V> Deref for NodeMut<'a, K, V> {
+    type Target = Node<K, V>;
+
+    #[inline]
+    fn deref(&self) -> &Node<K, V> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<'a, K: Debug, V: Debug> Debug for NodeMut<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+// Structural operations work on raw pointers only. Every stored link is a copy of
+// the pointer returned by `Node::alloc`, and no reference to a node is held while
+// its links are rewritten, so the tree is sound under Stacked and Tree Borrows.
+impl<K, V> Node<K, V> {
+    /// Allocates a detached node on the heap.
+    #[inline]
+    pub(crate) fn alloc(key: K, value: V) -> NonNull<Self> {
+        let node = Box::into_raw(Box::new(Node::new(key, value)));
+        unsafe { NonNull::new_unchecked(node) }
+    }
+
+    /// Returns `true` if the node at `ptr` is a left child.
+    #[inline]
+    pub(crate) unsafe fn is_left_ptr(ptr: NonNull<Self>) -> bool {
+        match (*ptr.as_ptr()).parent {
+            Some(parent) => (*parent.as_ptr()).left == Some(ptr),
+            None => false,
         }
+    }
 
-        if self.is_root() {
-            Some(self_ptr)
+    /// Returns a pointer to the other child of the parent of the node at `ptr`.
+    #[inline]
+    pub(crate) unsafe fn sibling_ptr(ptr: NonNull<Self>) -> NodePtr<K, V> {
+        let parent = (*ptr.as_ptr()).parent?;
+        if (*parent.as_ptr()).left == Some(ptr) {
+            (*parent.as_ptr()).right
         } else {
-            None
+            (*parent.as_ptr()).left
         }
     }
 
+    /// Returns a pointer to the leftmost node of the subtree at `ptr`.
     #[inline]
-    pub(crate) fn merge(&mut self, right: &mut Self) -> NodePtr<K, V> {
-        let left_max = self.find_max();
-        let res = left_max.splay();
-        right.parent = res;
-        left_max.right = Some(right.into());
-        res
+    pub(crate) unsafe fn leftmost(mut ptr: NonNull<Self>) -> NonNull<Self> {
+        while let Some(left) = (*ptr.as_ptr()).left {
+            ptr = left;
+        }
+        ptr
     }
 
+    /// Returns a pointer to the rightmost node of the subtree at `ptr`.
     #[inline]
-    pub(crate) fn find_max(&mut self) -> &mut Self {
-        let mut cur_node = self;
+    pub(crate) unsafe fn rightmost(mut ptr: NonNull<Self>) -> NonNull<Self> {
+        while let Some(right) = (*ptr.as_ptr()).right {
+            ptr = right;
+        }
+        ptr
+    }
+
+    /// Returns a pointer to the in-order successor of the node at `ptr`.
+    pub(crate) unsafe fn successor(ptr: NonNull<Self>) -> NodePtr<K, V> {
+        if let Some(right) = (*ptr.as_ptr()).right {
+            return Some(Self::leftmost(right));
+        }
 
+        let mut cur_ptr = ptr;
         loop {
-            let ptr: *const Self = cur_node;
-            cur_node = if let Some(next) = cur_node.right_mut() {
-                next
-            } else {
-                break unsafe {
-                    &mut *mem::transmute::<*const Self, *mut Self>(ptr)
-                };
+            let parent = (*cur_ptr.as_ptr()).parent?;
+            if (*parent.as_ptr()).left == Some(cur_ptr) {
+                return Some(parent);
             }
+            cur_ptr = parent;
         }
     }
 
-    #[inline]
-    pub(crate) fn find_min(&mut self) -> &mut Self {
-        let mut cur_node = self;
+    /// Returns a pointer to the in-order predecessor of the node at `ptr`.
+    pub(crate) unsafe fn predecessor(ptr: NonNull<Self>) -> NodePtr<K, V> {
+        if let Some(left) = (*ptr.as_ptr()).left {
+            return Some(Self::rightmost(left));
+        }
 
+        let mut cur_ptr = ptr;
         loop {
-            let ptr: *const Self = cur_node;
-            cur_node = if let Some(next) = cur_node.left_mut() {
-                next
-            } else {
-                break unsafe {
-                    &mut *mem::transmute::<*const Self, *mut Self>(ptr)
-                };
+            let parent = (*cur_ptr.as_ptr()).parent?;
+            if (*parent.as_ptr()).right == Some(cur_ptr) {
+                return Some(parent);
             }
+            cur_ptr = parent;
         }
     }
 
-    #[inline]
-    fn rotate_left(&mut self) {
-        let self_ptr = self.into();
-        let parent_ptr = self.parent;
-        let right_ptr = self.right;
-        let right = match self.right_mut() {
-            Some(ptr) => ptr,
-            None => return,
-        };
-        let left_ptr = right.left;
+    /// Returns the number of nodes before the node at `ptr` in the order of its tree.
+    pub(crate) unsafe fn position(mut ptr: NonNull<Self>) -> usize {
+        let mut position = Self::size_of((*ptr.as_ptr()).left);
+        while let Some(parent) = (*ptr.as_ptr()).parent {
+            if (*parent.as_ptr()).right == Some(ptr) {
+                position += Self::size_of((*parent.as_ptr()).left) + 1;
+            }
+            ptr = parent;
+        }
+        position
+    }
 
-        right.parent = parent_ptr;
+    /// Returns `true` if the node at `ptr` is in the subtree at `root`.
+    pub(crate) unsafe fn is_in_subtree(ptr: NonNull<Self>, root: NodePtr<K, V>) -> bool {
+        let mut cur_ptr = Some(ptr);
+        while let Some(node_ptr) = cur_ptr {
+            if Some(node_ptr) == root {
+                return true;
+            }
+            cur_ptr = (*node_ptr.as_ptr()).parent;
+        }
+        false
+    }
 
-        right.left = Some(self_ptr);
+    /// Returns the number of edges between the node at `ptr` and the root.
+    pub(crate) unsafe fn depth(mut ptr: NonNull<Self>) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = (*ptr.as_ptr()).parent {
+            ptr = parent;
+            depth += 1;
+        }
+        depth
+    }
 
-        let is_left = self.is_left();
-        if let Some(parent) = self.parent_mut() {
-            if is_left {
-                parent.left = right_ptr;
-            } else {
-                parent.right = right_ptr;
+    /// Splays the node at `ptr` to the root of its tree and returns it.
+    pub(crate) unsafe fn splay(ptr: NonNull<Self>) -> NonNull<Self> {
+        while let Some(splay_type) = Self::splay_type(ptr) {
+            match splay_type {
+                SplayType::Zig => Self::rotate_up(ptr),
+                SplayType::ZigZig => {
+                    if let Some(parent) = (*ptr.as_ptr()).parent {
+                        Self::rotate_up(parent);
+                    }
+                    Self::rotate_up(ptr);
+                },
+                SplayType::ZigZag => {
+                    Self::rotate_up(ptr);
+                    Self::rotate_up(ptr);
+                },
             }
         }
 
-        self.parent = right_ptr;
+        ptr
+    }
 
-        self.right = left_ptr;
-        self.right_mut().map(|mut r| { r.parent = Some(self_ptr); r });
+    /// Returns the number of nodes in the subtree at `ptr`.
+    #[inline]
+    pub(crate) fn size_of(ptr: NodePtr<K, V>) -> usize {
+        ptr.map_or(0, |p| unsafe { (*p.as_ptr()).size })
     }
 
+    /// Recomputes the size of the subtree at `ptr` from the sizes of its children.
     #[inline]
-    fn rotate_right(&mut self) {
-        let self_ptr = self.into();
-        let parent_ptr = self.parent;
-        let left_ptr = self.left;
-        let left = match self.left_mut() {
-            Some(ptr) => ptr,
-            None => return,
+    pub(crate) unsafe fn update_size(ptr: NonNull<Self>) {
+        let node = ptr.as_ptr();
+        (*node).size = 1 + Self::size_of((*node).left) + Self::size_of((*node).right);
+    }
+
+    /// Recomputes the sizes of the subtrees at `ptr` and at all of its ancestors.
+    pub(crate) unsafe fn update_sizes_up(ptr: NodePtr<K, V>) {
+        let mut cur_ptr = ptr;
+        while let Some(node_ptr) = cur_ptr {
+            Self::update_size(node_ptr);
+            cur_ptr = (*node_ptr.as_ptr()).parent;
+        }
+    }
+
+    /// Joins two detached trees, where all keys of `left` are less than the keys
+    /// of `right`, and returns the new root.
+    pub(crate) unsafe fn merge(left: NodePtr<K, V>, right: NodePtr<K, V>) -> NodePtr<K, V> {
+        let left = match left {
+            Some(left) => left,
+            None => return right,
         };
-        let right_ptr = left.right;
 
-        left.parent = parent_ptr;
+        let max = Self::splay(Self::rightmost(left));
+        (*max.as_ptr()).right = right;
+        if let Some(r) = right {
+            (*r.as_ptr()).parent = Some(max);
+        }
+        Self::update_size(max);
+
+        Some(max)
+    }
 
-        left.right = Some(self_ptr);
+    /// Rotates the node at `ptr` above its parent.
+    unsafe fn rotate_up(ptr: NonNull<Self>) {
+        let node = ptr.as_ptr();
+        let parent_ptr = match (*node).parent {
+            Some(parent_ptr) => parent_ptr,
+            None => return,
+        };
+        let parent = parent_ptr.as_ptr();
+        let grandparent = (*parent).parent;
+
+        let middle = if (*parent).left == Some(ptr) {
+            let middle = (*node).right;
+            (*parent).left = middle;
+            (*node).right = Some(parent_ptr);
+            middle
+        } else {
+            let middle = (*node).left;
+            (*parent).right = middle;
+            (*node).left = Some(parent_ptr);
+            middle
+        };
 
-        let is_left = self.is_left();
-        if let Some(parent) = self.parent_mut() {
-            if is_left {
-                parent.left = left_ptr;
+        if let Some(m) = middle {
+            (*m.as_ptr()).parent = Some(parent_ptr);
+        }
+        (*parent).parent = Some(ptr);
+        (*node).parent = grandparent;
+        Self::update_size(parent_ptr);
+        Self::update_size(ptr);
+
+        if let Some(g) = grandparent {
+            let g = g.as_ptr();
+            if (*g).left == Some(parent_ptr) {
+                (*g).left = Some(ptr);
             } else {
-                parent.right = left_ptr;
+                (*g).right = Some(ptr);
             }
         }
-
-        self.parent = left_ptr;
-
-        self.left = right_ptr;
-        self.left_mut().map(|mut l| l.parent = Some(self_ptr));
     }
 
     #[inline]
-    fn splay_type(&self) -> Option<SplayType> {
-        if self.parent()?.is_root() {
+    unsafe fn splay_type(ptr: NonNull<Self>) -> Option<SplayType> {
+        let parent = (*ptr.as_ptr()).parent?;
+
+        if (*parent.as_ptr()).parent.is_none() {
             Some(SplayType::Zig)
-        } else if (self.is_left() && self.parent()?.is_left()) ||
-                  (self.is_right() && self.parent()?.is_right()) {
+        } else if Self::is_left_ptr(ptr) == Self::is_left_ptr(parent) {
             Some(SplayType::ZigZig)
-        } else if !self.parent()?.is_root() {
-            Some(SplayType::ZigZag)
         } else {
-            None
+            Some(SplayType::ZigZag)
         }
     }
 
-    pub(crate) fn free(&mut self) {
-        match self.left {
-            Some(left_ptr) => {
-                let mut left_box = unsafe { Box::from_raw(left_ptr.as_ptr()) };
-                left_box.free();
-            },
-            None => {},
-        }
-
-        match self.right {
-            Some(right_ptr) => {
-                let mut right_box = unsafe { Box::from_raw(right_ptr.as_ptr()) };
-                right_box.free();
-            },
-            None => {},
+    /// Frees the subtree at `root` together with the root itself.
+    ///
+    /// Left children are rotated into a right-leaning spine which is freed from the
+    /// top, so the tree's shape doesn't matter and no extra space is needed.
+    /// Parent pointers aren't maintained, since every node is freed.
+    ///
+    /// # Safety
+    ///
+    /// The subtree must be owned by the caller and not accessed afterwards.
+    pub(crate) unsafe fn free(root: NodePtr<K, V>) {
+        let mut cur_ptr = root;
+
+        while let Some(node_ptr) = cur_ptr {
+            let node = node_ptr.as_ptr();
+            cur_ptr = if let Some(left_ptr) = (*node).left {
+                let left = left_ptr.as_ptr();
+                (*node).left = (*left).right;
+                (*left).right = Some(node_ptr);
+                Some(left_ptr)
+            } else {
+                let right = (*node).right;
+                drop(Box::from_raw(node));
+                right
+            };
         }
     }
 }
@@ -8,15 +8,17 @@ struct Root<K: Ord, V> {
 }
 
 impl<K: Ord, V> Root<K, V> {
-    pub fn root_mut(&mut self) -> Option<&mut Node<K, V>> {
-        self.root.map(|mut r| unsafe { r.as_mut() })
-    }
-
     fn root(&self) -> Option<&Node<K, V>> {
         self.root.map(|r| unsafe { r.as_ref() })
     }
 }
 
+impl<K: Ord, V> Drop for Root<K, V> {
+    fn drop(&mut self) {
+        unsafe { Node::free(self.root.take()) };
+    }
+}
+
 fn check_tree_structure<K: Ord + Debug, V: Debug>(root: &Node<K, V>, keys: &[K]) {
     assert!(root.parent.is_none());
     check_node_structure(root, keys);
@@ -25,28 +27,21 @@ fn check_tree_structure<K: Ord + Debug, V: Debug>(root: &Node<K, V>, keys: &[K])
 fn check_node_structure<'a, K, V>(root: &Node<K, V>, mut keys: &'a [K]) -> &'a [K]
     where K: Ord + Debug, V: Debug
 {
-    assert!(keys.len() != 0);
+    assert!(!keys.is_empty());
     assert_eq!(root.key, keys[0]);
+    assert_eq!(root.size, 1 + Node::size_of(root.left) + Node::size_of(root.right));
 
     keys = &keys[1..];
 
     if let Some(left) = root.left() {
-        let parent_ptr = unsafe {
-            mem::transmute::<*mut Node<K, V>, *const Node<K, V>>(
-                left.parent.unwrap().as_ptr()
-            )
-        };
+        let parent_ptr = left.parent.unwrap().as_ptr() as *const Node<K, V>;
         assert_eq!(parent_ptr, root);
 
         keys = check_node_structure(left, keys);
     }
 
     if let Some(right) = root.right() {
-        let parent_ptr = unsafe {
-            mem::transmute::<*mut Node<K, V>, *const Node<K, V>>(
-                right.parent.unwrap().as_ptr()
-            )
-        };
+        let parent_ptr = right.parent.unwrap().as_ptr() as *const Node<K, V>;
         assert_eq!(parent_ptr, root);
 
         keys = check_node_structure(right, keys);
@@ -56,15 +51,10 @@ fn check_node_structure<'a, K, V>(root: &Node<K, V>, mut keys: &'a [K]) -> &'a [
 }
 
 fn tree_from_slice<K: Ord + Copy>(mut keys: &[Option<K>]) -> Option<Root<K, K>> {
-    let root_ptr = NonNull::new(Box::leak(Box::new(Node {
-        key: keys[0]?,
-        value: keys[0]?,
-        left: None,
-        right: None,
-        parent: None,
-    })))?;
+    let root_ptr = NonNull::new(Box::leak(Box::new(Node::new(keys[0]?, keys[0]?))))?;
     let mut queue = LinkedList::new();
     queue.push_back(root_ptr);
+    let mut created = vec![root_ptr];
 
     keys = &keys[1..];
 
@@ -74,14 +64,12 @@ fn tree_from_slice<K: Ord + Copy>(mut keys: &[Option<K>]) -> Option<Root<K, K>>
 
         if let Some(left_key) = keys[i] {
             let left_ptr = NonNull::new(Box::leak(Box::new(Node {
-                key: left_key,
-                value: left_key,
-                left: None,
-                right: None,
                 parent: Some(cur_node_ptr),
+                ..Node::new(left_key, left_key)
             })))?;
 
             queue.push_back(left_ptr);
+            created.push(left_ptr);
             cur_node.left = Some(left_ptr);
         }
 
@@ -91,27 +79,28 @@ fn tree_from_slice<K: Ord + Copy>(mut keys: &[Option<K>]) -> Option<Root<K, K>>
 
         if let Some(right_key) = keys[i + 1] {
             let right_ptr = NonNull::new(Box::leak(Box::new(Node {
-                key: right_key,
-                value: right_key,
-                left: None,
-                right: None,
                 parent: Some(cur_node_ptr),
+                ..Node::new(right_key, right_key)
             })))?;
 
             queue.push_back(right_ptr);
+            created.push(right_ptr);
             cur_node.right = Some(right_ptr);
         }
     }
 
+    for node_ptr in created.into_iter().rev() {
+        unsafe { Node::update_size(node_ptr) };
+    }
     Some(Root { root: Some(root_ptr) })
 }
 
 #[test]
 fn splay_zig_left() {
     let mut tree = tree_from_slice(&[Some(10u32), Some(5), Some(12), Some(3), Some(6)]).unwrap();
-    let elem = tree.root_mut().unwrap().left_mut().unwrap(); // 5
-    assert_eq!(elem.splay_type(), Some(SplayType::Zig));
-    elem.splay().map(|r| tree.root = Some(r));
+    let elem = tree.root().unwrap().left.unwrap(); // 5
+    assert_eq!(unsafe { Node::splay_type(elem) }, Some(SplayType::Zig));
+    tree.root = Some(unsafe { Node::splay(elem) });
     check_tree_structure(tree.root().unwrap(), &[5, 3, 10, 6, 12]);
 }
 
@@ -120,9 +109,9 @@ fn splay_zig_right() {
     let mut tree = tree_from_slice(&[
         Some(10u32), Some(5), Some(20), None, None, Some(15), Some(30)
     ]).unwrap();
-    let elem = tree.root_mut().unwrap().right_mut().unwrap(); // 20
-    assert_eq!(elem.splay_type(), Some(SplayType::Zig));
-    elem.splay().map(|r| tree.root = Some(r));
+    let elem = tree.root().unwrap().right.unwrap(); // 20
+    assert_eq!(unsafe { Node::splay_type(elem) }, Some(SplayType::Zig));
+    tree.root = Some(unsafe { Node::splay(elem) });
     check_tree_structure(tree.root().unwrap(), &[20, 10, 5, 15, 30]);
 }
 
@@ -132,9 +121,9 @@ fn splay_zig_zig_left() {
         Some(20u32), Some(15), Some(30), Some(13), Some(16), None, None,
         Some(12), Some(14)
     ]).unwrap();
-    let elem = tree.root_mut().unwrap().left_mut().unwrap().left_mut().unwrap(); // 13
-    assert_eq!(elem.splay_type(), Some(SplayType::ZigZig));
-    elem.splay().map(|r| tree.root = Some(r));
+    let elem = tree.root().unwrap().left().unwrap().left.unwrap(); // 13
+    assert_eq!(unsafe { Node::splay_type(elem) }, Some(SplayType::ZigZig));
+    tree.root = Some(unsafe { Node::splay(elem) });
     check_tree_structure(tree.root().unwrap(), &[13, 12, 15, 14, 20, 16, 30]);
 }
 
@@ -144,9 +133,9 @@ fn splay_zig_zig_right() {
         Some(13), Some(12), Some(15), None, None, Some(14), Some(20), None, None,
         Some(16), Some(30)
     ]).unwrap();
-    let elem = tree.root_mut().unwrap().right_mut().unwrap().right_mut().unwrap(); // 20
-    assert_eq!(elem.splay_type(), Some(SplayType::ZigZig));
-    elem.splay().map(|r| tree.root = Some(r));
+    let elem = tree.root().unwrap().right().unwrap().right.unwrap(); // 20
+    assert_eq!(unsafe { Node::splay_type(elem) }, Some(SplayType::ZigZig));
+    tree.root = Some(unsafe { Node::splay(elem) });
     check_tree_structure(tree.root().unwrap(), &[20, 15, 13, 12, 14, 16, 30]);
 }
 
@@ -156,9 +145,9 @@ fn splay_zig_zag_left() {
         Some(40), Some(30), Some(50), None, None, Some(45), Some(60),
         Some(44), Some(46)
     ]).unwrap();
-    let elem = tree.root_mut().unwrap().right_mut().unwrap().left_mut().unwrap(); // 45
-    assert_eq!(elem.splay_type(), Some(SplayType::ZigZag));
-    elem.splay().map(|r| tree.root = Some(r));
+    let elem = tree.root().unwrap().right().unwrap().left.unwrap(); // 45
+    assert_eq!(unsafe { Node::splay_type(elem) }, Some(SplayType::ZigZag));
+    tree.root = Some(unsafe { Node::splay(elem) });
     check_tree_structure(tree.root().unwrap(), &[45, 40, 30, 44, 50, 46, 60]);
 }
 
@@ -168,8 +157,27 @@ fn splay_zig_zag_right() {
         Some(40), Some(30), Some(50), Some(20), Some(35), None, None, None, None,
         Some(34), Some(36)
     ]).unwrap();
-    let elem = tree.root_mut().unwrap().left_mut().unwrap().right_mut().unwrap(); // 35
-    assert_eq!(elem.splay_type(), Some(SplayType::ZigZag));
-    elem.splay().map(|r| tree.root = Some(r));
+    let elem = tree.root().unwrap().left().unwrap().right.unwrap(); // 35
+    assert_eq!(unsafe { Node::splay_type(elem) }, Some(SplayType::ZigZag));
+    tree.root = Some(unsafe { Node::splay(elem) });
     check_tree_structure(tree.root().unwrap(), &[35, 30, 20, 34, 40, 36, 50]);
 }
+
+#[test]
+fn sibling_leaf_len() {
+    let tree = tree_from_slice(&[Some(10u32), Some(5), Some(12), Some(3)]).unwrap();
+    let root = tree.root().unwrap();
+    let left = root.left().unwrap();
+    let right = root.right().unwrap();
+    let leaf = left.left().unwrap();
+
+    assert!(root.sibling().is_none());
+    assert_eq!(left.sibling().map(|n| n.key), Some(12));
+    assert_eq!(right.sibling().map(|n| n.key), Some(5));
+    assert!(leaf.sibling().is_none());
+
+    assert!(!root.is_leaf());
+    assert!(!left.is_leaf());
+    assert!(right.is_leaf() && leaf.is_leaf());
+    assert_eq!([root, left, right, leaf].map(Node::subtree_len), [4, 2, 1, 1]);
+}
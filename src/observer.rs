@@ -0,0 +1,32 @@
+/// Hooks called by a `SplayTree` on every change of its entries, registered
+/// with [`SplayTreeBy::set_observer`].
+///
+/// Every method does nothing by default, so an observer only implements the hooks
+/// it needs, e.g. to maintain a secondary index or to track dirty keys. Hooks are
+/// called once the tree is consistent again; a panicking hook doesn't corrupt it.
+///
+/// Changes made through mutable references to values, like the ones returned by
+/// [`NodeMut::value_mut`], [`NodeMut::key_value_mut`] and [`NodeMut::into_value_mut`],
+/// aren't observed.
+///
+/// [`SplayTreeBy::set_observer`]: crate::SplayTreeBy::set_observer
+/// [`NodeMut::value_mut`]: crate::NodeMut::value_mut
+/// [`NodeMut::key_value_mut`]: crate::NodeMut::key_value_mut
+/// [`NodeMut::into_value_mut`]: crate::NodeMut::into_value_mut
+pub trait Observer<K, V> {
+    /// Called after an entry is inserted.
+    #[inline]
+    fn on_insert(&mut self, _key: &K, _value: &V) {}
+
+    /// Called after an entry is removed, before it's returned or dropped.
+    #[inline]
+    fn on_remove(&mut self, _key: &K, _value: &V) {}
+
+    /// Called after the value of an entry is replaced with a new one.
+    #[inline]
+    fn on_replace(&mut self, _key: &K, _old: &V, _new: &V) {}
+
+    /// Called after a node is splayed to the root with the number of rotations made.
+    #[inline]
+    fn on_rotate(&mut self, _rotations: usize) {}
+}
@@ -0,0 +1,472 @@
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::mem;
+
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+
+use crate::{Node, NodePtr, SplayTree, Natural};
+use crate::tree::iter::take_min;
+
+/// Parallel iterator over the entries of a `SplayTree`, sorted by key.
+///
+/// The tree is split at subtree boundaries, so every worker walks its own part of
+/// the tree. Created by `par_iter()`.
+pub struct ParIter<'a, K: Ord, V> {
+    root: NodePtr<K, V>,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+/// Parallel iterator over the entries of a `SplayTree` with mutable references
+/// to the values, sorted by key. Created by `par_iter_mut()`.
+pub struct ParIterMut<'a, K: Ord, V> {
+    root: NodePtr<K, V>,
+    marker: PhantomData<&'a mut Node<K, V>>,
+}
+
+/// Owning parallel iterator over the entries of a `SplayTree`, sorted by key.
+/// Created by `into_par_iter()`.
+pub struct IntoParIter<K: Ord, V> {
+    tree: SplayTree<K, V>,
+}
+
+// SAFETY: these iterators behave like `&SplayTree` and `&mut SplayTree` respectively.
+unsafe impl<'a, K: Ord + Sync, V: Sync> Send for ParIter<'a, K, V> {}
+unsafe impl<'a, K: Ord + Sync, V: Send> Send for ParIterMut<'a, K, V> {}
+
+impl<'a, K: Ord + Sync + 'a, V: Sync + 'a> IntoParallelIterator for &'a SplayTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V>;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter {
+            root: self.root,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: Ord + Sync + 'a, V: Send + 'a> IntoParallelIterator for &'a mut SplayTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type Iter = ParIterMut<'a, K, V>;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        ParIterMut {
+            root: self.root,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Ord + Send, V: Send> IntoParallelIterator for SplayTree<K, V> {
+    type Item = (K, V);
+    type Iter = IntoParIter<K, V>;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter { tree: self }
+    }
+}
+
+impl<'a, K: Ord + Sync + 'a, V: Sync + 'a> ParallelIterator for ParIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        let producer = SubtreeProducer::<K, V, Shared<'a>>::new(self.root);
+        bridge_unindexed(producer, consumer)
+    }
+}
+
+impl<'a, K: Ord + Sync + 'a, V: Send + 'a> ParallelIterator for ParIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        let producer = SubtreeProducer::<K, V, Exclusive<'a>>::new(self.root);
+        bridge_unindexed(producer, consumer)
+    }
+}
+
+impl<K: Ord + Send, V: Send> ParallelIterator for IntoParIter<K, V> {
+    type Item = (K, V);
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(mut self, consumer: C) -> C::Result {
+        let producer = OwnedProducer {
+            head: None,
+            root: self.tree.take_root(),
+            marker: PhantomData,
+        };
+        bridge_unindexed(producer, consumer)
+    }
+}
+
+impl<K: Ord + Send, V: Send> FromParallelIterator<(K, V)> for SplayTree<K, V> {
+    /// Collects the entries into a balanced tree. Entries are sorted and the tree
+    /// is linked on worker threads. If a key occurs several times, the value
+    /// which comes last in the iterator's order is kept.
+    fn from_par_iter<I>(par_iter: I) -> Self
+        where I: IntoParallelIterator<Item = (K, V)>
+    {
+        let mut entries: Vec<(K, V)> = par_iter.into_par_iter().collect();
+        sort_dedup(&mut entries);
+        build_sorted(entries)
+    }
+}
+
+impl<K: Ord + Send, V: Send> ParallelExtend<(K, V)> for SplayTree<K, V> {
+    /// Extends the tree with the entries of a parallel iterator, replacing
+    /// the values of existing keys. The new entries are sorted on worker threads
+    /// and merged with the tree, which is then rebuilt balanced.
+    ///
+    /// If comparing keys panics during the merge, the tree is left empty.
+    fn par_extend<I>(&mut self, par_iter: I)
+        where I: IntoParallelIterator<Item = (K, V)>
+    {
+        let mut entries: Vec<(K, V)> = par_iter.into_par_iter().collect();
+        if entries.is_empty() {
+            return;
+        }
+        sort_dedup(&mut entries);
+
+        let old_tree = mem::take(self);
+        let merged = if old_tree.is_empty() {
+            entries
+        } else {
+            merge_sorted(old_tree.into_iter(), entries)
+        };
+        *self = build_sorted(merged);
+    }
+}
+
+impl<K: Ord + Send, V: Send> SplayTree<K, V> {
+    /// Builds a balanced tree from unsorted entries, which are sorted in place and
+    /// linked on worker threads. If a key occurs several times, the entry which
+    /// comes last is kept.
+    ///
+    /// Unlike collecting a parallel iterator, this takes the entries without
+    /// copying them into a new vector first.
+    ///
+    /// This operation should compute in *O*(*n log n*) time, divided between
+    /// the threads of the pool.
+    pub fn build_parallel(mut entries: Vec<(K, V)>) -> Self {
+        sort_dedup(&mut entries);
+        build_sorted(entries)
+    }
+}
+
+/// Stable-sorts the entries by key and keeps only the last one of equal keys.
+fn sort_dedup<K: Ord + Send, V: Send>(entries: &mut Vec<(K, V)>) {
+    entries.par_sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries.dedup_by(|next, prev| {
+        if next.0 == prev.0 {
+            mem::swap(next, prev);
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Merges two sorted sequences of entries, preferring `new` on equal keys.
+fn merge_sorted<K: Ord, V, I>(old: I, new: Vec<(K, V)>) -> Vec<(K, V)>
+    where I: Iterator<Item = (K, V)> + ExactSizeIterator
+{
+    let mut merged = Vec::with_capacity(old.len() + new.len());
+    let mut old = old.peekable();
+    let mut new = new.into_iter().peekable();
+
+    loop {
+        let take_old = match (old.peek(), new.peek()) {
+            (Some((old_key, _)), Some((new_key, _))) => old_key < new_key,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+
+        if take_old {
+            merged.extend(old.next());
+        } else {
+            let entry = new.next();
+            if let (Some((old_key, _)), Some((new_key, _))) = (old.peek(), entry.as_ref()) {
+                if old_key == new_key {
+                    old.next();
+                }
+            }
+            merged.extend(entry);
+        }
+    }
+
+    merged
+}
+
+struct SendPtr<K: Ord, V>(NonNull<Node<K, V>>);
+
+impl<K: Ord, V> Clone for SendPtr<K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Ord, V> Copy for SendPtr<K, V> {}
+
+// SAFETY: used only while building a tree, when every task links its own nodes.
+unsafe impl<K: Ord + Send, V: Send> Send for SendPtr<K, V> {}
+unsafe impl<K: Ord + Send, V: Send> Sync for SendPtr<K, V> {}
+
+/// Builds a balanced tree from entries sorted by key without duplicates,
+/// allocating and linking the nodes on worker threads.
+fn build_sorted<K: Ord + Send, V: Send>(entries: Vec<(K, V)>) -> SplayTree<K, V> {
+    let length = entries.len();
+    let nodes: Vec<SendPtr<K, V>> = entries
+        .into_par_iter()
+        .map(|(key, value)| SendPtr(Node::alloc(key, value)))
+        .collect();
+
+    SplayTree::from_root(link_balanced(&nodes).map(|root| root.0), length, Natural)
+}
+
+fn link_balanced<K: Ord + Send, V: Send>(nodes: &[SendPtr<K, V>]) -> Option<SendPtr<K, V>> {
+    const SEQUENTIAL_THRESHOLD: usize = 1024;
+
+    if nodes.is_empty() {
+        return None;
+    }
+
+    let mid = nodes.len() / 2;
+    let (left, right) = if nodes.len() > SEQUENTIAL_THRESHOLD {
+        rayon::join(|| link_balanced(&nodes[..mid]), || link_balanced(&nodes[mid + 1..]))
+    } else {
+        (link_balanced(&nodes[..mid]), link_balanced(&nodes[mid + 1..]))
+    };
+
+    let node_ptr = nodes[mid];
+    unsafe {
+        let node = &mut *node_ptr.0.as_ptr();
+        node.left = left.map(|l| l.0);
+        node.right = right.map(|r| r.0);
+        node.size = nodes.len();
+        if let Some(l) = left {
+            (*l.0.as_ptr()).parent = Some(node_ptr.0);
+        }
+        if let Some(r) = right {
+            (*r.0.as_ptr()).parent = Some(node_ptr.0);
+        }
+    }
+
+    Some(node_ptr)
+}
+
+/// Produces the items of a borrowing parallel iterator out of a node pointer.
+trait Access<K: Ord, V> {
+    type Item;
+
+    /// # Safety
+    ///
+    /// Every node must be accessed at most once while the borrow lasts.
+    unsafe fn access(ptr: NonNull<Node<K, V>>) -> Self::Item;
+}
+
+struct Shared<'a>(PhantomData<&'a ()>);
+
+struct Exclusive<'a>(PhantomData<&'a mut ()>);
+
+impl<'a, K: Ord + 'a, V: 'a> Access<K, V> for Shared<'a> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    unsafe fn access(ptr: NonNull<Node<K, V>>) -> Self::Item {
+        let node = &*ptr.as_ptr();
+        (&node.key, &node.value)
+    }
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Access<K, V> for Exclusive<'a> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    unsafe fn access(ptr: NonNull<Node<K, V>>) -> Self::Item {
+        let node = ptr.as_ptr();
+        (&(*node).key, &mut (*node).value)
+    }
+}
+
+/// Produces `head` followed by the in-order traversal of the subtree at `root`.
+///
+/// Splitting yields `head` with the left subtree and the root with the right
+/// subtree. Links are only read through raw pointers, so a worker never creates
+/// a reference to a node whose value another worker holds mutably.
+struct SubtreeProducer<K: Ord, V, A> {
+    head: NodePtr<K, V>,
+    root: NodePtr<K, V>,
+    marker: PhantomData<A>,
+}
+
+// SAFETY: the producers are only created by iterators which are `Send`,
+// and they own disjoint parts of the tree.
+unsafe impl<K: Ord, V, A> Send for SubtreeProducer<K, V, A> {}
+
+impl<K: Ord, V, A> SubtreeProducer<K, V, A> {
+    #[inline]
+    fn new(root: NodePtr<K, V>) -> Self {
+        SubtreeProducer {
+            head: None,
+            root,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, A> UnindexedProducer for SubtreeProducer<K, V, A>
+    where K: Ord, A: Access<K, V>, A::Item: Send
+{
+    type Item = A::Item;
+
+    fn split(self) -> (Self, Option<Self>) {
+        let root_ptr = match self.root {
+            Some(root_ptr) => root_ptr,
+            None => return (self, None),
+        };
+
+        let (left, right) = unsafe { ((*root_ptr.as_ptr()).left, (*root_ptr.as_ptr()).right) };
+        let left_part = SubtreeProducer {
+            head: self.head,
+            root: left,
+            marker: PhantomData,
+        };
+        let right_part = SubtreeProducer {
+            head: Some(root_ptr),
+            root: right,
+            marker: PhantomData,
+        };
+        (left_part, Some(right_part))
+    }
+
+    fn fold_with<F: Folder<Self::Item>>(self, mut folder: F) -> F {
+        if let Some(head) = self.head {
+            folder = folder.consume(unsafe { A::access(head) });
+        }
+
+        let root_ptr = match self.root {
+            Some(root_ptr) => root_ptr,
+            None => return folder,
+        };
+
+        let mut cur_ptr = Some(unsafe { Node::leftmost(root_ptr) });
+        while let Some(node_ptr) = cur_ptr {
+            if folder.full() {
+                break;
+            }
+            cur_ptr = unsafe { raw_successor(node_ptr, root_ptr) };
+            folder = folder.consume(unsafe { A::access(node_ptr) });
+        }
+
+        folder
+    }
+}
+
+/// Owning counterpart of `SubtreeProducer`. Splitting detaches the subtrees,
+/// and every part frees the nodes it has yielded or hasn't got to.
+struct OwnedProducer<K: Ord, V> {
+    head: NodePtr<K, V>,
+    root: NodePtr<K, V>,
+    marker: PhantomData<Box<Node<K, V>>>,
+}
+
+// SAFETY: the producer uniquely owns its nodes.
+unsafe impl<K: Ord + Send, V: Send> Send for OwnedProducer<K, V> {}
+
+impl<K: Ord + Send, V: Send> UnindexedProducer for OwnedProducer<K, V> {
+    type Item = (K, V);
+
+    fn split(mut self) -> (Self, Option<Self>) {
+        let mut root_ptr = match self.root.take() {
+            Some(root_ptr) => root_ptr,
+            None => return (self, None),
+        };
+
+        let root = unsafe { root_ptr.as_mut() };
+        let left = root.left.take();
+        let right = root.right.take();
+        for mut child in left.into_iter().chain(right) {
+            unsafe { child.as_mut() }.parent = None;
+        }
+
+        let left_part = OwnedProducer {
+            head: self.head.take(),
+            root: left,
+            marker: PhantomData,
+        };
+        let right_part = OwnedProducer {
+            head: Some(root_ptr),
+            root: right,
+            marker: PhantomData,
+        };
+        (left_part, Some(right_part))
+    }
+
+    fn fold_with<F: Folder<Self::Item>>(mut self, mut folder: F) -> F {
+        if let Some(head) = self.head.take() {
+            let node = unsafe { Box::from_raw(head.as_ptr()) };
+            folder = folder.consume((node.key, node.value));
+        }
+
+        let mut front = self.root.take().map(|r| unsafe { Node::leftmost(r) });
+        while let Some(min_ptr) = front {
+            if folder.full() {
+                self.root = Some(min_ptr);
+                break;
+            }
+            let (node, next_min) = unsafe { take_min(min_ptr) };
+            front = next_min;
+            folder = folder.consume((node.key, node.value));
+        }
+
+        folder
+    }
+}
+
+impl<K: Ord, V> Drop for OwnedProducer<K, V> {
+    fn drop(&mut self) {
+        if let Some(head) = self.head.take() {
+            drop(unsafe { Box::from_raw(head.as_ptr()) });
+        }
+
+        // The root may be any node of a partially drained tree, so climb to the top first.
+        let mut front = self.root.take().map(|mut cur_ptr| {
+            while let Some(parent) = unsafe { (*cur_ptr.as_ptr()).parent } {
+                cur_ptr = parent;
+            }
+            unsafe { Node::leftmost(cur_ptr) }
+        });
+        while let Some(min_ptr) = front {
+            let (node, next_min) = unsafe { take_min(min_ptr) };
+            front = next_min;
+            drop(node);
+        }
+    }
+}
+
+/// Returns the in-order successor of a node within the subtree at `root_ptr`.
+#[inline]
+unsafe fn raw_successor<K: Ord, V>(
+    node_ptr: NonNull<Node<K, V>>,
+    root_ptr: NonNull<Node<K, V>>
+) -> NodePtr<K, V> {
+    if let Some(right) = (*node_ptr.as_ptr()).right {
+        return Some(Node::leftmost(right));
+    }
+
+    let mut cur_ptr = node_ptr;
+    while cur_ptr != root_ptr {
+        let parent = (*cur_ptr.as_ptr()).parent?;
+        if (*parent.as_ptr()).left == Some(cur_ptr) {
+            return Some(parent);
+        }
+        cur_ptr = parent;
+    }
+
+    None
+}
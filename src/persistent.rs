@@ -0,0 +1,390 @@
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+use std::sync::Arc;
+use std::mem;
+
+type Link<K, V> = Option<Arc<PersistentNode<K, V>>>;
+
+#[derive(Clone)]
+struct PersistentNode<K, V> {
+    key: K,
+    value: V,
+    height: u32,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+/// Persistent ordered map with the same API family as `SplayTree`.
+///
+/// Nodes are shared between versions through `Arc`, so cloning is *O*(1) and
+/// every mutation copies only the path it touches. Splaying on reads can't be
+/// shared between versions and its amortized bounds don't survive persistence,
+/// so the tree is kept height-balanced instead and lookups take `&self`.
+pub struct PersistentTree<K: Ord, V> {
+    root: Link<K, V>,
+    length: usize,
+}
+
+impl<K: Ord, V> PersistentTree<K, V> {
+    /// Creates an empty `PersistentTree`.
+    #[inline]
+    pub fn new() -> Self {
+        PersistentTree {
+            root: None,
+            length: 0,
+        }
+    }
+
+    /// Returns a reference to the value by a key,
+    /// or `None` if the tree doesn't contain that key.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut cur_node = self.root.as_deref();
+
+        while let Some(node) = cur_node {
+            cur_node = match key.cmp(&node.key) {
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Equal => return Some(&node.value),
+                Ordering::Greater => node.right.as_deref(),
+            };
+        }
+
+        None
+    }
+
+    /// Returns `true` if the tree contains a value for the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the entry with a minimum key, or `None` if the tree is empty.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    pub fn get_min(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(left) = node.left.as_deref() {
+            node = left;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// Returns the entry with a maximum key, or `None` if the tree is empty.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    pub fn get_max(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(right) = node.right.as_deref() {
+            node = right;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// Returns an iterator over the entries of the tree, sorted by key.
+    #[inline]
+    pub fn iter(&self) -> PersistentIter<'_, K, V> {
+        let mut iter = PersistentIter {
+            stack: Vec::new(),
+            remaining: self.length,
+        };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+
+    /// Returns `true` if both trees are the same version, i.e. share the root node.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the tree contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns the length of the `PersistentTree`.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.length
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> PersistentTree<K, V> {
+    /// Inserts a value to the tree with a key and returns the replaced value, if any.
+    /// Other versions of the tree are not affected.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = insert_node(&mut self.root, key, value);
+        if old.is_none() {
+            self.length += 1;
+        }
+        old
+    }
+
+    /// Removes an entry with a given key and returns its value, or `None` if the tree
+    /// doesn't contain that key. Other versions of the tree are not affected.
+    ///
+    /// This operation should compute in *O*(*log n*) time.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        let value = remove_node(&mut self.root, key);
+        self.length -= 1;
+        value
+    }
+}
+
+impl<K: Ord, V> Clone for PersistentTree<K, V> {
+    /// Returns a new version sharing all nodes with `self`.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    fn clone(&self) -> Self {
+        PersistentTree {
+            root: self.root.clone(),
+            length: self.length,
+        }
+    }
+}
+
+impl<K: Ord, V> Default for PersistentTree<K, V> {
+    #[inline]
+    fn default() -> Self {
+        PersistentTree::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> FromIterator<(K, V)> for PersistentTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = PersistentTree::new();
+        for (key, value) in iter {
+            tree.insert(key, value);
+        }
+        tree
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a PersistentTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = PersistentIter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Nodes shared between versions are counted once per measurement, so measuring
+/// a structure holding several versions doesn't count them twice.
+#[cfg(feature = "deepsize")]
+impl<K, V> deepsize::DeepSizeOf for PersistentTree<K, V>
+    where K: Ord + deepsize::DeepSizeOf, V: deepsize::DeepSizeOf
+{
+    #[inline]
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.root.deep_size_of_children(context)
+    }
+}
+
+#[cfg(feature = "deepsize")]
+impl<K, V> deepsize::DeepSizeOf for PersistentNode<K, V>
+    where K: deepsize::DeepSizeOf, V: deepsize::DeepSizeOf
+{
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.key.deep_size_of_children(context)
+            + self.value.deep_size_of_children(context)
+            + self.left.deep_size_of_children(context)
+            + self.right.deep_size_of_children(context)
+    }
+}
+
+/// Iterator over the entries of a `PersistentTree`, sorted by key.
+pub struct PersistentIter<'a, K, V> {
+    stack: Vec<&'a PersistentNode<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> PersistentIter<'a, K, V> {
+    fn push_left(&mut self, mut cur_node: Option<&'a PersistentNode<K, V>>) {
+        while let Some(node) = cur_node {
+            self.stack.push(node);
+            cur_node = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for PersistentIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for PersistentIter<'a, K, V> {}
+
+impl<K, V> PersistentNode<K, V> {
+    #[inline]
+    fn new(key: K, value: V) -> Self {
+        PersistentNode {
+            key,
+            value,
+            height: 1,
+            left: None,
+            right: None,
+        }
+    }
+
+    #[inline]
+    fn update_height(&mut self) {
+        self.height = 1 + height(&self.left).max(height(&self.right));
+    }
+
+    #[inline]
+    fn balance_factor(&self) -> i64 {
+        height(&self.left) as i64 - height(&self.right) as i64
+    }
+}
+
+#[inline]
+fn height<K, V>(link: &Link<K, V>) -> u32 {
+    link.as_ref().map(|n| n.height).unwrap_or(0)
+}
+
+fn insert_node<K: Ord + Clone, V: Clone>(link: &mut Link<K, V>, key: K, value: V) -> Option<V> {
+    let node = match link {
+        Some(node) => Arc::make_mut(node),
+        None => {
+            *link = Some(Arc::new(PersistentNode::new(key, value)));
+            return None;
+        },
+    };
+
+    let old = match key.cmp(&node.key) {
+        Ordering::Less => insert_node(&mut node.left, key, value),
+        Ordering::Equal => return Some(mem::replace(&mut node.value, value)),
+        Ordering::Greater => insert_node(&mut node.right, key, value),
+    };
+
+    rebalance(link);
+    old
+}
+
+fn remove_node<K: Ord + Clone, V: Clone>(link: &mut Link<K, V>, key: &K) -> Option<V> {
+    let node = Arc::make_mut(link.as_mut()?);
+
+    let value = match key.cmp(&node.key) {
+        Ordering::Less => remove_node(&mut node.left, key),
+        Ordering::Greater => remove_node(&mut node.right, key),
+        Ordering::Equal => {
+            let node = into_owned(link.take()?);
+            *link = match (node.left, node.right) {
+                (None, right) => right,
+                (left, None) => left,
+                (left, mut right) => {
+                    let mut min = remove_min(&mut right);
+                    min.left = left;
+                    min.right = right;
+                    Some(Arc::new(min))
+                },
+            };
+            Some(node.value)
+        },
+    };
+
+    rebalance(link);
+    value
+}
+
+fn remove_min<K: Clone, V: Clone>(link: &mut Link<K, V>) -> PersistentNode<K, V> {
+    let node = Arc::make_mut(link.as_mut().expect("remove_min on an empty subtree"));
+
+    if node.left.is_some() {
+        let min = remove_min(&mut node.left);
+        rebalance(link);
+        min
+    } else {
+        let mut min = into_owned(link.take().unwrap());
+        *link = min.right.take();
+        min
+    }
+}
+
+#[inline]
+fn into_owned<K: Clone, V: Clone>(node: Arc<PersistentNode<K, V>>) -> PersistentNode<K, V> {
+    Arc::try_unwrap(node).unwrap_or_else(|shared| (*shared).clone())
+}
+
+fn rebalance<K: Clone, V: Clone>(link: &mut Link<K, V>) {
+    let mut root = match link.take() {
+        Some(root) => root,
+        None => return,
+    };
+
+    let node = Arc::make_mut(&mut root);
+    node.update_height();
+    let balance = node.balance_factor();
+
+    *link = Some(if balance > 1 {
+        if node.left.as_ref().map(|l| l.balance_factor() < 0).unwrap_or(false) {
+            node.left = node.left.take().map(rotate_left);
+        }
+        rotate_right(root)
+    } else if balance < -1 {
+        if node.right.as_ref().map(|r| r.balance_factor() > 0).unwrap_or(false) {
+            node.right = node.right.take().map(rotate_right);
+        }
+        rotate_left(root)
+    } else {
+        root
+    });
+}
+
+fn rotate_right<K: Clone, V: Clone>(mut root: Arc<PersistentNode<K, V>>) -> Arc<PersistentNode<K, V>> {
+    let node = Arc::make_mut(&mut root);
+    let mut left = match node.left.take() {
+        Some(left) => left,
+        None => return root,
+    };
+    let left_node = Arc::make_mut(&mut left);
+    node.left = left_node.right.take();
+    node.update_height();
+    left_node.right = Some(root);
+    left_node.update_height();
+    left
+}
+
+fn rotate_left<K: Clone, V: Clone>(mut root: Arc<PersistentNode<K, V>>) -> Arc<PersistentNode<K, V>> {
+    let node = Arc::make_mut(&mut root);
+    let mut right = match node.right.take() {
+        Some(right) => right,
+        None => return root,
+    };
+    let right_node = Arc::make_mut(&mut right);
+    node.right = right_node.left.take();
+    node.update_height();
+    right_node.left = Some(root);
+    right_node.update_height();
+    right
+}
@@ -0,0 +1,183 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::ops::RangeBounds;
+
+use crate::{Iter, Node, SplayTree};
+
+/// `SplayTree` mirrored by a `BTreeMap`, for diagnosing suspected corruption of
+/// a tree in a downstream application.
+///
+/// Every mutation is applied to both maps, and after every operation the wrapper
+/// asserts that they agree on the result, the length and the sorted contents.
+/// Lookups and removals have the same signatures as on `SplayTree`, but
+/// mutable access to values goes through [`update`](ShadowTree::update), and
+/// [`insert`](ShadowTree::insert) returns the replaced value instead of a
+/// handle to the node, so code switched over to the wrapper may need small
+/// changes at those call sites.
+///
+/// Checks take *O*(*n*) time, so the wrapper is only meant for debugging.
+pub struct ShadowTree<K, V> {
+    tree: SplayTree<K, V>,
+    shadow: BTreeMap<K, V>,
+}
+
+impl<K, V> ShadowTree<K, V>
+    where K: Ord + Clone + Debug, V: Clone + PartialEq + Debug
+{
+    /// Creates an empty `ShadowTree`.
+    #[inline]
+    pub fn new() -> Self {
+        ShadowTree { tree: SplayTree::new(), shadow: BTreeMap::new() }
+    }
+
+    /// Returns a reference to the node with a key, or `None` if the tree doesn't
+    /// contain that key.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized + Ord + Debug
+    {
+        let expected = self.shadow.get(key);
+        let found = self.tree.get(key);
+        assert_eq!(found.map(Node::value), expected, "lookup of {:?} disagrees with the shadow", key);
+        found
+    }
+
+    /// Returns `true` if the tree contains a value for the specified key.
+    #[inline]
+    pub fn contains_key<Q>(&mut self, key: &Q) -> bool
+        where K: Borrow<Q>, Q: ?Sized + Ord + Debug
+    {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value to the tree with a key and returns the replaced value, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let expected = self.shadow.insert(key.clone(), value.clone());
+        let replaced = match self.tree.entry(key) {
+            crate::Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            crate::Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            },
+        };
+        assert_eq!(replaced, expected, "insertion disagrees with the shadow");
+        self.check();
+        replaced
+    }
+
+    /// Calls `f` with a mutable reference to the value by a key and returns its
+    /// result, or `None` if the tree doesn't contain that key.
+    pub fn update<R, F: FnOnce(&mut V) -> R>(&mut self, key: &K, f: F) -> Option<R> {
+        let result = match self.tree.get_mut(key) {
+            Some(mut node) => {
+                let result = f(node.value_mut());
+                let value = node.value().clone();
+                let shadow = self.shadow.get_mut(key)
+                    .unwrap_or_else(|| panic!("{:?} is missing from the shadow", key));
+                *shadow = value;
+                Some(result)
+            },
+            None => {
+                assert!(!self.shadow.contains_key(key), "{:?} is missing from the tree", key);
+                None
+            },
+        };
+        self.check();
+        result
+    }
+
+    /// Removes an entry with a given key and returns its node, or `None` if the
+    /// tree doesn't contain that key.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Box<Node<K, V>>>
+        where K: Borrow<Q>, Q: ?Sized + Ord + Debug
+    {
+        let expected = self.shadow.remove(key);
+        let removed = self.tree.remove(key);
+        assert_eq!(removed.as_deref().map(Node::value), expected.as_ref(),
+                   "removal of {:?} disagrees with the shadow", key);
+        self.check();
+        removed
+    }
+
+    /// Removes all entries with keys in `range` and returns their number.
+    pub fn remove_range<Q, R>(&mut self, range: R) -> usize
+        where K: Borrow<Q>, Q: ?Sized + Ord, R: RangeBounds<Q>
+    {
+        let before = self.shadow.len();
+        self.shadow.retain(|key, _| !range.contains(key.borrow()));
+        let removed = self.tree.remove_range(range);
+        assert_eq!(removed, before - self.shadow.len(), "range removal disagrees with the shadow");
+        self.check();
+        removed
+    }
+
+    /// Removes all entries from the tree.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.shadow.clear();
+        self.check();
+    }
+
+    /// Returns the length of the tree.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns `true` if the tree contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Gets an iterator over the entries of the tree, sorted by key.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.tree.iter()
+    }
+
+    /// Returns a reference to the underlying tree.
+    #[inline]
+    pub fn as_tree(&self) -> &SplayTree<K, V> {
+        &self.tree
+    }
+
+    /// Panics if the tree and the shadow differ in length or contents.
+    pub fn check(&self) {
+        self.tree.debug_validate();
+        assert_eq!(self.tree.len(), self.shadow.len(), "length disagrees with the shadow");
+        if let Some(((key, value), expected)) = self.tree.iter()
+            .zip(&self.shadow)
+            .find(|&(entry, expected)| entry != expected)
+        {
+            panic!("entry ({:?}, {:?}) disagrees with the shadow entry {:?}", key, value, expected);
+        }
+    }
+
+    /// Checks the tree and returns it, dropping the shadow.
+    #[inline]
+    pub fn into_tree(self) -> SplayTree<K, V> {
+        self.check();
+        self.tree
+    }
+}
+
+impl<K, V> Default for ShadowTree<K, V>
+    where K: Ord + Clone + Debug, V: Clone + PartialEq + Debug
+{
+    #[inline]
+    fn default() -> Self {
+        ShadowTree::new()
+    }
+}
+
+impl<K, V> From<SplayTree<K, V>> for ShadowTree<K, V>
+    where K: Ord + Clone + Debug, V: Clone + PartialEq + Debug
+{
+    /// Wraps a tree, copying its entries into the shadow.
+    #[inline]
+    fn from(tree: SplayTree<K, V>) -> Self {
+        let shadow = tree.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+        ShadowTree { tree, shadow }
+    }
+}
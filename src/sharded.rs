@@ -0,0 +1,169 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FusedIterator, Peekable};
+use std::vec;
+
+use crate::{SplayTree, SyncSplayTree, Natural};
+
+/// Concurrent map partitioning keys by hash over independently locked splay trees.
+///
+/// Operations on different shards don't contend with each other, while every shard
+/// keeps adapting to its own access pattern. Sorted iteration merges the shards.
+///
+/// All methods panic if a shard's lock was poisoned by a panic in another thread.
+pub struct ShardedSplayMap<K: Ord, V> {
+    shards: Vec<SyncSplayTree<K, V>>,
+    hasher: RandomState,
+}
+
+impl<K: Ord + Hash, V> ShardedSplayMap<K, V> {
+    /// Creates an empty `ShardedSplayMap` with a given number of shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn new(shards: usize) -> Self {
+        assert!(shards > 0, "ShardedSplayMap needs at least one shard");
+        ShardedSplayMap {
+            shards: (0..shards).map(|_| SyncSplayTree::new()).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Calls `f` with a reference to the value by a key and returns its result,
+    /// or `None` if the map doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_with<R, F: FnOnce(&V) -> R>(&self, key: &K, f: F) -> Option<R> {
+        self.shard(key).get_with(key, f)
+    }
+
+    /// Calls `f` with a mutable reference to the value by a key and returns its
+    /// result, or `None` if the map doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_mut_with<R, F: FnOnce(&mut V) -> R>(&self, key: &K, f: F) -> Option<R> {
+        self.shard(key).get_mut_with(key, f)
+    }
+
+    /// Inserts a value to the map with a key and returns the replaced value, if any.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.shard(&key).insert(key, value)
+    }
+
+    /// Removes an entry with a given key and returns its value, or `None` if the map
+    /// doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.shard(key).remove(key)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.shard(key).contains_key(key)
+    }
+
+    #[inline]
+    fn shard(&self, key: &K) -> &SyncSplayTree<K, V> {
+        let idx = self.hasher.hash_one(key) as usize % self.shards.len();
+        &self.shards[idx]
+    }
+}
+
+impl<K: Ord, V> ShardedSplayMap<K, V> {
+    /// Returns the number of shards.
+    #[inline]
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the length of the map. Shards are locked one by one, so the result
+    /// may be stale if other threads are modifying the map.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_empty())
+    }
+
+    /// Consumes the map and returns an iterator over its entries, sorted by key.
+    pub fn into_sorted_iter(self) -> MergeIter<crate::IntoIter<K, V>> {
+        MergeIter::new(self.shards.into_iter().map(|shard| shard.into_inner().into_iter()))
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> ShardedSplayMap<K, V> {
+    /// Returns an iterator over clones of the entries, sorted by key.
+    ///
+    /// All shards are locked while their entries are copied, so the iterator
+    /// observes a consistent snapshot of the map.
+    pub fn iter(&self) -> MergeIter<vec::IntoIter<(K, V)>> {
+        let guards: Vec<_> = self.shards.iter().map(|shard| shard.lock()).collect();
+        let snapshots: Vec<Vec<(K, V)>> = guards.iter().map(|tree| {
+            tree.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+        }).collect();
+        drop(guards);
+
+        MergeIter::new(snapshots.into_iter().map(|entries| entries.into_iter()))
+    }
+}
+
+impl<K: Ord, V> From<ShardedSplayMap<K, V>> for SplayTree<K, V> {
+    /// Merges the shards into a single balanced tree.
+    #[inline]
+    fn from(map: ShardedSplayMap<K, V>) -> Self {
+        let entries: Vec<(K, V)> = map.into_sorted_iter().collect();
+        SplayTree::from_sorted_iter(entries.into_iter(), Natural)
+    }
+}
+
+/// Iterator merging several iterators sorted by key into one.
+pub struct MergeIter<I: Iterator> {
+    sources: Vec<Peekable<I>>,
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>> MergeIter<I> {
+    #[inline]
+    fn new<S: Iterator<Item = I>>(sources: S) -> Self {
+        MergeIter { sources: sources.map(Iterator::peekable).collect() }
+    }
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>> Iterator for MergeIter<I> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        let mut min_idx = None;
+        let mut min_key = None;
+
+        for (idx, source) in self.sources.iter_mut().enumerate() {
+            if let Some((key, _)) = source.peek() {
+                if min_key.map(|min| key < min).unwrap_or(true) {
+                    min_key = Some(key);
+                    min_idx = Some(idx);
+                }
+            }
+        }
+
+        self.sources[min_idx?].next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sources.iter().fold((0, Some(0)), |(lo, hi), source| {
+            let (s_lo, s_hi) = source.size_hint();
+            (lo + s_lo, hi.and_then(|hi| Some(hi + s_hi?)))
+        })
+    }
+}
+
+impl<K: Ord, V, I: FusedIterator<Item = (K, V)>> FusedIterator for MergeIter<I> {}
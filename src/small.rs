@@ -0,0 +1,277 @@
+use std::array;
+use std::borrow::Borrow;
+use std::iter::FusedIterator;
+use std::mem;
+use std::slice;
+
+use crate::{Compare, Iter, Natural, NodeMut, SplayTreeBy};
+
+/// Ordered map which keeps up to `N` entries in an inline sorted array and
+/// switches to a `SplayTreeBy` once it grows beyond that.
+///
+/// Small maps then need no allocation at all, and lookups run a binary search
+/// over a contiguous array instead of chasing pointers. Once the tree becomes
+/// empty again, the map goes back to the array. Lookups in the array don't
+/// reorder anything.
+pub struct SmallSplayMap<K, V, C = Natural, const N: usize = 16> {
+    inline: [Option<(K, V)>; N],
+    len: usize,
+    tree: SplayTreeBy<K, V, C>,
+}
+
+impl<K, V, C: Compare<K> + Default, const N: usize> SmallSplayMap<K, V, C, N> {
+    /// Creates an empty `SmallSplayMap`.
+    #[inline]
+    pub fn new() -> Self {
+        SmallSplayMap::with_comparator(C::default())
+    }
+}
+
+impl<K, V, C: Compare<K>, const N: usize> SmallSplayMap<K, V, C, N> {
+    /// Creates an empty `SmallSplayMap` ordering keys by `cmp`.
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        SmallSplayMap {
+            inline: array::from_fn(|_| None),
+            len: 0,
+            tree: SplayTreeBy::with_comparator(cmp),
+        }
+    }
+
+    /// Returns a reference to the value by a key, or `None` if the map doesn't
+    /// contain that key.
+    ///
+    /// This operation should compute in *O*(*log N*) time while the entries are
+    /// inline, and in amortized *O*(*log n*) time afterwards.
+    #[inline]
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        if !self.is_inline() {
+            return self.tree.get(key).map(|node| node.value());
+        }
+        let idx = self.search(key).ok()?;
+        self.inline[idx].as_ref().map(|(_, value)| value)
+    }
+
+    /// Returns a mutable reference to the value by a key, or `None` if the map
+    /// doesn't contain that key.
+    ///
+    /// This operation should compute in *O*(*log N*) time while the entries are
+    /// inline, and in amortized *O*(*log n*) time afterwards.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        if !self.is_inline() {
+            return self.tree.get_mut(key).map(NodeMut::into_value_mut);
+        }
+        let idx = self.search(key).ok()?;
+        self.inline[idx].as_mut().map(|(_, value)| value)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    #[inline]
+    pub fn contains_key<Q>(&mut self, key: &Q) -> bool
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value to the map with a key and returns the replaced value, if any.
+    /// The key isn't updated if it's already in the map. If the inline array
+    /// is full, its entries are moved to a tree.
+    ///
+    /// This operation should compute in *O*(*N*) time while the entries are
+    /// inline, and in amortized *O*(*log n*) time afterwards.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if !self.is_inline() {
+            return self.tree_insert(key, value);
+        }
+        match self.search(&key) {
+            Ok(idx) => {
+                let (_, old) = self.inline[idx].as_mut().expect("inline entry is empty");
+                Some(mem::replace(old, value))
+            },
+            Err(_) if self.len == N => {
+                self.promote();
+                self.tree_insert(key, value)
+            },
+            Err(idx) => {
+                self.inline[self.len] = Some((key, value));
+                self.inline[idx..=self.len].rotate_right(1);
+                self.len += 1;
+                None
+            },
+        }
+    }
+
+    /// Removes an entry with a given key and returns its value, or `None` if
+    /// the map doesn't contain that key.
+    ///
+    /// This operation should compute in *O*(*N*) time while the entries are
+    /// inline, and in amortized *O*(*log n*) time afterwards.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        if !self.is_inline() {
+            return self.tree.remove(key).map(|node| node.value);
+        }
+        let idx = self.search(key).ok()?;
+        let (_, value) = self.inline[idx].take().expect("inline entry is empty");
+        self.inline[idx..self.len].rotate_left(1);
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Returns the number of entries in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len + self.tree.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the entries are kept in the inline array.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Gets an iterator over the entries of the map, sorted by key.
+    #[inline]
+    pub fn iter(&self) -> SmallIter<'_, K, V> {
+        let inner = if self.is_inline() {
+            Entries::Inline(self.inline[..self.len].iter())
+        } else {
+            Entries::Tree(self.tree.iter())
+        };
+        SmallIter { inner }
+    }
+
+    /// Removes all entries from the map.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.inline[..self.len].iter_mut().for_each(|entry| *entry = None);
+        self.len = 0;
+        self.tree.clear();
+    }
+
+    fn search<Q>(&self, key: &Q) -> Result<usize, usize>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let cmp = self.tree.comparator();
+        self.inline[..self.len].binary_search_by(|entry| {
+            let (entry_key, _) = entry.as_ref().expect("inline entry is empty");
+            cmp.compare(entry_key.borrow(), key)
+        })
+    }
+
+    fn tree_insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.tree.entry(key) {
+            crate::Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            crate::Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            },
+        }
+    }
+
+    /// Moves the inline entries to the tree. Every entry is greater than the
+    /// previous maximum, so it's linked below the root with a single rotation.
+    fn promote(&mut self) {
+        let len = mem::take(&mut self.len);
+        for (key, value) in self.inline[..len].iter_mut().filter_map(Option::take) {
+            self.tree.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, C: Compare<K> + Default, const N: usize> Default for SmallSplayMap<K, V, C, N> {
+    #[inline]
+    fn default() -> Self {
+        SmallSplayMap::new()
+    }
+}
+
+impl<K, V, C: Compare<K>, const N: usize> Extend<(K, V)> for SmallSplayMap<K, V, C, N> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, C: Compare<K> + Default, const N: usize> FromIterator<(K, V)> for SmallSplayMap<K, V, C, N> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = SmallSplayMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, K, V, C: Compare<K>, const N: usize> IntoIterator for &'a SmallSplayMap<K, V, C, N> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = SmallIter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> SmallIter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of a `SmallSplayMap`, sorted by key.
+pub struct SmallIter<'a, K, V> {
+    inner: Entries<'a, K, V>,
+}
+
+enum Entries<'a, K, V> {
+    Inline(slice::Iter<'a, Option<(K, V)>>),
+    Tree(Iter<'a, K, V>),
+}
+
+#[inline]
+fn inline_entry<K, V>(entry: &Option<(K, V)>) -> (&K, &V) {
+    let (key, value) = entry.as_ref().expect("inline entry is empty");
+    (key, value)
+}
+
+impl<'a, K, V> Iterator for SmallIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        match &mut self.inner {
+            Entries::Inline(inner) => inner.next().map(inline_entry),
+            Entries::Tree(inner) => inner.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            Entries::Inline(inner) => inner.size_hint(),
+            Entries::Tree(inner) => inner.size_hint(),
+        }
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for SmallIter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        match &mut self.inner {
+            Entries::Inline(inner) => inner.next_back().map(inline_entry),
+            Entries::Tree(inner) => inner.next_back(),
+        }
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for SmallIter<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for SmallIter<'a, K, V> {}
@@ -0,0 +1,25 @@
+/// Counters of the work done by a `SplayTree`, returned by [`SplayTree::stats`].
+///
+/// [`SplayTree::stats`]: crate::SplayTree::stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    /// Number of single rotations.
+    pub rotations: u64,
+    /// Number of zig, zig-zig and zig-zag steps.
+    pub splay_steps: u64,
+    /// Number of key comparisons made while searching.
+    pub comparisons: u64,
+    /// Number of nodes allocated by insertions.
+    pub allocations: u64,
+    /// Number of nodes unlinked by removals.
+    pub frees: u64,
+}
+
+impl TreeStats {
+    /// Records splaying a node which was `depth` edges below the root.
+    #[inline]
+    pub(crate) fn record_splay(&mut self, depth: usize) {
+        self.rotations += depth as u64;
+        self.splay_steps += (depth as u64).div_ceil(2);
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::Debug;
+
+use proptest::collection::{btree_map, vec, SizeRange};
+use proptest::sample::Index;
+use proptest::strategy::Strategy;
+
+use crate::SplayTree;
+
+/// Operation on a `SplayTree`, generated by [`operation_strategy`] for stateful
+/// property tests which run a program against a tree and a model side by side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeOp<K, V> {
+    /// Inserts a value with a key.
+    Insert(K, V),
+    /// Removes an entry by a key.
+    Remove(K),
+    /// Looks up a value by a key.
+    Get(K),
+}
+
+/// Strategy generating trees with keys and values from the given strategies and
+/// a number of entries in `size`.
+///
+/// After being built, every tree is splayed by a random sequence of lookups, so
+/// trees come in the unbalanced shapes which real workloads leave behind.
+pub fn splay_tree_strategy<K, V>(
+    key: impl Strategy<Value = K>,
+    value: impl Strategy<Value = V>,
+    size: impl Into<SizeRange>,
+) -> impl Strategy<Value = SplayTree<K, V>>
+    where K: Ord + Clone + Debug, V: Clone + Debug
+{
+    let size = size.into();
+    (btree_map(key, value, size.clone()), vec(proptest::arbitrary::any::<Index>(), size))
+        .prop_map(|(entries, accesses)| {
+            let keys: Vec<K> = entries.keys().cloned().collect();
+            let mut tree = SplayTree::from(entries);
+            if !keys.is_empty() {
+                for access in accesses {
+                    tree.get(&keys[access.index(keys.len())]);
+                }
+            }
+            tree
+        })
+}
+
+/// Strategy generating a single insertion, removal or lookup with keys and values
+/// from the given strategies.
+pub fn operation_strategy<K, V>(
+    key: impl Strategy<Value = K>,
+    value: impl Strategy<Value = V>,
+) -> impl Strategy<Value = TreeOp<K, V>>
+    where K: Debug, V: Debug
+{
+    (0..3u8, key, value).prop_map(|(kind, key, value)| match kind {
+        0 => TreeOp::Insert(key, value),
+        1 => TreeOp::Remove(key),
+        _ => TreeOp::Get(key),
+    })
+}
+
+/// Strategy generating programs of insertions, removals and lookups with a number
+/// of operations in `size`.
+pub fn operations_strategy<K, V>(
+    key: impl Strategy<Value = K>,
+    value: impl Strategy<Value = V>,
+    size: impl Into<SizeRange>,
+) -> impl Strategy<Value = Vec<TreeOp<K, V>>>
+    where K: Debug, V: Debug
+{
+    vec(operation_strategy(key, value), size)
+}
@@ -0,0 +1,75 @@
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{FusedStream, Stream};
+
+use crate::{IntoIter, SplayTreeBy};
+
+/// Stream draining a `SplayTree` in chunks of owned entries, sorted by key.
+///
+/// After every chunk, the stream returns `Poll::Pending` once and wakes its task
+/// right away, so a task draining a huge tree lets the other tasks of the executor
+/// run between chunks, even if it never awaits anything else. Entries which
+/// haven't been yielded are dropped with the stream.
+///
+/// Created by [`SplayTreeBy::into_stream`].
+pub struct IntoStream<K, V> {
+    inner: IntoIter<K, V>,
+    chunk_size: usize,
+    should_yield: bool,
+}
+
+impl<K, V, C> SplayTreeBy<K, V, C> {
+    /// Converts the tree into a stream of chunks of `chunk_size` entries, sorted
+    /// by key, which yields to the executor between chunks. The last chunk may be
+    /// shorter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    #[inline]
+    pub fn into_stream(self, chunk_size: usize) -> IntoStream<K, V> {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+        IntoStream {
+            inner: self.into_iter(),
+            chunk_size,
+            should_yield: false,
+        }
+    }
+}
+
+// The stream never pins its fields.
+impl<K, V> Unpin for IntoStream<K, V> {}
+
+impl<K, V> Stream for IntoStream<K, V> {
+    type Item = Vec<(K, V)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.inner.len() == 0 {
+            return Poll::Ready(None);
+        }
+        if mem::take(&mut this.should_yield) {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let chunk: Vec<(K, V)> = this.inner.by_ref().take(this.chunk_size).collect();
+        this.should_yield = true;
+        Poll::Ready(Some(chunk))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunks = self.inner.len().div_ceil(self.chunk_size);
+        (chunks, Some(chunks))
+    }
+}
+
+impl<K, V> FusedStream for IntoStream<K, V> {
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        self.inner.len() == 0
+    }
+}
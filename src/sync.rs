@@ -0,0 +1,126 @@
+use std::sync::{Mutex, MutexGuard};
+use std::mem;
+
+use crate::SplayTree;
+
+/// `SplayTree` behind an internal lock, which can be shared between threads.
+///
+/// Splaying mutates the tree even on lookups, so readers can't share a read guard
+/// and every operation takes the lock exclusively. Values are returned either
+/// cloned or through a closure running under the lock.
+///
+/// All methods panic if the lock was poisoned by a panic in another thread.
+pub struct SyncSplayTree<K: Ord, V> {
+    tree: Mutex<SplayTree<K, V>>,
+}
+
+impl<K: Ord, V> SyncSplayTree<K, V> {
+    /// Creates an empty `SyncSplayTree`.
+    #[inline]
+    pub fn new() -> Self {
+        SyncSplayTree::from(SplayTree::new())
+    }
+
+    /// Calls `f` with a reference to the value by a key and returns its result,
+    /// or `None` if the tree doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_with<R, F: FnOnce(&V) -> R>(&self, key: &K, f: F) -> Option<R> {
+        self.lock().get(key).map(|node| f(node.value()))
+    }
+
+    /// Calls `f` with a mutable reference to the value by a key and returns its
+    /// result, or `None` if the tree doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_mut_with<R, F: FnOnce(&mut V) -> R>(&self, key: &K, f: F) -> Option<R> {
+        self.lock().get_mut(key).map(|node| f(node.into_value_mut()))
+    }
+
+    /// Inserts a value to the tree with a key and returns the replaced value, if any.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let mut tree = self.lock();
+        if let Some(mut node) = tree.get_mut(&key) {
+            return Some(mem::replace(node.value_mut(), value));
+        }
+
+        tree.insert(key, value);
+        None
+    }
+
+    /// Removes an entry with a given key and returns its value, or `None` if the tree
+    /// doesn't contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.lock().remove(key).map(|node| node.value)
+    }
+
+    /// Returns `true` if the tree contains a value for the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.lock().contains_key(key)
+    }
+
+    /// Returns the length of the tree.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the tree contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Locks the tree and returns a guard giving exclusive access to it,
+    /// for operations which aren't covered by the wrapper.
+    #[inline]
+    pub fn lock(&self) -> MutexGuard<'_, SplayTree<K, V>> {
+        self.tree.lock().expect("SyncSplayTree lock poisoned")
+    }
+
+    /// Returns a mutable reference to the underlying tree. No locking is needed,
+    /// since the wrapper is borrowed mutably.
+    #[inline]
+    pub fn get_tree_mut(&mut self) -> &mut SplayTree<K, V> {
+        self.tree.get_mut().expect("SyncSplayTree lock poisoned")
+    }
+
+    /// Consumes the wrapper and returns the underlying tree.
+    #[inline]
+    pub fn into_inner(self) -> SplayTree<K, V> {
+        self.tree.into_inner().expect("SyncSplayTree lock poisoned")
+    }
+}
+
+impl<K: Ord, V: Clone> SyncSplayTree<K, V> {
+    /// Returns a clone of the value by a key, or `None` if the tree doesn't
+    /// contain that key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.get_with(key, V::clone)
+    }
+}
+
+impl<K: Ord, V> From<SplayTree<K, V>> for SyncSplayTree<K, V> {
+    #[inline]
+    fn from(tree: SplayTree<K, V>) -> Self {
+        SyncSplayTree { tree: Mutex::new(tree) }
+    }
+}
+
+impl<K: Ord, V> Default for SyncSplayTree<K, V> {
+    #[inline]
+    fn default() -> Self {
+        SyncSplayTree::new()
+    }
+}
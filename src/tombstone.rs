@@ -0,0 +1,255 @@
+use std::borrow::Borrow;
+use std::iter::FusedIterator;
+
+use crate::{Compare, Entry, Iter, Natural, SplayTreeBy};
+
+const DEFAULT_PURGE_RATIO: f64 = 0.5;
+
+/// Ordered map built on top of `SplayTreeBy`, which removes entries lazily.
+///
+/// A removal only marks the node of an entry as deleted, leaving the links of the
+/// tree untouched, and lookups skip deleted nodes. Once the share of deleted nodes
+/// exceeds the purge ratio, they are dropped at once and the tree is rebuilt
+/// balanced. It trades memory for fewer restructures during bursts of removals,
+/// and inserting a removed key again reuses its node.
+pub struct TombstoneTree<K, V, C = Natural> {
+    tree: SplayTreeBy<K, Option<V>, C>,
+    tombstones: usize,
+    purge_ratio: f64,
+}
+
+impl<K, V, C: Compare<K> + Default> TombstoneTree<K, V, C> {
+    /// Creates an empty `TombstoneTree`, which purges deleted nodes once they make
+    /// up half of the tree.
+    #[inline]
+    pub fn new() -> Self {
+        TombstoneTree::with_comparator(C::default())
+    }
+}
+
+impl<K, V, C: Compare<K>> TombstoneTree<K, V, C> {
+    /// Creates an empty `TombstoneTree` ordering keys by `cmp`.
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        TombstoneTree {
+            tree: SplayTreeBy::with_comparator(cmp),
+            tombstones: 0,
+            purge_ratio: DEFAULT_PURGE_RATIO,
+        }
+    }
+
+    /// Sets the share of deleted nodes in the tree, above which they are purged,
+    /// and purges them if it's already exceeded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` isn't in the range `(0, 1]`.
+    pub fn set_purge_ratio(&mut self, ratio: f64) {
+        assert!(ratio > 0.0 && ratio <= 1.0, "purge ratio must be in (0, 1]");
+        self.purge_ratio = ratio;
+        self.purge_if_needed();
+    }
+
+    /// Returns the share of deleted nodes in the tree, above which they are purged.
+    #[inline]
+    pub fn purge_ratio(&self) -> f64 {
+        self.purge_ratio
+    }
+
+    /// Returns a reference to the value by a key, or `None` if the tree doesn't
+    /// contain that key or its entry is removed.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.tree.get(key).and_then(|node| node.value().as_ref())
+    }
+
+    /// Returns a mutable reference to the value by a key, or `None` if the tree
+    /// doesn't contain that key or its entry is removed.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.tree.get_mut(key).and_then(|node| node.into_value_mut().as_mut())
+    }
+
+    /// Returns `true` if the tree contains a value for the specified key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn contains_key<Q>(&mut self, key: &Q) -> bool
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value to the tree with a key and returns the replaced value, if any.
+    /// If the entry of the key is removed, its node is reused.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.tree.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let old = entry.insert(Some(value));
+                if old.is_none() {
+                    self.tombstones -= 1;
+                }
+                old
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(Some(value));
+                None
+            },
+        }
+    }
+
+    /// Marks the entry with a given key as removed and returns its value, or `None`
+    /// if the tree doesn't contain that key. Deleted nodes are purged if their
+    /// share exceeds the purge ratio.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time, and in *O*(*n*)
+    /// time when it purges deleted nodes.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let value = self.tree.get_mut(key)?.value_mut().take()?;
+        self.tombstones += 1;
+        self.purge_if_needed();
+        Some(value)
+    }
+
+    /// Frees all deleted nodes and rebuilds the tree balanced.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn purge(&mut self) {
+        if self.tombstones > 0 {
+            self.tree.rebuild_retaining(|_, value| value.is_some());
+            self.tree.shrink_to_fit();
+            self.tombstones = 0;
+        }
+    }
+
+    /// Returns the number of deleted nodes, which are still in the tree.
+    #[inline]
+    pub fn tombstones(&self) -> usize {
+        self.tombstones
+    }
+
+    /// Returns the number of entries in the tree, not counting removed ones.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tree.len() - self.tombstones
+    }
+
+    /// Returns `true` if the tree contains no entries, not counting removed ones.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets an iterator over the entries of the tree, sorted by key.
+    /// Removed entries are skipped.
+    #[inline]
+    pub fn iter(&self) -> LiveIter<'_, K, V> {
+        LiveIter { inner: self.tree.iter(), remaining: self.len() }
+    }
+
+    /// Returns an approximate number of bytes of heap memory held by the tree,
+    /// including deleted nodes. Heap memory owned by keys and values themselves
+    /// isn't counted.
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.tree.memory_usage()
+    }
+
+    fn purge_if_needed(&mut self) {
+        if self.tombstones as f64 > self.purge_ratio * self.tree.len() as f64 {
+            self.purge();
+        }
+    }
+}
+
+impl<K, V, C> TombstoneTree<K, V, C> {
+    /// Removes all entries from the tree, including deleted nodes.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.tombstones = 0;
+    }
+}
+
+impl<K, V, C: Compare<K> + Default> Default for TombstoneTree<K, V, C> {
+    #[inline]
+    fn default() -> Self {
+        TombstoneTree::new()
+    }
+}
+
+impl<K, V, C: Compare<K>> Extend<(K, V)> for TombstoneTree<K, V, C> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, C: Compare<K> + Default> FromIterator<(K, V)> for TombstoneTree<K, V, C> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = TombstoneTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<'a, K, V, C: Compare<K>> IntoIterator for &'a TombstoneTree<K, V, C> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = LiveIter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> LiveIter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of a `TombstoneTree`, sorted by key.
+pub struct LiveIter<'a, K, V> {
+    inner: Iter<'a, K, Option<V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for LiveIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let entry = self.inner.find_map(|(key, value)| Some((key, value.as_ref()?)))?;
+        self.remaining -= 1;
+        Some(entry)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for LiveIter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        let entry = self.inner.rfind(|(_, value)| value.is_some())
+            .and_then(|(key, value)| Some((key, value.as_ref()?)))?;
+        self.remaining -= 1;
+        Some(entry)
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for LiveIter<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for LiveIter<'a, K, V> {}
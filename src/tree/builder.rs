@@ -0,0 +1,149 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+use crate::{Compare, Natural, Node, SplayTreeBy};
+
+/// Builder of a tree with an explicit shape, for deterministic benchmarks and
+/// tests of code which depends on the depth of nodes.
+///
+/// The shape is given in level order: the first entry is the root, and every
+/// following pair of positions holds the left and the right child of the next
+/// node of the previous levels. A hole marks a missing child, and holes don't
+/// have children themselves, so only the positions under entries are listed.
+pub struct TreeBuilder<K, V, C = Natural> {
+    slots: Vec<Option<(K, V)>>,
+    cmp: C,
+}
+
+/// Error of [`TreeBuilder::build`], holding the level-order position of the
+/// offending entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeError {
+    /// The entry has no parent, because the root is a hole or there are no
+    /// entries left to hang it on.
+    Orphan(usize),
+    /// The key of the entry isn't greater than the key of its in-order predecessor.
+    Unordered(usize),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::Orphan(pos) => write!(f, "entry at position {} has no parent", pos),
+            ShapeError::Unordered(pos) => write!(f, "entry at position {} is out of order", pos),
+        }
+    }
+}
+
+impl Error for ShapeError {}
+
+impl<K: Ord, V> TreeBuilder<K, V> {
+    /// Creates an empty builder of a `SplayTree`.
+    #[inline]
+    pub fn new() -> Self {
+        TreeBuilder::with_comparator(Natural)
+    }
+}
+
+impl<K: Ord, V> Default for TreeBuilder<K, V> {
+    #[inline]
+    fn default() -> Self {
+        TreeBuilder::new()
+    }
+}
+
+impl<K, V, C: Compare<K>> TreeBuilder<K, V, C> {
+    /// Creates an empty builder of a tree ordering keys by `cmp`.
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        TreeBuilder { slots: Vec::new(), cmp }
+    }
+
+    /// Appends an entry at the next position.
+    #[inline]
+    pub fn node(mut self, key: K, value: V) -> Self {
+        self.slots.push(Some((key, value)));
+        self
+    }
+
+    /// Appends a hole at the next position.
+    #[inline]
+    pub fn hole(mut self) -> Self {
+        self.slots.push(None);
+        self
+    }
+
+    /// Builds the tree, or returns an error if an entry has no parent or the keys
+    /// aren't ordered by the shape. Trailing holes are ignored.
+    ///
+    /// Keys are compared before any node is allocated, so nothing leaks if the
+    /// comparator panics.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn build(self) -> Result<SplayTreeBy<K, V, C>, ShapeError> {
+        let TreeBuilder { slots, cmp } = self;
+        let mut children = vec![(None, None); slots.len()];
+
+        let mut queue = VecDeque::new();
+        if let Some(Some(_)) = slots.first() {
+            queue.push_back(0);
+        }
+        let mut pos = 1;
+        while pos < slots.len() {
+            let Some(parent) = queue.pop_front() else { break };
+            let (left, right) = &mut children[parent];
+            for child in [left, right] {
+                if slots.get(pos).is_some_and(Option::is_some) {
+                    *child = Some(pos);
+                    queue.push_back(pos);
+                }
+                pos += 1;
+            }
+        }
+        if let Some(orphan) = slots.iter().skip(pos).position(Option::is_some) {
+            return Err(ShapeError::Orphan(pos + orphan));
+        }
+
+        let key = |pos: usize| slots.get(pos).and_then(Option::as_ref).map(|(key, _)| key);
+        let mut stack = Vec::new();
+        let mut cur = key(0).map(|_| 0);
+        let mut prev = None;
+        while cur.is_some() || !stack.is_empty() {
+            while let Some(pos) = cur {
+                stack.push(pos);
+                cur = children[pos].0;
+            }
+            let Some(pos) = stack.pop() else { break };
+            if let (Some(prev), Some(cur)) = (prev.and_then(key), key(pos)) {
+                if cmp.compare(prev, cur) != Ordering::Less {
+                    return Err(ShapeError::Unordered(pos));
+                }
+            }
+            prev = Some(pos);
+            cur = children[pos].1;
+        }
+
+        let nodes: Vec<_> = slots.into_iter()
+            .map(|slot| slot.map(|(key, value)| Node::alloc(key, value)))
+            .collect();
+        let mut length = 0;
+        for (node_ptr, &(left, right)) in nodes.iter().zip(children.iter()) {
+            let Some(node_ptr) = *node_ptr else { continue };
+            length += 1;
+            let node = unsafe { &mut *node_ptr.as_ptr() };
+            node.left = left.and_then(|pos| nodes[pos]);
+            node.right = right.and_then(|pos| nodes[pos]);
+            for child in [node.left, node.right].into_iter().flatten() {
+                unsafe { (*child.as_ptr()).parent = Some(node_ptr) };
+            }
+        }
+        // Children come after their parents in level order.
+        for node_ptr in nodes.iter().rev().flatten() {
+            unsafe { Node::update_size(*node_ptr) };
+        }
+        let root = nodes.first().copied().flatten();
+        Ok(SplayTreeBy::from_root(root, length, cmp))
+    }
+}
@@ -0,0 +1,63 @@
+use std::cell::Cell;
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+use crate::{Natural, Node, SplayTreeBy};
+
+/// Reference to a value returned by [`SplayTreeBy::get_deferred`], which splays
+/// the node of the value when dropped, but only if the value has been read.
+///
+/// Lookups which turn out not to need the value leave the tree untouched, and
+/// the rotations of the others happen once the value is no longer used.
+///
+/// [`SplayTreeBy::get_deferred`]: crate::SplayTreeBy::get_deferred
+pub struct DeferredRef<'a, K, V, C = Natural> {
+    tree: &'a mut SplayTreeBy<K, V, C>,
+    node_ptr: NonNull<Node<K, V>>,
+    used: Cell<bool>,
+}
+
+impl<'a, K, V, C> DeferredRef<'a, K, V, C> {
+    #[inline]
+    pub(crate) fn new(tree: &'a mut SplayTreeBy<K, V, C>, node_ptr: NonNull<Node<K, V>>) -> Self {
+        DeferredRef { tree, node_ptr, used: Cell::new(false) }
+    }
+
+    /// Returns a reference to the key of the entry. It doesn't count as a read.
+    #[inline]
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.node_ptr.as_ptr()).key }
+    }
+
+    /// Returns a reference to the value of the entry, so the node is splayed
+    /// once the guard is dropped.
+    #[inline]
+    pub fn value(&self) -> &V {
+        self.used.set(true);
+        unsafe { &(*self.node_ptr.as_ptr()).value }
+    }
+
+    /// Returns `true` if the value has been read.
+    #[inline]
+    pub fn is_used(&self) -> bool {
+        self.used.get()
+    }
+}
+
+impl<'a, K, V, C> Deref for DeferredRef<'a, K, V, C> {
+    type Target = V;
+
+    #[inline]
+    fn deref(&self) -> &V {
+        self.value()
+    }
+}
+
+impl<'a, K, V, C> Drop for DeferredRef<'a, K, V, C> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.used.get() && self.tree.root != Some(self.node_ptr) {
+            unsafe { self.tree.splay(self.node_ptr) };
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::cmp::Ordering;
+use std::iter::{FusedIterator, Peekable};
+
+use crate::{Compare, Iter};
+
+/// Difference of an entry between two trees, yielded by [`Diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<'a, K, V> {
+    /// The key is only in the other tree.
+    Added(&'a K, &'a V),
+    /// The key is only in this tree.
+    Removed(&'a K, &'a V),
+    /// The key is in both trees with different values, this tree's one first.
+    Changed(&'a K, &'a V, &'a V),
+}
+
+/// An iterator over the changes turning one `SplayTree` into another, sorted by key.
+///
+/// This `struct` is created by [`SplayTreeBy::diff`].
+///
+/// [`SplayTreeBy::diff`]: crate::SplayTreeBy::diff
+pub struct Diff<'a, K, V, C> {
+    old: Peekable<Iter<'a, K, V>>,
+    new: Peekable<Iter<'a, K, V>>,
+    cmp: &'a C,
+}
+
+impl<'a, K, V, C> Diff<'a, K, V, C> {
+    #[inline]
+    pub(crate) fn new(old: Iter<'a, K, V>, new: Iter<'a, K, V>, cmp: &'a C) -> Self {
+        Diff {
+            old: old.peekable(),
+            new: new.peekable(),
+            cmp,
+        }
+    }
+}
+
+impl<'a, K, V: PartialEq, C: Compare<K>> Iterator for Diff<'a, K, V, C> {
+    type Item = Change<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ordering = match (self.old.peek(), self.new.peek()) {
+                (Some((old_key, _)), Some((new_key, _))) => self.cmp.compare(old_key, new_key),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+
+            match ordering {
+                Ordering::Less => {
+                    let (key, value) = self.old.next()?;
+                    return Some(Change::Removed(key, value));
+                },
+                Ordering::Greater => {
+                    let (key, value) = self.new.next()?;
+                    return Some(Change::Added(key, value));
+                },
+                Ordering::Equal => {
+                    let (key, old_value) = self.old.next()?;
+                    let (_, new_value) = self.new.next()?;
+                    if old_value != new_value {
+                        return Some(Change::Changed(key, old_value, new_value));
+                    }
+                },
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (old, _) = self.old.size_hint();
+        let (new, _) = self.new.size_hint();
+        (old.abs_diff(new), Some(old + new))
+    }
+}
+
+impl<'a, K, V: PartialEq, C: Compare<K>> FusedIterator for Diff<'a, K, V, C> {}
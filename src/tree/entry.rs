@@ -1,29 +1,33 @@
-use crate::{SplayTree, Node};
+use std::cmp::Ordering;
+use std::ptr::NonNull;
+use std::borrow::Borrow;
 
-pub enum Entry<'a, K: Ord, V> {
-    Occupied(OccupiedEntry<'a, K, V>),
-    Vacant(VacantEntry<'a, K, V>),
+use crate::{SplayTreeBy, Node, NodeMut, NodeId, Compare, Natural};
+
+pub enum Entry<'a, K, V, C = Natural> {
+    Occupied(OccupiedEntry<'a, K, V, C>),
+    Vacant(VacantEntry<'a, K, V, C>),
 }
 
 use Entry::{Occupied, Vacant};
 
-impl<'a, K: Ord, V> Entry<'a, K, V> {
+impl<'a, K, V, C: Compare<K>> Entry<'a, K, V, C> {
     /// Ensures a value is in the entry by inserting the default if empty,
-    /// and returns a mutable reference to the node.
+    /// and returns mutable access to the node.
     #[inline]
-    pub fn or_insert(self, value: V) -> &'a mut Node<K, V> {
+    pub fn or_insert(self, value: V) -> NodeMut<'a, K, V> {
         match self {
-            Occupied(entry) => entry.elem,
+            Occupied(entry) => entry.into_node(),
             Vacant(entry) => entry.insert(value),
         }
     }
 
     /// Ensures a value is in the entry by inserting the result of the default function
-    /// if empty, and returns a mutable reference to the node.
+    /// if empty, and returns mutable access to the node.
     #[inline]
-    pub fn or_insert_with<F: FnOnce() -> V>(self, calc: F) -> &'a mut Node<K, V> {
+    pub fn or_insert_with<F: FnOnce() -> V>(self, calc: F) -> NodeMut<'a, K, V> {
         match self {
-            Occupied(entry) => entry.elem,
+            Occupied(entry) => entry.into_node(),
             Vacant(entry) => entry.insert(calc()),
         }
     }
@@ -33,9 +37,9 @@ impl<'a, K: Ord, V> Entry<'a, K, V> {
     /// key-derived values for insertion by providing the default function a reference
     /// to the key that was moved during the .entry(key) method call.
     #[inline]
-    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, calc: F) -> &'a mut Node<K, V> {
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, calc: F) -> NodeMut<'a, K, V> {
         match self {
-            Occupied(entry) => entry.elem,
+            Occupied(entry) => entry.into_node(),
             Vacant(entry) => {
                 let val = calc(&entry.key);
                 entry.insert(val)
@@ -43,11 +47,24 @@ impl<'a, K: Ord, V> Entry<'a, K, V> {
         }
     }
 
+    /// Ensures a value is in the entry by inserting the result of the fallible
+    /// default function if empty, and returns mutable access to the node.
+    /// If the function fails, the tree is left unchanged and the error is returned.
+    #[inline]
+    pub fn or_try_insert_with<E, F>(self, calc: F) -> Result<NodeMut<'a, K, V>, E>
+        where F: FnOnce() -> Result<V, E>
+    {
+        match self {
+            Occupied(entry) => Ok(entry.into_node()),
+            Vacant(entry) => Ok(entry.insert(calc()?)),
+        }
+    }
+
     /// Returns a reference to this entry's key.
     #[inline]
     pub fn key(&self) -> &K {
         match self {
-            Occupied(entry) => entry.elem.key(),
+            Occupied(entry) => entry.key(),
             Vacant(entry) => &entry.key,
         }
     }
@@ -57,83 +74,398 @@ impl<'a, K: Ord, V> Entry<'a, K, V> {
     #[inline]
     pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
         match self {
-            Occupied(entry) => {
-                f(entry.elem.value_mut());
+            Occupied(mut entry) => {
+                f(entry.get_mut());
                 Occupied(entry)
             },
             _ => self,
         }
     }
 
-    /// Sets the value of the entry, and returns mutable reference to the node.
+    /// Sets the value of the entry, and returns mutable access to the node.
     #[inline]
-    pub fn insert(self, value: V) -> &'a mut Node<K, V> {
+    pub fn insert(self, value: V) -> NodeMut<'a, K, V> {
         match self {
-            Occupied(entry) =>  {
-                *entry.elem.value_mut() = value;
-                entry.elem
+            Occupied(mut entry) =>  {
+                entry.insert(value);
+                entry.into_node()
             },
             Vacant(entry) => {
                 entry.insert(value)
             },
         }
     }
+
+    /// Sets the value of the entry, and returns an occupied entry
+    /// for further manipulation of it.
+    #[inline]
+    pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+        match self {
+            Occupied(mut entry) => {
+                entry.insert(value);
+                entry
+            },
+            Vacant(entry) => entry.insert_entry(value),
+        }
+    }
 }
 
-impl<'a, K: Ord, V: Default> Entry<'a, K, V> {
+impl<'a, K, V: Default, C: Compare<K>> Entry<'a, K, V, C> {
     /// Ensures a value is in the entry by inserting the default value
-    /// if empty, and returns a mutable reference to the node.
+    /// if empty, and returns mutable access to the node.
     #[inline]
-    pub fn or_default(self) -> &'a mut Node<K, V> {
+    pub fn or_default(self) -> NodeMut<'a, K, V> {
         match self {
-            Occupied(entry) => entry.elem,
+            Occupied(entry) => entry.into_node(),
             Vacant(entry) => entry.insert(V::default()),
         }
     }
 }
 
-pub struct OccupiedEntry<'a, K: Ord, V> {
-    elem: &'a mut Node<K, V>,
+/// Occupied entry of a tree. The node of the entry has been splayed to the root.
+pub struct OccupiedEntry<'a, K, V, C = Natural> {
+    tree: &'a mut SplayTreeBy<K, V, C>,
 }
 
-impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+impl<'a, K, V, C: Compare<K>> OccupiedEntry<'a, K, V, C> {
+    #[inline]
+    pub(crate) fn new(tree: &'a mut SplayTreeBy<K, V, C>) -> Self {
+        OccupiedEntry { tree }
+    }
+
+    /// Returns a reference to this entry's key.
+    #[inline]
+    pub fn key(&self) -> &K {
+        self.node().key()
+    }
+
+    /// Returns a reference to the value in the entry.
+    #[inline]
+    pub fn get(&self) -> &V {
+        self.node().value()
+    }
+
+    /// Returns a mutable reference to the value in the entry.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        self.node_mut().into_value_mut()
+    }
+
+    /// Converts the entry into a mutable reference to its value,
+    /// which lives as long as the borrow of the tree.
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        self.into_node().into_value_mut()
+    }
+
+    /// Sets the value of the entry, and returns the old value.
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        self.tree.replace_root_value(value)
+    }
+
+    /// Returns the handle of the entry's node, which stays valid until
+    /// the node is removed from the tree.
+    #[inline]
+    pub fn id(&mut self) -> NodeId {
+        let root = self.tree.root.expect("occupied entry of an empty tree");
+        self.tree.handles.id(root)
+    }
+
+    /// Removes the entry from the tree and returns its value.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Removes the entry from the tree and returns its key and value.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn remove_entry(self) -> (K, V) {
+        self.tree.take_root_entry().expect("occupied entry of an empty tree")
+    }
+
+    /// Replaces the value of the entry with the result of `resolve` called with
+    /// the key, the old value and `value`, like [`SplayTreeBy::merge_with`].
     #[inline]
-    pub(crate) fn new(elem: &'a mut Node<K, V>) -> Self {
-        OccupiedEntry { elem }
+    pub(crate) fn resolve<F>(self, value: V, resolve: &mut F)
+        where F: FnMut(&K, V, V) -> V
+    {
+        let root = self.tree.root.expect("occupied entry of an empty tree");
+        unsafe { self.tree.resolve_value(root, value, resolve) }
+    }
+
+    #[inline]
+    fn node(&self) -> &Node<K, V> {
+        self.tree.root().expect("occupied entry of an empty tree")
+    }
+
+    #[inline]
+    fn node_mut(&mut self) -> NodeMut<'_, K, V> {
+        self.tree.root_mut().expect("occupied entry of an empty tree")
+    }
+
+    #[inline]
+    fn into_node(self) -> NodeMut<'a, K, V> {
+        self.tree.root_mut().expect("occupied entry of an empty tree")
     }
 }
 
-pub struct VacantEntry<'a, K: Ord, V> {
-    tree: &'a mut SplayTree<K, V>,
-    parent: Option<&'a mut Node<K, V>>,
+pub struct VacantEntry<'a, K, V, C = Natural> {
+    tree: &'a mut SplayTreeBy<K, V, C>,
+    parent: Option<(NonNull<Node<K, V>>, Ordering)>,
     key: K,
 }
 
-impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+impl<'a, K, V, C: Compare<K>> VacantEntry<'a, K, V, C> {
     #[inline]
-    pub(crate) fn new_root(tree: &'a mut SplayTree<K, V>, key: K) -> Self {
+    pub(crate) fn new(
+        tree: &'a mut SplayTreeBy<K, V, C>,
+        parent: Option<(NonNull<Node<K, V>>, Ordering)>,
+        key: K
+    ) -> Self {
         VacantEntry {
-            tree: tree,
-            parent: None,
-            key: key,
+            tree,
+            parent,
+            key,
         }
     }
 
     #[inline]
-    pub(crate) fn new_elem(
-        tree: &'a mut SplayTree<K, V>,
-        parent: &'a mut Node<K, V>,
-        key: K
+    pub(crate) fn insert(self, value: V) -> NodeMut<'a, K, V> {
+        self.tree.insert_child(self.parent, self.key, value)
+    }
+
+    #[inline]
+    fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+        self.tree.insert_child(self.parent, self.key, value);
+        OccupiedEntry::new(self.tree)
+    }
+}
+
+/// Entry looked up by a borrowed key with [`SplayTreeBy::entry_ref`].
+///
+/// The owned key is made from the borrowed one with `From` only when a vacant
+/// entry is filled.
+pub enum EntryRef<'a, 'q, K, Q: ?Sized, V, C = Natural> {
+    Occupied(OccupiedEntry<'a, K, V, C>),
+    Vacant(VacantEntryRef<'a, 'q, K, Q, V, C>),
+}
+
+impl<'a, 'q, K, Q, V, C> EntryRef<'a, 'q, K, Q, V, C>
+    where K: Borrow<Q> + From<&'q Q>, Q: ?Sized, C: Compare<K>
+{
+    /// Ensures a value is in the entry by inserting the default if empty,
+    /// and returns mutable access to the node.
+    #[inline]
+    pub fn or_insert(self, value: V) -> NodeMut<'a, K, V> {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_node(),
+            EntryRef::Vacant(entry) => entry.insert(value),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function
+    /// if empty, and returns mutable access to the node.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, calc: F) -> NodeMut<'a, K, V> {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_node(),
+            EntryRef::Vacant(entry) => entry.insert(calc()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of
+    /// the default function called with the borrowed key.
+    #[inline]
+    pub fn or_insert_with_key<F: FnOnce(&Q) -> V>(self, calc: F) -> NodeMut<'a, K, V> {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_node(),
+            EntryRef::Vacant(entry) => {
+                let val = calc(entry.key);
+                entry.insert(val)
+            }
+        }
+    }
+
+    /// Returns a reference to this entry's key.
+    #[inline]
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key,
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential
+    /// inserts into the map.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            EntryRef::Occupied(mut entry) => {
+                f(entry.get_mut());
+                EntryRef::Occupied(entry)
+            },
+            _ => self,
+        }
+    }
+
+    /// Sets the value of the entry, and returns mutable access to the node.
+    #[inline]
+    pub fn insert(self, value: V) -> NodeMut<'a, K, V> {
+        match self {
+            EntryRef::Occupied(mut entry) => {
+                entry.insert(value);
+                entry.into_node()
+            },
+            EntryRef::Vacant(entry) => {
+                entry.insert(value)
+            },
+        }
+    }
+
+    /// Sets the value of the entry, and returns an occupied entry
+    /// for further manipulation of it.
+    #[inline]
+    pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+        match self {
+            EntryRef::Occupied(mut entry) => {
+                entry.insert(value);
+                entry
+            },
+            EntryRef::Vacant(entry) => entry.insert_entry(value),
+        }
+    }
+}
+
+impl<'a, 'q, K, Q, V, C> EntryRef<'a, 'q, K, Q, V, C>
+    where K: Borrow<Q> + From<&'q Q>, Q: ?Sized, V: Default, C: Compare<K>
+{
+    /// Ensures a value is in the entry by inserting the default value
+    /// if empty, and returns mutable access to the node.
+    #[inline]
+    pub fn or_default(self) -> NodeMut<'a, K, V> {
+        self.or_insert_with(V::default)
+    }
+}
+
+pub struct VacantEntryRef<'a, 'q, K, Q: ?Sized, V, C = Natural> {
+    tree: &'a mut SplayTreeBy<K, V, C>,
+    parent: Option<(NonNull<Node<K, V>>, Ordering)>,
+    key: &'q Q,
+}
+
+impl<'a, 'q, K, Q: ?Sized, V, C> VacantEntryRef<'a, 'q, K, Q, V, C> {
+    #[inline]
+    pub(crate) fn new(
+        tree: &'a mut SplayTreeBy<K, V, C>,
+        parent: Option<(NonNull<Node<K, V>>, Ordering)>,
+        key: &'q Q
     ) -> Self {
-        VacantEntry {
-            tree: tree,
-            parent: Some(parent),
-            key: key,
+        VacantEntryRef {
+            tree,
+            parent,
+            key,
+        }
+    }
+}
+
+impl<'a, 'q, K, Q, V, C> VacantEntryRef<'a, 'q, K, Q, V, C>
+    where K: From<&'q Q>, Q: ?Sized, C: Compare<K>
+{
+    #[inline]
+    fn insert(self, value: V) -> NodeMut<'a, K, V> {
+        self.tree.insert_child(self.parent, K::from(self.key), value)
+    }
+
+    #[inline]
+    fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+        self.tree.insert_child(self.parent, K::from(self.key), value);
+        OccupiedEntry::new(self.tree)
+    }
+}
+
+/// Entry looked up by a search closure with [`SplayTreeBy::entry_by`].
+pub enum RawEntry<'a, K, V, C = Natural> {
+    Occupied(OccupiedEntry<'a, K, V, C>),
+    Vacant(RawVacantEntry<'a, K, V, C>),
+}
+
+impl<'a, K, V, C: Compare<K>> RawEntry<'a, K, V, C> {
+    /// Ensures a value is in the entry by inserting the key and the value if empty,
+    /// and returns mutable access to the node.
+    #[inline]
+    pub fn or_insert(self, key: K, value: V) -> NodeMut<'a, K, V> {
+        match self {
+            RawEntry::Occupied(entry) => entry.into_node(),
+            RawEntry::Vacant(entry) => entry.insert(key, value),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the key and the value returned
+    /// by the default function if empty, and returns mutable access to the node.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> (K, V)>(self, calc: F) -> NodeMut<'a, K, V> {
+        match self {
+            RawEntry::Occupied(entry) => entry.into_node(),
+            RawEntry::Vacant(entry) => {
+                let (key, value) = calc();
+                entry.insert(key, value)
+            },
         }
     }
 
+    /// Provides in-place mutable access to an occupied entry before any potential
+    /// inserts into the map.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&K, &mut V)>(self, f: F) -> Self {
+        match self {
+            RawEntry::Occupied(mut entry) => {
+                let (key, value) = entry.node_mut().into_key_value_mut();
+                f(key, value);
+                RawEntry::Occupied(entry)
+            },
+            _ => self,
+        }
+    }
+}
+
+/// Vacant entry of a tree found by [`SplayTreeBy::entry_by`].
+///
+/// It remembers where the search stopped, so the inserted key must be one
+/// the search closure would have led to the same place; otherwise the tree
+/// becomes unordered and lookups may stop finding its keys.
+pub struct RawVacantEntry<'a, K, V, C = Natural> {
+    tree: &'a mut SplayTreeBy<K, V, C>,
+    parent: Option<(NonNull<Node<K, V>>, Ordering)>,
+}
+
+impl<'a, K, V, C> RawVacantEntry<'a, K, V, C> {
+    #[inline]
+    pub(crate) fn new(
+        tree: &'a mut SplayTreeBy<K, V, C>,
+        parent: Option<(NonNull<Node<K, V>>, Ordering)>
+    ) -> Self {
+        RawVacantEntry { tree, parent }
+    }
+}
+
+impl<'a, K, V, C: Compare<K>> RawVacantEntry<'a, K, V, C> {
+    /// Inserts the key and the value at the place where the search stopped,
+    /// and returns mutable access to the node.
+    #[inline]
+    pub fn insert(self, key: K, value: V) -> NodeMut<'a, K, V> {
+        self.tree.insert_child(self.parent, key, value)
+    }
+
+    /// Inserts the key and the value at the place where the search stopped,
+    /// and returns an occupied entry for further manipulation of it.
     #[inline]
-    fn insert(self, value: V) -> &'a mut Node<K, V> {
-        self.tree.insert_child(self.parent, self.key, value).unwrap()
+    pub fn insert_entry(self, key: K, value: V) -> OccupiedEntry<'a, K, V, C> {
+        self.tree.insert_child(self.parent, key, value);
+        OccupiedEntry::new(self.tree)
     }
 }
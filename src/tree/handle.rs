@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::mem;
+use std::ptr::NonNull;
+
+use crate::{Node, NodePtr};
+
+/// Handle of a node of a `SplayTree`, returned by [`SplayTreeBy::id_of`]
+/// and [`OccupiedEntry::id`].
+///
+/// It gives *O*(1) access to the node without a key search. Once the node is
+/// removed from the tree, the handle becomes stale and lookups by it return `None`,
+/// even if its slot has been reused by another node. A handle is only meaningful
+/// for the tree that issued it.
+///
+/// [`SplayTreeBy::id_of`]: crate::SplayTreeBy::id_of
+/// [`OccupiedEntry::id`]: crate::OccupiedEntry::id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+impl NodeId {
+    /// Handle of no node, standing in for a handle which isn't known yet.
+    pub(crate) const DANGLING: NodeId = NodeId { index: u32::MAX, generation: u32::MAX };
+}
+
+struct Slot<K, V> {
+    node: NodePtr<K, V>,
+    generation: u32,
+}
+
+/// Slot table mapping handles to the nodes of a tree.
+///
+/// The table is only allocated once a handle is asked for, so trees which don't
+/// use handles stay small and only pay for a null check on removal.
+pub(crate) struct Handles<K, V> {
+    table: Option<Box<Table<K, V>>>,
+}
+
+struct Table<K, V> {
+    slots: Vec<Slot<K, V>>,
+    free: Vec<u32>,
+    ids: HashMap<NonNull<Node<K, V>>, u32>,
+}
+
+impl<K, V> Handles<K, V> {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Handles { table: None }
+    }
+
+    /// Returns the handle of a node of the tree, allocating a slot for it
+    /// if it doesn't have one yet.
+    pub(crate) fn id(&mut self, node_ptr: NonNull<Node<K, V>>) -> NodeId {
+        let table = self.table.get_or_insert_with(|| {
+            Box::new(Table {
+                slots: Vec::new(),
+                free: Vec::new(),
+                ids: HashMap::new(),
+            })
+        });
+        if let Some(&index) = table.ids.get(&node_ptr) {
+            let generation = table.slots[index as usize].generation;
+            return NodeId { index, generation };
+        }
+
+        let index = match table.free.pop() {
+            Some(index) => index,
+            None => {
+                let index = u32::try_from(table.slots.len()).expect("too many node handles");
+                table.slots.push(Slot { node: None, generation: 0 });
+                index
+            },
+        };
+        let slot = &mut table.slots[index as usize];
+        slot.node = Some(node_ptr);
+        table.ids.insert(node_ptr, index);
+        NodeId { index, generation: slot.generation }
+    }
+
+    /// Returns the node of a handle, or `None` if the handle is stale.
+    #[inline]
+    pub(crate) fn get(&self, id: NodeId) -> NodePtr<K, V> {
+        self.table.as_ref()?
+            .slots.get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node)
+    }
+
+    /// Makes the handle of a node removed from the tree stale.
+    #[inline]
+    pub(crate) fn release(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        if let Some(table) = &mut self.table {
+            if let Some(index) = table.ids.remove(&node_ptr) {
+                table.vacate(index);
+            }
+        }
+    }
+
+    /// Makes all handles stale, keeping the generations of the slots.
+    pub(crate) fn clear(&mut self) {
+        if let Some(table) = &mut self.table {
+            let indices: Vec<u32> = table.ids.drain().map(|(_, index)| index).collect();
+            for index in indices {
+                table.vacate(index);
+            }
+        }
+    }
+
+    /// Returns an approximate number of bytes of heap memory held by the table.
+    #[inline]
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.table.as_ref().map_or(0, |table| {
+            mem::size_of::<Table<K, V>>()
+                + table.slots.capacity() * mem::size_of::<Slot<K, V>>()
+                + table.free.capacity() * mem::size_of::<u32>()
+                + table.ids.capacity() * mem::size_of::<(NonNull<Node<K, V>>, u32)>()
+        })
+    }
+}
+
+impl<K, V> Table<K, V> {
+    #[inline]
+    fn vacate(&mut self, index: u32) {
+        let slot = &mut self.slots[index as usize];
+        slot.node = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::{self, Display, Write};
+
+use crate::NodePtr;
+
+/// Writes the nodes of the subtree at `root` as a JSON document:
+///
+/// ```json
+/// {"len":3,"nodes":[
+/// {"key":"2","depth":0,"size":3,"parent":null,"hits":4},
+/// {"key":"1","depth":1,"size":1,"parent":0,"hits":0},
+/// {"key":"3","depth":1,"size":1,"parent":0,"hits":1}
+/// ]}
+/// ```
+///
+/// `hits` is only written with the `stats` feature.
+pub(crate) fn write<K: Display, V, W: Write>(root: NodePtr<K, V>, len: usize, out: &mut W) -> fmt::Result {
+    write!(out, "{{\"len\":{},\"nodes\":[", len)?;
+    let mut stack: Vec<_> = root.map(|root| (root, 0, None)).into_iter().collect();
+    let mut index = 0;
+
+    while let Some((node_ptr, depth, parent)) = stack.pop() {
+        let node = unsafe { &*node_ptr.as_ptr() };
+        out.write_str(if index == 0 { "\n{\"key\":\"" } else { ",\n{\"key\":\"" })?;
+        write!(Escaped(out), "{}", node.key)?;
+        write!(out, "\",\"depth\":{},\"size\":{},\"parent\":", depth, node.size)?;
+        match parent {
+            Some(parent) => write!(out, "{}", parent)?,
+            None => out.write_str("null")?,
+        }
+        #[cfg(feature = "stats")]
+        write!(out, ",\"hits\":{}", node.hits)?;
+        out.write_char('}')?;
+
+        stack.extend(node.right.map(|right| (right, depth + 1, Some(index))));
+        stack.extend(node.left.map(|left| (left, depth + 1, Some(index))));
+        index += 1;
+    }
+
+    out.write_str(if index == 0 { "]}" } else { "\n]}" })
+}
+
+/// Writer escaping the contents of a JSON string.
+pub(crate) struct Escaped<'a, W>(pub(crate) &'a mut W);
+
+impl<'a, W: Write> Write for Escaped<'a, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                c if c < ' ' => write!(self.0, "\\u{:04x}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
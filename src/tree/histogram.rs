@@ -0,0 +1,72 @@
+/// Numbers of nodes at every depth of a tree, returned by
+/// [`SplayTreeBy::depth_histogram`].
+///
+/// [`SplayTreeBy::depth_histogram`]: crate::SplayTreeBy::depth_histogram
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepthHistogram {
+    counts: Vec<usize>,
+    len: usize,
+}
+
+impl DepthHistogram {
+    /// Counts a node at `depth`.
+    #[inline]
+    pub(crate) fn record(&mut self, depth: usize) {
+        if self.counts.len() <= depth {
+            self.counts.resize(depth + 1, 0);
+        }
+        self.counts[depth] += 1;
+        self.len += 1;
+    }
+
+    /// Returns the number of nodes at every depth, starting from the root.
+    #[inline]
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Returns the number of nodes at `depth`.
+    #[inline]
+    pub fn count_at(&self, depth: usize) -> usize {
+        self.counts.get(depth).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of counted nodes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no nodes were counted.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the greatest depth of a node, or `None` if no nodes were counted.
+    #[inline]
+    pub fn max_depth(&self) -> Option<usize> {
+        self.counts.len().checked_sub(1)
+    }
+
+    /// Returns the smallest depth such that at least `p` percent of the nodes are
+    /// at most that deep, or `None` if no nodes were counted. `p` is clamped to
+    /// the range from 0 to 100.
+    pub fn percentile(&self, p: f64) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * self.len as f64).ceil().max(1.0) as usize;
+        let mut seen = 0;
+        self.counts.iter().position(|&count| {
+            seen += count;
+            seen >= rank
+        })
+    }
+
+    /// Returns the median depth of a node, or `None` if no nodes were counted.
+    #[inline]
+    pub fn median(&self) -> Option<usize> {
+        self.percentile(50.0)
+    }
+}
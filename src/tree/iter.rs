@@ -0,0 +1,644 @@
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use crate::{Node, NodePtr};
+
+/// An iterator over the entries of a `SplayTree`, sorted by key.
+///
+/// Iteration doesn't splay, so it only needs a shared reference to the tree.
+pub struct Iter<'a, K, V> {
+    front: NodePtr<K, V>,
+    back: NodePtr<K, V>,
+    length: usize,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+// SAFETY: the iterator only gives out shared references, like `&SplayTree`.
+unsafe impl<'a, K: Sync, V: Sync> Send for Iter<'a, K, V> {}
+
+// SAFETY: `&Iter` doesn't give any access to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for Iter<'a, K, V> {}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(root: NodePtr<K, V>, length: usize) -> Self {
+        Iter {
+            front: root.map(|r| unsafe { Node::leftmost(r) }),
+            back: root.map(|r| unsafe { Node::rightmost(r) }),
+            length,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+
+        let node_ptr = self.front?;
+        self.front = unsafe { Node::successor(node_ptr) };
+        let node = unsafe { &*node_ptr.as_ptr() };
+        self.length -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+
+        let node_ptr = self.back?;
+        self.back = unsafe { Node::predecessor(node_ptr) };
+        let node = unsafe { &*node_ptr.as_ptr() };
+        self.length -= 1;
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+
+impl<'a, K, V> Clone for Iter<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Iter { ..*self }
+    }
+}
+
+/// An iterator over the pairs of consecutive entries of a `SplayTree`, sorted by key.
+///
+/// Created by [`SplayTreeBy::adjacent_pairs`](crate::SplayTreeBy::adjacent_pairs).
+pub struct AdjacentPairs<'a, K, V> {
+    prev: Option<(&'a K, &'a V)>,
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> AdjacentPairs<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(mut inner: Iter<'a, K, V>) -> Self {
+        AdjacentPairs { prev: inner.next(), inner }
+    }
+}
+
+impl<'a, K, V> Iterator for AdjacentPairs<'a, K, V> {
+    type Item = ((&'a K, &'a V), (&'a K, &'a V));
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.inner.next()?;
+        let prev = self.prev.replace(next)?;
+        Some((prev, next))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for AdjacentPairs<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for AdjacentPairs<'a, K, V> {}
+
+impl<'a, K, V> Clone for AdjacentPairs<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        AdjacentPairs { prev: self.prev, inner: self.inner.clone() }
+    }
+}
+
+/// An iterator over the entries of a `SplayTree` between two nodes, sorted by key.
+///
+/// Created by [`SplayTree::prefix_range`](crate::SplayTree::prefix_range).
+pub struct Range<'a, K, V> {
+    front: NodePtr<K, V>,
+    back: NodePtr<K, V>,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+// SAFETY: the iterator only gives out shared references, like `&SplayTree`.
+unsafe impl<'a, K: Sync, V: Sync> Send for Range<'a, K, V> {}
+
+// SAFETY: `&Range` doesn't give any access to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for Range<'a, K, V> {}
+
+impl<'a, K, V> Range<'a, K, V> {
+    /// Makes an iterator from `front` to `back` inclusive. Both must be `None`
+    /// or `front` must not be greater than `back`.
+    #[inline]
+    pub(crate) fn new(front: NodePtr<K, V>, back: NodePtr<K, V>) -> Self {
+        Range {
+            front,
+            back,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_ptr = self.front?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = unsafe { Node::successor(node_ptr) };
+        }
+        let node = unsafe { &*node_ptr.as_ptr() };
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node_ptr = self.back?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = unsafe { Node::predecessor(node_ptr) };
+        }
+        let node = unsafe { &*node_ptr.as_ptr() };
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> FusedIterator for Range<'a, K, V> {}
+
+impl<'a, K, V> Clone for Range<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Range { ..*self }
+    }
+}
+
+/// An iterator over the entries of a `SplayTree` in pre-order, where every node comes before its subtrees.
+///
+/// Nodes are walked by parent links, so the iterator doesn't allocate. Created by
+/// [`SplayTreeBy::pre_order`](crate::SplayTreeBy::pre_order).
+pub struct PreOrder<'a, K, V> {
+    next: NodePtr<K, V>,
+    length: usize,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+// SAFETY: the iterator only gives out shared references, like `&SplayTree`.
+unsafe impl<'a, K: Sync, V: Sync> Send for PreOrder<'a, K, V> {}
+
+// SAFETY: `&PreOrder` doesn't give any access to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for PreOrder<'a, K, V> {}
+
+impl<'a, K, V> PreOrder<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(root: NodePtr<K, V>, length: usize) -> Self {
+        PreOrder {
+            next: root,
+            length,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for PreOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_ptr = self.next?;
+        self.next = unsafe { pre_order_next(node_ptr) };
+        self.length -= 1;
+        let node = unsafe { &*node_ptr.as_ptr() };
+        Some((&node.key, &node.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for PreOrder<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for PreOrder<'a, K, V> {}
+
+impl<'a, K, V> Clone for PreOrder<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        PreOrder { ..*self }
+    }
+}
+
+/// An iterator over the entries of a `SplayTree` in post-order, where every node comes after its subtrees.
+///
+/// Nodes are walked by parent links, so the iterator doesn't allocate. Created by
+/// [`SplayTreeBy::post_order`](crate::SplayTreeBy::post_order).
+pub struct PostOrder<'a, K, V> {
+    next: NodePtr<K, V>,
+    length: usize,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+// SAFETY: the iterator only gives out shared references, like `&SplayTree`.
+unsafe impl<'a, K: Sync, V: Sync> Send for PostOrder<'a, K, V> {}
+
+// SAFETY: `&PostOrder` doesn't give any access to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for PostOrder<'a, K, V> {}
+
+impl<'a, K, V> PostOrder<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(root: NodePtr<K, V>, length: usize) -> Self {
+        PostOrder {
+            next: root.map(|r| unsafe { first_post_order(r) }),
+            length,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for PostOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_ptr = self.next?;
+        self.next = unsafe { post_order_next(node_ptr) };
+        self.length -= 1;
+        let node = unsafe { &*node_ptr.as_ptr() };
+        Some((&node.key, &node.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for PostOrder<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for PostOrder<'a, K, V> {}
+
+impl<'a, K, V> Clone for PostOrder<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        PostOrder { ..*self }
+    }
+}
+
+/// An iterator over the entries of a `SplayTree` in breadth-first order,
+/// yielding the depth of every node along with its entry.
+///
+/// Created by [`SplayTreeBy::level_order`](crate::SplayTreeBy::level_order).
+pub struct LevelOrder<'a, K, V> {
+    queue: VecDeque<(usize, NonNull<Node<K, V>>)>,
+    length: usize,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+// SAFETY: the iterator only gives out shared references, like `&SplayTree`.
+unsafe impl<'a, K: Sync, V: Sync> Send for LevelOrder<'a, K, V> {}
+
+// SAFETY: `&LevelOrder` doesn't give any access to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for LevelOrder<'a, K, V> {}
+
+impl<'a, K, V> LevelOrder<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(root: NodePtr<K, V>, length: usize) -> Self {
+        LevelOrder {
+            queue: root.map(|r| (0, r)).into_iter().collect(),
+            length,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for LevelOrder<'a, K, V> {
+    type Item = (usize, &'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node_ptr) = self.queue.pop_front()?;
+        let node = unsafe { &*node_ptr.as_ptr() };
+        self.queue.extend(node.left.into_iter().chain(node.right).map(|child| (depth + 1, child)));
+        self.length -= 1;
+        Some((depth, &node.key, &node.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for LevelOrder<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for LevelOrder<'a, K, V> {}
+
+impl<'a, K, V> Clone for LevelOrder<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        LevelOrder {
+            queue: self.queue.clone(),
+            length: self.length,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// An iterator over the entries of a `SplayTree`, roughly from the most recently
+/// accessed one to the least recently accessed one.
+///
+/// Created by [`SplayTreeBy::access_order`](crate::SplayTreeBy::access_order).
+pub struct AccessOrder<'a, K, V> {
+    inner: LevelOrder<'a, K, V>,
+}
+
+impl<'a, K, V> AccessOrder<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(inner: LevelOrder<'a, K, V>) -> Self {
+        AccessOrder { inner }
+    }
+}
+
+impl<'a, K, V> Iterator for AccessOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, key, value)| (key, value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for AccessOrder<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for AccessOrder<'a, K, V> {}
+
+impl<'a, K, V> Clone for AccessOrder<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        AccessOrder { inner: self.inner.clone() }
+    }
+}
+
+/// Iterator whose items borrow from the iterator itself, so only one of them
+/// is alive at a time.
+pub trait LendingIterator {
+    /// The type of the items, borrowing from the iterator for `'a`.
+    type Item<'a> where Self: 'a;
+
+    /// Advances the iterator and returns the next item.
+    fn next(&mut self) -> Option<Self::Item<'_>>;
+}
+
+/// A lending iterator over the entries of a `SplayTree`, sorted by key, which
+/// gives out mutable references to values.
+///
+/// A value reference lives until the next call to `next`, so it can be held
+/// across any amount of work on the entry, while the tree stays borrowed and
+/// can't be restructured. Created by
+/// [`SplayTreeBy::lending_iter_mut`](crate::SplayTreeBy::lending_iter_mut).
+pub struct LendingIterMut<'a, K, V> {
+    front: NodePtr<K, V>,
+    length: usize,
+    marker: PhantomData<&'a mut Node<K, V>>,
+}
+
+// SAFETY: the iterator gives out shared references to keys and mutable
+// references to values, like `&mut SplayTree` would.
+unsafe impl<'a, K: Sync, V: Send> Send for LendingIterMut<'a, K, V> {}
+
+// SAFETY: `&LendingIterMut` doesn't give any access to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for LendingIterMut<'a, K, V> {}
+
+impl<'a, K, V> LendingIterMut<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(root: NodePtr<K, V>, length: usize) -> Self {
+        LendingIterMut {
+            front: root.map(|r| unsafe { Node::leftmost(r) }),
+            length,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of remaining entries.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Returns `true` if there are no entries left.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+impl<'a, K, V> LendingIterator for LendingIterMut<'a, K, V> {
+    type Item<'b> = (&'b K, &'b mut V) where Self: 'b;
+
+    #[inline]
+    fn next(&mut self) -> Option<(&K, &mut V)> {
+        if self.length == 0 {
+            return None;
+        }
+
+        let node_ptr = self.front?;
+        self.front = unsafe { Node::successor(node_ptr) };
+        self.length -= 1;
+        let node = node_ptr.as_ptr();
+        Some(unsafe { (&(*node).key, &mut (*node).value) })
+    }
+}
+
+/// An owning iterator over the entries of a `SplayTree`, sorted by key.
+///
+/// Nodes are unlinked and freed as they are yielded, so the iterator
+/// doesn't need any extra space.
+pub struct IntoIter<K, V> {
+    front: NodePtr<K, V>,
+    back: NodePtr<K, V>,
+    length: usize,
+    marker: PhantomData<Box<Node<K, V>>>,
+}
+
+// SAFETY: the iterator uniquely owns the remaining nodes, just like the tree did.
+unsafe impl<K: Send, V: Send> Send for IntoIter<K, V> {}
+
+// SAFETY: `&IntoIter` doesn't give any access to the remaining nodes.
+unsafe impl<K: Sync, V: Sync> Sync for IntoIter<K, V> {}
+
+impl<K, V> IntoIter<K, V> {
+    #[inline]
+    pub(crate) fn new(root: NodePtr<K, V>, length: usize) -> Self {
+        IntoIter {
+            front: root.map(|r| unsafe { Node::leftmost(r) }),
+            back: root.map(|r| unsafe { Node::rightmost(r) }),
+            length,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        if self.length == 0 {
+            return None;
+        }
+
+        let (node, front) = unsafe { take_min(self.front?) };
+        self.front = front;
+        self.length -= 1;
+
+        Some((node.key, node.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<(K, V)> {
+        if self.length == 0 {
+            return None;
+        }
+
+        let (node, back) = unsafe { take_max(self.back?) };
+        self.back = back;
+        self.length -= 1;
+
+        Some((node.key, node.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+impl<K, V> Drop for IntoIter<K, V> {
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
+
+/// Returns the node after `ptr` in pre-order: its first child, or the right child
+/// of the nearest ancestor which is entered from the left and has one.
+unsafe fn pre_order_next<K, V>(ptr: NonNull<Node<K, V>>) -> NodePtr<K, V> {
+    let node = ptr.as_ptr();
+    if let Some(child) = (*node).left.or((*node).right) {
+        return Some(child);
+    }
+
+    let mut cur_ptr = ptr;
+    loop {
+        let parent = (*cur_ptr.as_ptr()).parent?;
+        if (*parent.as_ptr()).left == Some(cur_ptr) {
+            if let Some(right) = (*parent.as_ptr()).right {
+                return Some(right);
+            }
+        }
+        cur_ptr = parent;
+    }
+}
+
+/// Returns the first node of the subtree at `ptr` in post-order, the leaf reached
+/// by preferring left children.
+unsafe fn first_post_order<K, V>(ptr: NonNull<Node<K, V>>) -> NonNull<Node<K, V>> {
+    let mut cur_ptr = ptr;
+    while let Some(child) = (*cur_ptr.as_ptr()).left.or((*cur_ptr.as_ptr()).right) {
+        cur_ptr = child;
+    }
+    cur_ptr
+}
+
+/// Returns the node after `ptr` in post-order: the first node of the right subtree
+/// of its parent if it's a left child, or the parent itself.
+unsafe fn post_order_next<K, V>(ptr: NonNull<Node<K, V>>) -> NodePtr<K, V> {
+    let parent = (*ptr.as_ptr()).parent?;
+    match (*parent.as_ptr()).right {
+        Some(right) if right != ptr => Some(first_post_order(right)),
+        _ => Some(parent),
+    }
+}
+
+/// Unlinks the minimum node of a tree, splicing its right subtree in its place,
+/// and returns it together with the new minimum.
+///
+/// # Safety
+///
+/// `min_ptr` must point to the minimum node of a tree owned by the caller.
+pub(crate) unsafe fn take_min<K, V>(
+    min_ptr: NonNull<Node<K, V>>
+) -> (Box<Node<K, V>>, NodePtr<K, V>) {
+    let min = min_ptr.as_ptr();
+    let parent = (*min).parent.take();
+    let right = (*min).right.take();
+
+    if let Some(r) = right {
+        (*r.as_ptr()).parent = parent;
+    }
+    if let Some(p) = parent {
+        (*p.as_ptr()).left = right;
+    }
+
+    let next_min = match right {
+        Some(r) => Some(Node::leftmost(r)),
+        None => parent,
+    };
+
+    (Box::from_raw(min), next_min)
+}
+
+/// Mirrored counterpart of [`take_min`].
+///
+/// # Safety
+///
+/// `max_ptr` must point to the maximum node of a tree owned by the caller.
+pub(crate) unsafe fn take_max<K, V>(
+    max_ptr: NonNull<Node<K, V>>
+) -> (Box<Node<K, V>>, NodePtr<K, V>) {
+    let max = max_ptr.as_ptr();
+    let parent = (*max).parent.take();
+    let left = (*max).left.take();
+
+    if let Some(l) = left {
+        (*l.as_ptr()).parent = parent;
+    }
+    if let Some(p) = parent {
+        (*p.as_ptr()).right = left;
+    }
+
+    let next_max = match left {
+        Some(l) => Some(Node::rightmost(l)),
+        None => parent,
+    };
+
+    (Box::from_raw(max), next_max)
+}
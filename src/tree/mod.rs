@@ -1,5 +1,32 @@
+pub mod builder;
+pub mod deferred;
+pub mod diff;
 pub mod entry;
+pub mod handle;
+mod heat_map;
+pub mod histogram;
+pub mod iter;
+pub mod op;
+#[cfg(feature = "insertion_order")]
+pub mod order;
+pub mod pool;
+#[cfg(feature = "snapshots")]
+mod snapshot;
 pub mod splay_tree;
+pub mod succinct;
+pub mod transaction;
 
+pub use self::builder::*;
+pub use self::deferred::*;
+pub use self::diff::*;
 pub use self::entry::*;
+pub use self::handle::*;
+pub use self::histogram::*;
+pub use self::iter::*;
+pub use self::op::*;
+#[cfg(feature = "insertion_order")]
+pub use self::order::*;
+pub use self::pool::*;
 pub use self::splay_tree::*;
+pub use self::succinct::*;
+pub use self::transaction::*;
@@ -0,0 +1,23 @@
+/// Operation on an entry of a `SplayTree`, applied in batches by
+/// [`SplayTreeBy::apply_batch`].
+///
+/// [`SplayTreeBy::apply_batch`]: crate::SplayTreeBy::apply_batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<K, V> {
+    /// Inserts a value with a key, replacing the value if the key is present.
+    Insert(K, V),
+    /// Removes the entry with a key, if any.
+    Remove(K),
+    /// Replaces the value of an entry with a key, if any, without inserting it.
+    Update(K, V),
+}
+
+impl<K, V> Op<K, V> {
+    /// Returns the key the operation applies to.
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Op::Insert(key, _) | Op::Remove(key) | Op::Update(key, _) => key,
+        }
+    }
+}
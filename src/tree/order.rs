@@ -0,0 +1,192 @@
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use crate::{Node, NodePtr};
+
+/// Nodes of a tree threaded into a doubly-linked list in the order of insertion.
+///
+/// Every node also keeps the sequence number of its insertion, so the nodes moved
+/// into another tree by splitting can be threaded again in the same order.
+pub(crate) struct InsertionList<K, V> {
+    oldest: NodePtr<K, V>,
+    newest: NodePtr<K, V>,
+    next_seq: u64,
+}
+
+impl<K, V> InsertionList<K, V> {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        InsertionList { oldest: None, newest: None, next_seq: 0 }
+    }
+
+    /// Threads the nodes of the subtree at `root` in the order of their sequence
+    /// numbers, and in the order of keys if the numbers are equal, and renumbers
+    /// them from zero.
+    ///
+    /// # Safety
+    ///
+    /// `root` must be the root of a subtree which isn't threaded into another list.
+    pub(crate) unsafe fn thread(root: NodePtr<K, V>) -> Self {
+        let mut nodes = Vec::new();
+        let mut cur = root.map(|root| Node::leftmost(root));
+        while let Some(node_ptr) = cur {
+            nodes.push(node_ptr);
+            cur = Node::successor(node_ptr);
+        }
+        nodes.sort_by_key(|node_ptr| (*node_ptr.as_ptr()).seq);
+
+        let mut list = InsertionList::new();
+        for node_ptr in nodes {
+            list.push(node_ptr);
+        }
+        list
+    }
+
+    /// Returns the least recently inserted node.
+    #[inline]
+    pub(crate) fn oldest(&self) -> NodePtr<K, V> {
+        self.oldest
+    }
+
+    /// Returns the most recently inserted node.
+    #[inline]
+    pub(crate) fn newest(&self) -> NodePtr<K, V> {
+        self.newest
+    }
+
+    /// Threads a new node as the most recently inserted one.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a node which isn't threaded into any list.
+    #[inline]
+    pub(crate) unsafe fn push(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        (*node_ptr.as_ptr()).seq = self.next_seq;
+        self.next_seq += 1;
+        self.link_newest(node_ptr);
+    }
+
+    /// Threads the nodes of `other` after the nodes of the list, renumbering them.
+    ///
+    /// # Safety
+    ///
+    /// The lists must be threaded through distinct nodes.
+    pub(crate) unsafe fn append(&mut self, other: Self) {
+        let mut cur = other.oldest;
+        while let Some(node_ptr) = cur {
+            cur = (*node_ptr.as_ptr()).newer;
+            self.push(node_ptr);
+        }
+    }
+
+    /// Unthreads a node, clearing its links.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a node threaded into this list.
+    #[inline]
+    pub(crate) unsafe fn unlink(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        let node = node_ptr.as_ptr();
+        let older = (*node).older.take();
+        let newer = (*node).newer.take();
+        match older {
+            Some(older_ptr) => (*older_ptr.as_ptr()).newer = newer,
+            None => self.oldest = newer,
+        }
+        match newer {
+            Some(newer_ptr) => (*newer_ptr.as_ptr()).older = older,
+            None => self.newest = older,
+        }
+    }
+
+    /// Forgets all nodes, keeping the sequence numbers increasing.
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.oldest = None;
+        self.newest = None;
+    }
+
+    #[inline]
+    unsafe fn link_newest(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        (*node_ptr.as_ptr()).older = self.newest;
+        (*node_ptr.as_ptr()).newer = None;
+        match self.newest {
+            Some(newest_ptr) => (*newest_ptr.as_ptr()).newer = Some(node_ptr),
+            None => self.oldest = Some(node_ptr),
+        }
+        self.newest = Some(node_ptr);
+    }
+}
+
+/// An iterator over the entries of a `SplayTree` in the order of their insertion.
+///
+/// Created by [`SplayTreeBy::iter_insertion_order`](crate::SplayTreeBy::iter_insertion_order).
+pub struct InsertionOrder<'a, K, V> {
+    front: NodePtr<K, V>,
+    back: NodePtr<K, V>,
+    length: usize,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+// SAFETY: the iterator only gives out shared references, like `&SplayTree`.
+unsafe impl<'a, K: Sync, V: Sync> Send for InsertionOrder<'a, K, V> {}
+
+// SAFETY: `&InsertionOrder` doesn't give any access to the nodes.
+unsafe impl<'a, K: Sync, V: Sync> Sync for InsertionOrder<'a, K, V> {}
+
+impl<'a, K, V> InsertionOrder<'a, K, V> {
+    #[inline]
+    pub(crate) fn new(list: &InsertionList<K, V>, length: usize) -> Self {
+        InsertionOrder {
+            front: list.oldest(),
+            back: list.newest(),
+            length,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for InsertionOrder<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        let node = unsafe { &*self.front?.as_ptr() };
+        self.front = node.newer;
+        self.length -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for InsertionOrder<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        let node = unsafe { &*self.back?.as_ptr() };
+        self.back = node.older;
+        self.length -= 1;
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for InsertionOrder<'a, K, V> {}
+
+impl<'a, K, V> FusedIterator for InsertionOrder<'a, K, V> {}
+
+impl<'a, K, V> Clone for InsertionOrder<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        InsertionOrder { ..*self }
+    }
+}
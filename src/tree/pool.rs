@@ -0,0 +1,246 @@
+use std::mem::{self, MaybeUninit};
+use std::ptr::{self, NonNull};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{Node, NodePtr};
+
+type Block<K, V> = Box<MaybeUninit<Node<K, V>>>;
+
+/// Pool of node allocations shared by several trees, created once and passed
+/// to [`SplayTreeBy::with_pool`].
+///
+/// A tree takes the nodes of new entries from the pool once its own spare nodes
+/// run out, and gives its nodes back when their entries are removed beyond the
+/// capacity it has reserved, and when it's dropped or shrunk with
+/// [`SplayTreeBy::shrink_to_fit`]. Applications juggling many small trees
+/// then reuse the same allocations instead of going to the allocator for every
+/// entry. Cloning the pool gives another handle to the same nodes.
+///
+/// [`SplayTreeBy::with_pool`]: crate::SplayTreeBy::with_pool
+/// [`SplayTreeBy::shrink_to_fit`]: crate::SplayTreeBy::shrink_to_fit
+pub struct NodePool<K, V> {
+    blocks: Arc<Mutex<Vec<Block<K, V>>>>,
+}
+
+impl<K, V> NodePool<K, V> {
+    /// Creates an empty pool.
+    #[inline]
+    pub fn new() -> Self {
+        NodePool { blocks: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// Creates a pool holding `capacity` free nodes.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let pool = NodePool::new();
+        pool.reserve(capacity);
+        pool
+    }
+
+    /// Allocates free nodes until the pool holds at least `count` of them.
+    pub fn reserve(&self, count: usize) {
+        let mut blocks = self.lock();
+        let additional = count.saturating_sub(blocks.len());
+        blocks.reserve(additional);
+        blocks.extend((0..additional).map(|_| Box::new(MaybeUninit::uninit())));
+    }
+
+    /// Returns the number of free nodes in the pool.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the pool holds no free nodes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Frees all free nodes of the pool. Nodes used by trees are kept.
+    #[inline]
+    pub fn shrink_to_fit(&self) {
+        *self.lock() = Vec::new();
+    }
+
+    #[inline]
+    fn pop(&self) -> Option<Block<K, V>> {
+        self.lock().pop()
+    }
+
+    #[inline]
+    fn push(&self, block: Block<K, V>) {
+        self.lock().push(block);
+    }
+
+    #[inline]
+    fn append(&self, blocks: &mut Vec<Block<K, V>>) {
+        self.lock().append(blocks);
+    }
+
+    /// Locks the blocks. The critical sections only move blocks around and leave
+    /// the list consistent even if they panic, so poisoning is ignored.
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Vec<Block<K, V>>> {
+        self.blocks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K, V> Clone for NodePool<K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        NodePool { blocks: Arc::clone(&self.blocks) }
+    }
+}
+
+impl<K, V> Default for NodePool<K, V> {
+    #[inline]
+    fn default() -> Self {
+        NodePool::new()
+    }
+}
+
+/// Allocations of nodes which a tree has reserved but doesn't use yet.
+///
+/// Every block has the layout of a boxed node, so a node taken from the spares
+/// is freed like any other node, and detached nodes can be handed out as boxes.
+/// Nodes of removed entries are kept only up to the number of blocks reserved
+/// explicitly, so a shrinking tree doesn't hold on to its peak memory. If the tree
+/// uses a shared pool, the blocks go back to it instead of being freed.
+pub(crate) struct Spares<K, V> {
+    blocks: Vec<Block<K, V>>,
+    reserved: usize,
+    pool: Option<NodePool<K, V>>,
+}
+
+impl<K, V> Spares<K, V> {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Spares { blocks: Vec::new(), reserved: 0, pool: None }
+    }
+
+    #[inline]
+    pub(crate) fn with_pool(pool: NodePool<K, V>) -> Self {
+        Spares { blocks: Vec::new(), reserved: 0, pool: Some(pool) }
+    }
+
+    /// Returns the shared pool, if any.
+    #[inline]
+    pub(crate) fn pool(&self) -> Option<&NodePool<K, V>> {
+        self.pool.as_ref()
+    }
+
+    /// Returns the number of spare blocks.
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Allocates blocks until there are at least `count` of them, and keeps up
+    /// to `count` blocks of removed entries from now on.
+    pub(crate) fn reserve(&mut self, count: usize) {
+        self.reserved = self.reserved.max(count);
+        let additional = count.saturating_sub(self.blocks.len());
+        self.blocks.reserve(additional);
+        self.blocks.extend((0..additional).map(|_| Box::new(MaybeUninit::uninit())));
+    }
+
+    /// Makes a new node in a spare block, then in a block of the shared pool,
+    /// or in a new allocation if there are no blocks left.
+    #[inline]
+    pub(crate) fn alloc(&mut self, key: K, value: V) -> NonNull<Node<K, V>> {
+        let block = self.blocks.pop().or_else(|| self.pool.as_ref()?.pop());
+        match block {
+            Some(block) => {
+                let node = Box::into_raw(Box::write(block, Node::new(key, value)));
+                unsafe { NonNull::new_unchecked(node) }
+            },
+            None => Node::alloc(key, value),
+        }
+    }
+
+    /// Drops the entries of the subtree at `root`, keeping its nodes as spare blocks
+    /// up to the reserved number and giving the rest to the shared pool, or freeing
+    /// them if there is no pool.
+    pub(crate) unsafe fn recycle(&mut self, root: NodePtr<K, V>) {
+        let mut surplus = Vec::new();
+        let mut cur_ptr = root;
+
+        while let Some(node_ptr) = cur_ptr {
+            let node = node_ptr.as_ptr();
+            cur_ptr = if let Some(left_ptr) = (*node).left {
+                let left = left_ptr.as_ptr();
+                (*node).left = (*left).right;
+                (*left).right = Some(node_ptr);
+                Some(left_ptr)
+            } else {
+                let right = (*node).right;
+                ptr::drop_in_place(node);
+                let block = Box::from_raw(node.cast::<MaybeUninit<Node<K, V>>>());
+                if self.blocks.len() < self.reserved {
+                    self.blocks.push(block);
+                } else if self.pool.is_some() {
+                    surplus.push(block);
+                }
+                right
+            };
+        }
+
+        if let Some(pool) = &self.pool {
+            pool.append(&mut surplus);
+        }
+    }
+
+    /// Moves the entry out of a detached node, keeping the node as a spare block
+    /// if fewer than the reserved number are kept, like [`recycle`](Self::recycle).
+    #[inline]
+    pub(crate) fn take_entry(&mut self, node: Box<Node<K, V>>) -> (K, V) {
+        let node = Box::into_raw(node);
+        let entry = unsafe { ptr::read(node) }.into_key_value();
+        let block = unsafe { Box::from_raw(node.cast::<MaybeUninit<Node<K, V>>>()) };
+        if self.blocks.len() < self.reserved {
+            self.blocks.push(block);
+        } else if let Some(pool) = &self.pool {
+            pool.push(block);
+        }
+        entry
+    }
+
+    /// Drops the entries of the subtree at `root`, giving its nodes back to the
+    /// shared pool, or freeing them if there is no pool.
+    pub(crate) unsafe fn release(&mut self, root: NodePtr<K, V>) {
+        if self.pool.is_some() {
+            self.recycle(root);
+            self.clear();
+        } else {
+            Node::free(root);
+        }
+    }
+
+    /// Gives all spare blocks back to the shared pool, or frees them if there is no pool,
+    /// and stops keeping the blocks of removed entries.
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.reserved = 0;
+        match &self.pool {
+            Some(pool) => pool.append(&mut self.blocks),
+            None => self.blocks = Vec::new(),
+        }
+    }
+
+    /// Returns an approximate number of bytes of heap memory held by the spare blocks.
+    #[inline]
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.blocks.len() * mem::size_of::<Node<K, V>>()
+            + self.blocks.capacity() * mem::size_of::<Block<K, V>>()
+    }
+}
+
+impl<K, V> Drop for Spares<K, V> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(pool) = &self.pool {
+            pool.append(&mut self.blocks);
+        }
+    }
+}
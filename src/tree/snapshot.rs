@@ -0,0 +1,93 @@
+use std::fmt::{self, Display, Write};
+use std::ptr::NonNull;
+
+use crate::tree::heat_map::Escaped;
+use crate::{Node, NodePtr};
+
+/// Receiver of the structure snapshots of a tree, set with
+/// [`SplayTreeBy::set_snapshot_sink`](crate::SplayTreeBy::set_snapshot_sink).
+///
+/// Every snapshot is a single line of JSON:
+///
+/// ```json
+/// {"op":"insert","key":"2","depth":2,"root":"2","len":3,"tree":[[null,"1",null],"2",[null,"3",null]]}
+/// ```
+///
+/// `depth` is the depth of the node with `key` before the operation, `root` is
+/// the key of the root afterwards, or `null` if the tree is empty, and `tree` is
+/// the resulting shape, with every node written as `[left,"key",right]`.
+pub(crate) struct SnapshotSink<K> {
+    emit: Box<dyn FnMut(&str) + Send>,
+    write_key: fn(&K, &mut String) -> fmt::Result,
+    line: String,
+}
+
+impl<K> SnapshotSink<K> {
+    #[inline]
+    pub(crate) fn new<F>(emit: F) -> Self
+        where K: Display, F: FnMut(&str) + Send + 'static
+    {
+        SnapshotSink {
+            emit: Box::new(emit),
+            write_key: |key, out| write!(Escaped(out), "{}", key),
+            line: String::new(),
+        }
+    }
+
+    /// Writes a snapshot of the tree at `root` after `op` on the entry with `key`
+    /// and passes it to the sink.
+    pub(crate) fn emit<V>(&mut self, op: &str, key: &K, depth: usize, root: NodePtr<K, V>, len: usize) {
+        self.line.clear();
+        // Writing into a `String` only fails if the `Display` impl of a key does.
+        if self.write(op, key, depth, root, len).is_ok() {
+            (self.emit)(&self.line);
+        }
+    }
+
+    fn write<V>(&mut self, op: &str, key: &K, depth: usize, root: NodePtr<K, V>, len: usize) -> fmt::Result {
+        let out = &mut self.line;
+        write!(out, "{{\"op\":\"{}\",\"key\":\"", op)?;
+        (self.write_key)(key, out)?;
+        write!(out, "\",\"depth\":{},\"root\":", depth)?;
+        match root {
+            Some(root_ptr) => {
+                out.push('"');
+                (self.write_key)(unsafe { &(*root_ptr.as_ptr()).key }, out)?;
+                out.push('"');
+            },
+            None => out.push_str("null"),
+        }
+        write!(out, ",\"len\":{},\"tree\":", len)?;
+
+        // Children are written after the key of their parent, so the stack holds
+        // both nodes to open and the closing parts of the ones being written.
+        let mut stack = vec![Part::Subtree(root)];
+        while let Some(part) = stack.pop() {
+            match part {
+                Part::Subtree(None) => out.push_str("null"),
+                Part::Subtree(Some(node_ptr)) => {
+                    let node = unsafe { &*node_ptr.as_ptr() };
+                    out.push('[');
+                    stack.push(Part::Close);
+                    stack.push(Part::Subtree(node.right));
+                    stack.push(Part::Key(node_ptr));
+                    stack.push(Part::Subtree(node.left));
+                },
+                Part::Key(node_ptr) => {
+                    out.push_str(",\"");
+                    (self.write_key)(unsafe { &(*node_ptr.as_ptr()).key }, out)?;
+                    out.push_str("\",");
+                },
+                Part::Close => out.push(']'),
+            }
+        }
+        out.push('}');
+        Ok(())
+    }
+}
+
+enum Part<K, V> {
+    Subtree(NodePtr<K, V>),
+    Key(NonNull<Node<K, V>>),
+    Close,
+}
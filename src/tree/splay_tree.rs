@@ -1,42 +1,461 @@
 use std::marker::PhantomData;
 use std::cmp::Ordering;
-use std::mem;
+use std::ptr::NonNull;
+use std::alloc::Layout;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ptr;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug, Display};
+use std::ops::{Bound, ControlFlow, RangeBounds};
 
-use crate::{Node, NodePtr, Entry, VacantEntry, OccupiedEntry};
+use crate::{Node, NodeMut, NodePtr, Entry, VacantEntry, OccupiedEntry, Iter, IntoIter, LendingIterMut, AdjacentPairs, AccessOrder, Range, LevelOrder, PreOrder, PostOrder, FrozenTree};
+use crate::{EntryRef, VacantEntryRef, RawEntry, RawVacantEntry};
+use crate::{Compare, Equivalent, Natural, ByKey, TotalOrder, NodeId, NodePool, Diff, DeferredRef, Observer, Op, Transaction};
+use crate::{SuccinctShape, SuccinctError, DepthHistogram};
+use crate::tree::handle::Handles;
+use crate::tree::pool::Spares;
+use crate::tree::heat_map;
+use crate::tree::succinct;
+#[cfg(feature = "insertion_order")]
+use crate::tree::order::{InsertionList, InsertionOrder};
 use crate::Entry::*;
+#[cfg(feature = "stats")]
+use crate::TreeStats;
+#[cfg(feature = "snapshots")]
+use crate::tree::snapshot::SnapshotSink;
 
-/// Splay tree. [Read more](https://en.wikipedia.org/wiki/Splay_tree).
-pub struct SplayTree<K: Ord, V> {
-    root: NodePtr<K, V>,
+/// Splay tree ordering keys by their `Ord` implementation.
+/// [Read more](https://en.wikipedia.org/wiki/Splay_tree).
+///
+/// It's a [`SplayTreeBy`] with the [`Natural`] comparator, see its documentation
+/// for the details.
+pub type SplayTree<K, V> = SplayTreeBy<K, V, Natural>;
+
+/// Splay tree ordering keys by a comparator `C`.
+///
+/// A panic in the comparator doesn't corrupt the tree: keys are only compared
+/// while searching, before any link is changed, so the tree stays valid and keeps
+/// all of its entries.
+///
+/// With the `tracing` feature, insertions and removals run inside `trace`-level
+/// spans, and splaying emits events with the depth of the splayed node. Keys aren't
+/// recorded, since they aren't required to implement `Debug`; wrap calls into
+/// a span of your own to attach them.
+///
+/// With the `metrics` feature, all trees report to the installed `metrics` recorder:
+/// the `splay_tree.len` gauge holds the total number of entries, the
+/// `splay_tree.access_depth` histogram gets the depth of every splayed node, and
+/// the `splay_tree.rotations` counter gets the number of rotations.
+pub struct SplayTreeBy<K, V, C> {
+    pub(crate) root: NodePtr<K, V>,
     length: usize,
+    cmp: C,
+    pub(crate) handles: Handles<K, V>,
+    spares: Spares<K, V>,
+    #[cfg(feature = "insertion_order")]
+    order: InsertionList<K, V>,
+    observer: Option<Box<dyn Observer<K, V> + Send>>,
+    #[cfg(feature = "stats")]
+    stats: TreeStats,
+    #[cfg(feature = "snapshots")]
+    snapshots: Option<SnapshotSink<K>>,
     marker: PhantomData<Box<Node<K, V>>>,
 }
 
-enum FindResult<K: Ord, V> {
-    Found(*mut Node<K, V>),
-    GoDown(*mut Node<K, V>),
+// SAFETY: the tree uniquely owns all of its nodes, the raw pointers are never
+// shared with another tree, and every operation that follows them to mutate
+// anything (splaying included) requires `&mut self`.
+unsafe impl<K: Send, V: Send, C: Send> Send for SplayTreeBy<K, V, C> {}
+
+// SAFETY: `&SplayTreeBy` only gives out shared references to keys and values,
+// and the observer and the snapshot sink are only reachable through `&mut SplayTreeBy`.
+unsafe impl<K: Sync, V: Sync, C: Sync> Sync for SplayTreeBy<K, V, C> {}
+
+enum FindResult<K, V> {
+    Found(NonNull<Node<K, V>>),
+    GoDown(NonNull<Node<K, V>>, Ordering),
     NotFound,
 }
 
 use self::FindResult::{Found, GoDown, NotFound};
 
-impl<K: Ord, V> SplayTree<K, V> {
-    /// Creates an empty `SplayTree`.
+#[cfg(feature = "metrics")]
+const LEN_GAUGE: &str = "splay_tree.len";
+
+impl<K, V, C: Compare<K> + Default> SplayTreeBy<K, V, C> {
+    /// Creates an empty tree.
     #[inline]
     pub fn new() -> Self {
-        SplayTree {
-            root: None,
-            length: 0,
+        SplayTreeBy::with_comparator(C::default())
+    }
+
+    /// Creates an empty tree with space for at least `capacity` entries, which
+    /// are inserted without calling the allocator.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut tree = SplayTreeBy::new();
+        tree.reserve(capacity);
+        tree
+    }
+
+    /// Rebuilds a tree with the shape returned by [`encode_succinct`](Self::encode_succinct)
+    /// from its entries in sorted order. Returns an error if the shape is malformed,
+    /// its number of nodes differs from the number of entries, or the keys aren't
+    /// strictly increasing.
+    ///
+    /// Keys are compared before any node is allocated.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn decode_succinct<I>(shape: &SuccinctShape, entries: I) -> Result<Self, SuccinctError>
+        where I: IntoIterator<Item = (K, V)>
+    {
+        let cmp = C::default();
+        let entries: Vec<(K, V)> = entries.into_iter().collect();
+        if let Some(pos) = entries.windows(2).position(|pair| {
+            cmp.compare(&pair[0].0, &pair[1].0) != Ordering::Less
+        }) {
+            return Err(SuccinctError::Unordered(pos + 1));
+        }
+        let length = entries.len();
+        let root = succinct::decode(shape, entries)?;
+        Ok(SplayTreeBy::from_root(root, length, cmp))
+    }
+
+    /// Creates an empty tree which takes nodes from `pool` and gives them back
+    /// once it's dropped.
+    #[inline]
+    pub fn with_pool(pool: NodePool<K, V>) -> Self {
+        let mut tree = SplayTreeBy::new();
+        tree.spares = Spares::with_pool(pool);
+        tree
+    }
+}
+
+impl<K, V, P: Ord, F: Fn(&K) -> P> SplayTreeBy<K, V, ByKey<F>> {
+    /// Creates an empty tree ordering keys by the result of `key`, e.g. structs
+    /// by one of their fields, without wrapping them into a newtype.
+    #[inline]
+    pub fn by_key(key: F) -> Self {
+        SplayTreeBy::with_comparator(ByKey::new(key))
+    }
+}
+
+impl<K, V> SplayTreeBy<K, V, TotalOrder>
+    where TotalOrder: Compare<K>
+{
+    /// Creates an empty tree with `f32` or `f64` keys ordered by the IEEE 754
+    /// `totalOrder` predicate.
+    #[inline]
+    pub fn with_total_order() -> Self {
+        SplayTreeBy::with_comparator(TotalOrder)
+    }
+}
+
+impl<K: Ord, V> SplayTree<K, V> {
+    /// Converts the tree into an immutable snapshot which supports lookups
+    /// through a shared reference and can be shared between threads.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    #[inline]
+    pub fn freeze(self) -> FrozenTree<K, V> {
+        FrozenTree::from(self)
+    }
+
+    /// Returns a reference to the node by a query compared with the keys through
+    /// [`Equivalent`], or `None` if the tree doesn't contain a matching key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn get_equiv<Q>(&mut self, query: &Q) -> Option<&Node<K, V>>
+        where Q: Equivalent<K> + ?Sized
+    {
+        match self.find_equiv(query) {
+            Found(_) => self.root(),
+            _ => None,
+        }
+    }
+
+    /// Returns mutable access to the node by a query compared with the keys
+    /// through [`Equivalent`], or `None` if the tree doesn't contain a matching key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn get_equiv_mut<Q>(&mut self, query: &Q) -> Option<NodeMut<'_, K, V>>
+        where Q: Equivalent<K> + ?Sized
+    {
+        match self.find_equiv(query) {
+            Found(_) => self.root_mut(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the tree contains a key matching a query compared with
+    /// the keys through [`Equivalent`].
+    #[inline]
+    pub fn contains_key_equiv<Q>(&mut self, query: &Q) -> bool
+        where Q: Equivalent<K> + ?Sized
+    {
+        self.get_equiv(query).is_some()
+    }
+
+    /// Removes the node by a query compared with the keys through [`Equivalent`]
+    /// and returns it, or `None` if the tree doesn't contain a matching key.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn remove_equiv<Q>(&mut self, query: &Q) -> Option<Box<Node<K, V>>>
+        where Q: Equivalent<K> + ?Sized
+    {
+        match self.find_equiv(query) {
+            Found(_) => self.remove_root(),
+            _ => None,
+        }
+    }
+
+    fn find_equiv<Q>(&mut self, query: &Q) -> FindResult<K, V>
+        where Q: Equivalent<K> + ?Sized
+    {
+        let (result, comparisons) = search(self.root, |node_key| query.compare(node_key));
+        self.finish_find(result, comparisons)
+    }
+}
+
+impl<K: Ord + Borrow<str>, V> SplayTree<K, V> {
+    /// Returns an iterator over the entries with keys starting with `prefix`,
+    /// sorted by key. The first of them is splayed.
+    ///
+    /// Keys starting with a prefix form a contiguous range in the order of `str`,
+    /// so the range is found by two descents and only its entries are visited.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time, plus *O*(1)
+    /// amortized time per yielded entry.
+    pub fn prefix_range(&mut self, prefix: &str) -> Range<'_, K, V> {
+        let mut front = None;
+        let mut cur = self.root;
+        while let Some(node_ptr) = cur {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            let key: &str = node.key.borrow();
+            if key >= prefix {
+                front = Some(node_ptr);
+                cur = node.left;
+            } else {
+                cur = node.right;
+            }
+        }
+        let front = front.filter(|node_ptr| {
+            let key: &str = unsafe { (*node_ptr.as_ptr()).key.borrow() };
+            key.starts_with(prefix)
+        });
+        let Some(front) = front else { return Range::new(None, None) };
+
+        let mut back = front;
+        let mut cur = self.root;
+        while let Some(node_ptr) = cur {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            let key: &str = node.key.borrow();
+            if key.starts_with(prefix) {
+                back = node_ptr;
+                cur = node.right;
+            } else if key < prefix {
+                cur = node.right;
+            } else {
+                cur = node.left;
+            }
+        }
+
+        unsafe { self.splay(front) };
+        Range::new(Some(front), Some(back))
+    }
+}
+
+impl<K, V, C> SplayTreeBy<K, V, C> {
+    /// Detaches all nodes from the tree, leaving it empty, and returns the root.
+    #[inline]
+    pub(crate) fn take_root(&mut self) -> NodePtr<K, V> {
+        #[cfg(feature = "metrics")]
+        if self.length > 0 {
+            metrics::gauge!(LEN_GAUGE).decrement(self.length as f64);
+        }
+        self.length = 0;
+        self.handles.clear();
+        #[cfg(feature = "insertion_order")]
+        self.order.clear();
+        self.root.take()
+    }
+
+    /// Makes a tree of nodes which may still be out of order.
+    #[inline]
+    pub(crate) fn from_root_unvalidated(root: NodePtr<K, V>, length: usize, cmp: C) -> Self {
+        #[cfg(feature = "metrics")]
+        if length > 0 {
+            metrics::gauge!(LEN_GAUGE).increment(length as f64);
+        }
+        SplayTreeBy {
+            root,
+            length,
+            cmp,
+            handles: Handles::new(),
+            spares: Spares::new(),
+            #[cfg(feature = "insertion_order")]
+            order: unsafe { InsertionList::thread(root) },
+            observer: None,
+            #[cfg(feature = "stats")]
+            stats: TreeStats::default(),
+            #[cfg(feature = "snapshots")]
+            snapshots: None,
             marker: PhantomData,
         }
     }
 
-    /// Returns a mutable reference to the root node, or `None` if the tree is empty.
+    /// Removes all entries from the tree. The nodes are freed, or given back to the
+    /// shared pool, except for as many as the tree has reserved with
+    /// [`reserve`](Self::reserve), which are kept for later insertions.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn clear(&mut self) {
+        let root = self.take_root();
+        if let Some(observer) = &mut self.observer {
+            for_each_node(root, |node_ptr| {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                observer.on_remove(&node.key, &node.value);
+            });
+        }
+        unsafe { self.spares.recycle(root) };
+    }
+
+    /// Splays the node at `ptr` to the root of the tree.
+    #[inline]
+    pub(crate) unsafe fn splay(&mut self, ptr: NonNull<Node<K, V>>) {
+        #[cfg(any(feature = "stats", feature = "tracing", feature = "metrics"))]
+        let depth = Node::depth(ptr);
+        let rotations = self.observer.as_ref().map(|_| Node::depth(ptr));
+        #[cfg(feature = "snapshots")]
+        let snapshot_depth = self.snapshots.as_ref().map(|_| Node::depth(ptr));
+        #[cfg(feature = "stats")]
+        self.stats.record_splay(depth);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(depth, "splay");
+        #[cfg(feature = "metrics")]
+        {
+            metrics::histogram!("splay_tree.access_depth").record(depth as f64);
+            metrics::counter!("splay_tree.rotations").increment(depth as u64);
+        }
+        self.root = Some(Node::splay(ptr));
+        if let (Some(observer), Some(rotations)) = (&mut self.observer, rotations) {
+            observer.on_rotate(rotations);
+        }
+        #[cfg(feature = "snapshots")]
+        if let Some(depth) = snapshot_depth {
+            self.emit_snapshot("splay", &(*ptr.as_ptr()).key, depth);
+        }
+    }
+
+    /// Passes a snapshot of the tree after `op` on the entry with `key` to the
+    /// snapshot sink, if there is one.
+    #[cfg(feature = "snapshots")]
+    fn emit_snapshot(&mut self, op: &str, key: &K, depth: usize) {
+        if let Some(snapshots) = &mut self.snapshots {
+            snapshots.emit(op, key, depth, self.root, self.length);
+        }
+    }
+
+    /// Reserves space for at least `additional` more entries, so that inserting
+    /// them doesn't call the allocator. From then on, up to `additional` nodes of
+    /// removed entries are kept for later insertions instead of being freed.
+    ///
+    /// This operation should compute in *O*(*additional*) time.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.spares.reserve(additional);
+    }
+
+    /// Frees the nodes kept by [`reserve`](Self::reserve) and stops keeping the
+    /// nodes of removed entries, so the tree only holds memory for its entries.
+    /// If the tree uses a shared pool, the nodes go back to it.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.spares.clear();
+    }
+
+    /// Returns the shared pool of nodes used by the tree, if any.
+    #[inline]
+    pub fn pool(&self) -> Option<&NodePool<K, V>> {
+        self.spares.pool()
+    }
+
+    /// Returns the number of entries the tree can hold without allocating.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.length + self.spares.len()
+    }
+
+    /// Takes the nodes and the comparator out of the tree without dropping them.
+    #[inline]
+    pub(crate) fn into_parts(self) -> (NodePtr<K, V>, usize, C) {
+        let mut tree = ManuallyDrop::new(self);
+        let length = tree.length;
+        let root = tree.take_root();
+        // SAFETY: the tree is never used again and its destructor doesn't run,
+        // so the handle table, the spares and the observer are dropped and the
+        // comparator is moved out only once.
+        unsafe {
+            ptr::drop_in_place(&mut tree.handles);
+            ptr::drop_in_place(&mut tree.spares);
+            #[cfg(feature = "insertion_order")]
+            ptr::drop_in_place(&mut tree.order);
+            ptr::drop_in_place(&mut tree.observer);
+            #[cfg(feature = "snapshots")]
+            ptr::drop_in_place(&mut tree.snapshots);
+            (root, length, ptr::read(&tree.cmp))
+        }
+    }
+
+    /// Decomposes the tree into a raw pointer to its root node, the number of its
+    /// entries and its comparator. The pointer is null if the tree is empty.
+    ///
+    /// The caller becomes responsible for the nodes, which are linked to each
+    /// other and individually allocated with the global allocator as
+    /// `Box<Node<K, V>>`. The only way to free them properly is to put them back
+    /// into a tree with [`from_raw_parts`](Self::from_raw_parts). Handles issued
+    /// by the tree become stale, and its observer is dropped. If the tree uses a
+    /// shared pool, its spare nodes go back to the pool.
+    #[inline]
+    pub fn into_raw_parts(self) -> (*mut Node<K, V>, usize, C) {
+        let (root, length, cmp) = self.into_parts();
+        let root = root.map_or(ptr::null_mut(), NonNull::as_ptr);
+        (root, length, cmp)
+    }
+}
+
+impl<K, V, C: Compare<K>> SplayTreeBy<K, V, C> {
+    /// Creates an empty tree ordering keys by `cmp`.
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        SplayTreeBy::from_root(None, 0, cmp)
+    }
+
+    /// Returns a reference to the comparator of the tree.
+    #[inline]
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    /// Builds a balanced tree from entries sorted by key without duplicates.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub(crate) fn from_sorted_iter<I>(mut iter: I, cmp: C) -> Self
+        where I: ExactSizeIterator<Item = (K, V)>
+    {
+        let length = iter.len();
+        SplayTreeBy::from_root(build_balanced(&mut iter, length), length, cmp)
+    }
+
+    /// Returns mutable access to the root node, or `None` if the tree is empty.
     ///
     /// This operation should compute in *O*(1) time.
     #[inline]
-    pub fn root_mut(&mut self) -> Option<&mut Node<K, V>> {
-        self.root.map(|mut r| unsafe { r.as_mut() })
+    pub fn root_mut(&mut self) -> Option<NodeMut<'_, K, V>> {
+        self.root.map(|r| unsafe { NodeMut::new(r) })
     }
 
     /// Returns a reference to the root node, or `None` if the tree is empty.
@@ -47,36 +466,183 @@ impl<K: Ord, V> SplayTree<K, V> {
         self.root.map(|r| unsafe { r.as_ref() })
     }
 
-    /// Returns a mutable reference to the node by a key,
+    /// Reconstitutes a tree from the parts returned by
+    /// [`into_raw_parts`](Self::into_raw_parts).
+    ///
+    /// # Safety
+    ///
+    /// `root` must be null or a pointer returned by `into_raw_parts`, and `length`
+    /// must be the number returned with it. `cmp` must order keys the same way as
+    /// the comparator of the decomposed tree. The nodes must not have been changed
+    /// or freed in the meantime, and must be put back into a tree only once.
+    #[inline]
+    pub unsafe fn from_raw_parts(root: *mut Node<K, V>, length: usize, cmp: C) -> Self {
+        SplayTreeBy::from_root(NonNull::new(root), length, cmp)
+    }
+
+    #[inline]
+    pub(crate) fn from_root(root: NodePtr<K, V>, length: usize, cmp: C) -> Self {
+        let tree = SplayTreeBy::from_root_unvalidated(root, length, cmp);
+        tree.debug_validate();
+        tree
+    }
+
+    /// Returns mutable access to the node by a key,
     /// or `None` if the tree doesn't contain that key.
     ///
+    /// The key may be any borrowed form of the tree's key type, which the
+    /// comparator must order consistently with the key type.
+    ///
     /// This operation should compute in amortized *O*(*log n*) time.
-    pub fn get_mut(&mut self, key: &K) -> Option<&mut Node<K, V>> {
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<NodeMut<'_, K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
         match self.find_ptr(key) {
-            Found(node_ptr) => unsafe { Some(&mut *node_ptr) },
+            Found(_) => self.root_mut(),
             _ => None,
         }
     }
 
     /// Returns a reference to the node by a key,
-    /// or `None` if the tree doesn't contain that key
+    /// or `None` if the tree doesn't contain that key.
+    ///
+    /// The key may be any borrowed form of the tree's key type, which the
+    /// comparator must order consistently with the key type.
     ///
     /// This operation should compute in amortized *O*(*log n*) time.
-    pub fn get(&mut self, key: &K) -> Option<&Node<K, V>> {
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
         match self.find_ptr(key) {
-            Found(node_ptr) => unsafe { Some(&*node_ptr) },
+            Found(_) => self.root(),
             _ => None,
         }
     }
 
-    /// Returns a mutable reference to the node with a maximum key,
+    /// Returns a guard giving access to the value by a key, or `None` if the tree
+    /// doesn't contain that key.
+    ///
+    /// Unlike [`get`](Self::get), the node isn't splayed by the lookup but when
+    /// the guard is dropped, and only if the value has been read through it.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn get_deferred<Q>(&mut self, key: &Q) -> Option<DeferredRef<'_, K, V, C>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let node_ptr = self.lookup_ptr(key)?;
+        Some(DeferredRef::new(self, node_ptr))
+    }
+
+    /// Returns a reference to the node with the smallest key greater than or equal
+    /// to `key`, or `None` if there is no such node.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_ceil<Q>(&mut self, key: &Q) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.splay_neighbour(key, Ordering::Greater, true)?;
+        self.root()
+    }
+
+    /// Returns a reference to the node with the greatest key less than or equal
+    /// to `key`, or `None` if there is no such node.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn get_floor<Q>(&mut self, key: &Q) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.splay_neighbour(key, Ordering::Less, true)?;
+        self.root()
+    }
+
+    /// Returns a reference to the first node whose key isn't less than the sought
+    /// position, or `None` if there is no such node, and splays it.
+    ///
+    /// `f` is called with the keys on the search path and must return the ordering
+    /// of the sought position relative to them, like in [`entry_by`](Self::entry_by),
+    /// consistently with the comparator of the tree. The sought position may be
+    /// described by a part of the key only, like the timestamp of a composite key.
+    /// The returned node can be used as a cursor with [`Node::next`] and [`Node::prev`].
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn lower_bound_by<F>(&mut self, mut f: F) -> Option<&Node<K, V>>
+        where F: FnMut(&K) -> Ordering
+    {
+        self.splay_partition_point(|key| f(key) != Ordering::Greater)?;
+        self.root()
+    }
+
+    /// Returns a reference to the first node whose key is greater than the sought
+    /// position, or `None` if there is no such node, and splays it.
+    ///
+    /// `f` is called like in [`lower_bound_by`](Self::lower_bound_by).
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn upper_bound_by<F>(&mut self, mut f: F) -> Option<&Node<K, V>>
+        where F: FnMut(&K) -> Ordering
+    {
+        self.splay_partition_point(|key| f(key) == Ordering::Less)?;
+        self.root()
+    }
+
+    /// Returns a reference to the node with the smallest key greater than `key`,
+    /// or `None` if there is no such node. The key doesn't have to be in the tree.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn next_after<Q>(&mut self, key: &Q) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.splay_neighbour(key, Ordering::Greater, false)?;
+        self.root()
+    }
+
+    /// Returns a reference to the node with the greatest key less than `key`,
+    /// or `None` if there is no such node. The key doesn't have to be in the tree.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn prev_before<Q>(&mut self, key: &Q) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.splay_neighbour(key, Ordering::Less, false)?;
+        self.root()
+    }
+
+    /// Looks up several keys at once and returns their nodes in the order of `keys`,
+    /// with `None` for the keys the tree doesn't contain.
+    ///
+    /// The keys are sorted, unless they already are, and searched for in a single
+    /// descent which splits them between the subtrees, so no node is visited twice.
+    /// Only the node of the last key found is splayed.
+    ///
+    /// This operation should compute in *O*(*m log m* + min(*n*, *m h*)) time for *m*
+    /// keys, where *h* is the height of the tree, or without the *m log m* term for
+    /// sorted keys.
+    pub fn get_batch<'q, Q, I>(&mut self, keys: I) -> Vec<Option<&Node<K, V>>>
+        where K: Borrow<Q>, Q: ?Sized + 'q, C: Compare<Q>, I: IntoIterator<Item = &'q Q>
+    {
+        let keys: Vec<&Q> = keys.into_iter().collect();
+        let (found, _) = self.locate_batch(&keys);
+        if let Some(&Some(last)) = found.iter().rev().find(|node_ptr| node_ptr.is_some()) {
+            unsafe { self.splay(last) };
+        }
+        found.into_iter()
+            .map(|node_ptr| node_ptr.map(|node_ptr| unsafe { &*node_ptr.as_ptr() }))
+            .collect()
+    }
+
+    /// Returns mutable access to the node with a maximum key,
     /// or `None` if the tree is empty.
     ///
     /// This operation should compute in amortized *O*(*log n*) time.
     #[inline]
-    pub fn get_max_mut(&mut self) -> Option<&mut Node<K, V>> {
-        let max = self.root_mut()?.find_max();
-        self.root = max.splay();
+    pub fn get_max_mut(&mut self) -> Option<NodeMut<'_, K, V>> {
+        unsafe { self.splay(Node::rightmost(self.root?)) };
         self.root_mut()
     }
 
@@ -86,20 +652,36 @@ impl<K: Ord, V> SplayTree<K, V> {
     /// This operation should compute in amortized *O*(*log n*) time.
     #[inline]
     pub fn get_max(&mut self) -> Option<&Node<K, V>> {
-        let max = self.root_mut()?.find_max();
-        self.root = max.splay();
+        unsafe { self.splay(Node::rightmost(self.root?)) };
         self.root()
     }
 
 
-    /// Returns a mutable reference to the node with a minimum key,
+    /// Returns a reference to the node with a minimum key, or `None` if the tree
+    /// is empty. Unlike [`get_min`](Self::get_min), the tree isn't splayed.
+    ///
+    /// This operation should compute in *O*(*h*) time, where *h* is the height of the tree.
+    #[inline]
+    pub fn peek_min(&self) -> Option<&Node<K, V>> {
+        self.root.map(|r| unsafe { &*Node::leftmost(r).as_ptr() })
+    }
+
+    /// Returns a reference to the node with a maximum key, or `None` if the tree
+    /// is empty. Unlike [`get_max`](Self::get_max), the tree isn't splayed.
+    ///
+    /// This operation should compute in *O*(*h*) time, where *h* is the height of the tree.
+    #[inline]
+    pub fn peek_max(&self) -> Option<&Node<K, V>> {
+        self.root.map(|r| unsafe { &*Node::rightmost(r).as_ptr() })
+    }
+
+    /// Returns mutable access to the node with a minimum key,
     /// or `None` if the tree is empty.
     ///
     /// This operation should compute in amortized *O*(*log n*) time.
     #[inline]
-    pub fn get_min_mut(&mut self) -> Option<&mut Node<K, V>> {
-        let min = self.root_mut()?.find_min();
-        self.root = min.splay();
+    pub fn get_min_mut(&mut self) -> Option<NodeMut<'_, K, V>> {
+        unsafe { self.splay(Node::leftmost(self.root?)) };
         self.root_mut()
     }
 
@@ -109,8 +691,7 @@ impl<K: Ord, V> SplayTree<K, V> {
     /// This operation should compute in amortized *O*(*log n*) time.
     #[inline]
     pub fn get_min(&mut self) -> Option<&Node<K, V>> {
-        let min = self.root_mut()?.find_min();
-        self.root = min.splay();
+        unsafe { self.splay(Node::leftmost(self.root?)) };
         self.root()
     }
 
@@ -119,30 +700,47 @@ impl<K: Ord, V> SplayTree<K, V> {
     ///
     /// This operation should compute in amortized *O*(*log n*) time.
     #[inline]
-    pub fn insert<'a>(&'a mut self, key: K, value: V) -> &'a mut Node<K, V> {
+    pub fn insert(&mut self, key: K, value: V) -> NodeMut<'_, K, V> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("splay_tree::insert", len = self.length).entered();
         self.entry(key).insert(value)
     }
 
-    #[inline]
-    pub(crate) fn insert_child<'a>(
-        &'a mut self,
-        maybe_parent: Option<&'a mut Node<K, V>>,
+    /// Links a new node as a child of `parent` on the side given by the ordering
+    /// of its key, or as the root if `parent` is `None`, and splays it.
+    pub(crate) fn insert_child(
+        &mut self,
+        parent: Option<(NonNull<Node<K, V>>, Ordering)>,
         key: K,
         value: V
-    ) -> Option<&'a mut Node<K, V>> {
-        if let Some(parent) = maybe_parent {
-            let node = parent.insert_child(key, value)?;
-            self.root = node.splay();
-            self.length += 1;
-            Some(node)
-        } else if self.root.is_none() {
-            let node = Box::leak(Box::new(Node::new(key, value))).into();
-            self.root = Some(node);
-            self.length += 1;
-            self.root_mut()
-        } else {
-            None
+    ) -> NodeMut<'_, K, V> {
+        let node_ptr = self.spares.alloc(key, value);
+        #[cfg(feature = "stats")]
+        {
+            self.stats.allocations += 1;
+        }
+        #[cfg(feature = "insertion_order")]
+        unsafe { self.order.push(node_ptr) };
+        #[cfg(feature = "snapshots")]
+        let snapshot_depth = self.snapshots.as_ref()
+            .map(|_| parent.map_or(0, |(parent_ptr, _)| unsafe { Node::depth(parent_ptr) } + 1));
+
+        // The length is updated first, so that splaying reports the new entry.
+        self.length += 1;
+        unsafe { self.link(parent, node_ptr) };
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(LEN_GAUGE).increment(1.0);
+        self.debug_validate();
+
+        #[cfg(feature = "snapshots")]
+        if let Some(depth) = snapshot_depth {
+            self.emit_snapshot("insert", unsafe { &(*node_ptr.as_ptr()).key }, depth);
+        }
+        if let Some(observer) = &mut self.observer {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            observer.on_insert(&node.key, &node.value);
         }
+        unsafe { NodeMut::new(node_ptr) }
     }
 
     /// Removes a node with a given key and returns it, or `None` if the tree
@@ -150,118 +748,2219 @@ impl<K: Ord, V> SplayTree<K, V> {
     ///
     /// This operation should compute in amortized *O*(*log n*) time.
     #[inline]
-    pub fn remove(&mut self, key: &K) -> Option<Box<Node<K, V>>> {
-        let node = self.get_mut(key)?;
-        let node_ptr: *const Node<K, V> = node;
-        let left = node.left().map(|l| unsafe {
-            &mut *mem::transmute::<*const Node<K, V>, *mut Node<K, V>>(l)
-        });
-        let right = node.right_mut();
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Box<Node<K, V>>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("splay_tree::remove", len = self.length).entered();
+        self.get_mut(key)?;
+        self.remove_root()
+    }
 
-        self.root = match (left, right) {
-            (Some(l), Some(r)) => {
-                l.parent = None;
-                r.parent = None;
-                l.merge(r)
-            },
-            (Some(l), None) => {
-                l.parent = None;
-                Some(l.into())
-            }
-            (None, Some(r)) => {
-                r.parent = None;
-                Some(r.into())
+    /// Removes the entry with a given key and returns its value, or `None` if the
+    /// tree doesn't contain that key. The key is dropped, and the node is kept for
+    /// later insertions if the tree has reserved room for it, like in [`clear`](Self::clear).
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn take<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let node = self.remove(key)?;
+        Some(self.spares.take_entry(node).1)
+    }
+
+    /// Removes all entries with keys in `range` and returns their number.
+    ///
+    /// The tree is split around the range by splaying the neighbours of its ends,
+    /// so the removed entries form a single subtree which is dropped as a whole.
+    /// Its nodes are freed or kept like the ones of [`clear`](Self::clear).
+    /// Keys are compared before any link is changed, and if an inconsistent
+    /// comparator makes the ends of the range cross, nothing is removed.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time,
+    /// plus *O*(*k*) time to drop the *k* removed entries.
+    pub fn remove_range<Q, R>(&mut self, range: R) -> usize
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>, R: RangeBounds<Q>
+    {
+        let (Some(first), Some(last)) = self.range_ends(&range) else { return 0 };
+        let middle = unsafe {
+            let Some(middle) = self.isolate_range(first, last) else { return 0 };
+            match (*middle.as_ptr()).parent.take() {
+                Some(parent_ptr) => {
+                    if (*parent_ptr.as_ptr()).left == Some(middle) {
+                        (*parent_ptr.as_ptr()).left = None;
+                    } else {
+                        (*parent_ptr.as_ptr()).right = None;
+                    }
+                    Node::update_sizes_up(Some(parent_ptr));
+                },
+                None => self.root = None,
             }
-            _ => None,
+            Some(middle)
         };
 
-        self.length -= 1;
-        
-        unsafe {
-            let node = &mut *mem::transmute::<*const Node<K, V>, *mut Node<K, V>>(
-                node_ptr
-            );
-            node.left = None;
-            node.right = None;
-            Some(node.ref_into_box())
-        }
-    }
+        let mut length = 0;
+        for_each_node(middle, |node_ptr| {
+            self.handles.release(node_ptr);
+            #[cfg(feature = "insertion_order")]
+            unsafe { self.order.unlink(node_ptr) };
+            length += 1;
+        });
+        self.length -= length;
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(LEN_GAUGE).decrement(length as f64);
+        self.debug_validate();
 
-    /// Returns `true` if the map contains no elements.
-    #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.root.is_none()
+        if let Some(observer) = &mut self.observer {
+            for_each_node(middle, |node_ptr| {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                observer.on_remove(&node.key, &node.value);
+            });
+        }
+        unsafe { self.spares.recycle(middle) };
+        length
     }
 
-    /// Returns `true` if the map contains a value for the specified key.
-    #[inline]
-    pub fn contains_key(&mut self, key: &K) -> bool {
-        self.get(&key).is_some()
+    /// Brings the neighbours of `range` to the top of the tree, so that all entries
+    /// with keys in `range` form a single subtree, and returns its root, or `None`
+    /// if the range is empty. If an inconsistent comparator makes the ends of the
+    /// range cross, `None` is returned and no subtree is relinked.
+    ///
+    /// The entry after the range is splayed to the root and the entry before it
+    /// below, so the subtree is the left subtree of the former or the right subtree
+    /// of the latter. Its [`subtree_len`](Node::subtree_len) is the number of
+    /// entries in the range, and its nodes can be walked for range aggregates.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn splay_range<Q, R>(&mut self, range: R) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>, R: RangeBounds<Q>
+    {
+        let (Some(first), Some(last)) = self.range_ends(&range) else { return None };
+        unsafe { self.isolate_range(first, last).map(|middle| middle.as_ref()) }
     }
 
-    /// Gets the given key’s corresponding entry in the tree for in-place manipulation.
+    /// Returns the number of entries with keys in `range`, counted by the sizes
+    /// of subtrees without visiting the entries. The nodes where the searches for
+    /// the ends of the range stop are splayed.
     ///
     /// This operation should compute in amortized *O*(*log n*) time.
-    pub fn entry<'a>(&'a mut self, key: K) -> Entry<'a, K, V> {
-        match self.find_ptr(&key) {
-            NotFound => {
-                Vacant(VacantEntry::new_root(self, key))
-            },
-            GoDown(parent_ptr) => {
-                Vacant(VacantEntry::new_elem(self, unsafe { &mut *parent_ptr }, key))
-            },
-            Found(node_ptr) => {
-                Occupied(OccupiedEntry::new(unsafe { &mut *node_ptr }))
-            },
+    pub fn len_in_range<Q, R>(&mut self, range: R) -> usize
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>, R: RangeBounds<Q>
+    {
+        let cmp = &self.cmp;
+        let (before_start, start_ptr) = rank(self.root, |key| match range.start_bound() {
+            Bound::Included(start) => cmp.compare(key.borrow(), start) == Ordering::Less,
+            Bound::Excluded(start) => cmp.compare(key.borrow(), start) != Ordering::Greater,
+            Bound::Unbounded => false,
+        });
+        let (up_to_end, end_ptr) = rank(self.root, |key| match range.end_bound() {
+            Bound::Included(end) => cmp.compare(key.borrow(), end) != Ordering::Greater,
+            Bound::Excluded(end) => cmp.compare(key.borrow(), end) == Ordering::Less,
+            Bound::Unbounded => true,
+        });
+
+        for node_ptr in start_ptr.into_iter().chain(end_ptr) {
+            unsafe { self.splay(node_ptr) };
         }
+        up_to_end.saturating_sub(before_start)
     }
 
-    #[inline]
-    fn find_ptr(&mut self, key: &K) -> FindResult<K, V> {
-        let mut cur_node = if let Some(root) = self.root_mut() {
-            root
-        } else {
-            return NotFound
-        };
-        let mut is_found = false;
-
-        loop {
-            let ptr: *mut Node<K, V> = cur_node;
-            let next_node = match key.cmp(cur_node.key()) {
-                Ordering::Less => cur_node.left_mut(),
-                Ordering::Equal => {
-                    is_found = true;
-                    self.root = cur_node.splay();
-                    None
+    /// Splays the successor of `last` to the root and the predecessor of `first`
+    /// below it, so that the nodes from `first` to `last` form a single subtree,
+    /// and returns its root.
+    ///
+    /// Returns `None` without changing any link if `first` comes after `last`,
+    /// which an inconsistent comparator can make [`range_ends`](Self::range_ends)
+    /// return.
+    ///
+    /// # Safety
+    ///
+    /// `first` and `last` must point to nodes of this tree.
+    unsafe fn isolate_range(&mut self, first: NonNull<Node<K, V>>, last: NonNull<Node<K, V>>) -> NodePtr<K, V> {
+        if Node::position(first) > Node::position(last) {
+            return None;
+        }
+        let pred = Node::predecessor(first);
+        let succ = Node::successor(last);
+        if let Some(succ) = succ {
+            self.splay(succ);
+        }
+        if let Some(pred) = pred {
+            match succ {
+                // The predecessor is splayed within the left subtree of the successor.
+                Some(succ) => {
+                    if !Node::is_in_subtree(pred, (*succ.as_ptr()).left) {
+                        return None;
+                    }
+                    self.root = (*succ.as_ptr()).left.take();
+                    if let Some(left) = self.root {
+                        (*left.as_ptr()).parent = None;
+                    }
+                    self.splay(pred);
+                    (*succ.as_ptr()).left = Some(pred);
+                    (*pred.as_ptr()).parent = Some(succ);
+                    Node::update_size(succ);
+                    self.root = Some(succ);
                 },
-                Ordering::Greater => cur_node.right_mut(),
-            };
+                None => self.splay(pred),
+            }
+        }
 
-            cur_node = if let Some(next) = next_node {
-                next
-            } else if is_found {
-                return Found(ptr)
-            } else {
-                return GoDown(ptr)
-            };
+        match (pred, succ) {
+            (Some(pred), _) => (*pred.as_ptr()).right,
+            (None, Some(succ)) => (*succ.as_ptr()).left,
+            (None, None) => self.root,
         }
     }
 
-    /// Returns the length of the `SplayTree`.
-    ///
-    /// This operation should compute in *O*(1) time.
-    #[inline]
-    pub fn len(&self) -> usize {
+    /// Finds the first and the last node with keys in `range`, which are both `None`
+    /// if the range is empty. Nothing is modified while keys are compared.
+    fn range_ends<Q, R>(&self, range: &R) -> (NodePtr<K, V>, NodePtr<K, V>)
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>, R: RangeBounds<Q>
+    {
+        let Some(root) = self.root else { return (None, None) };
+        let cmp = &self.cmp;
+        let past_end = |key: &K| match range.end_bound() {
+            Bound::Included(end) => cmp.compare(key.borrow(), end) == Ordering::Greater,
+            Bound::Excluded(end) => cmp.compare(key.borrow(), end) != Ordering::Less,
+            Bound::Unbounded => false,
+        };
+
+        let first = partition_point(root, |key| match range.start_bound() {
+            Bound::Included(start) => cmp.compare(key.borrow(), start) != Ordering::Less,
+            Bound::Excluded(start) => cmp.compare(key.borrow(), start) == Ordering::Greater,
+            Bound::Unbounded => true,
+        });
+        let Some(first) = first.filter(|first| !past_end(unsafe { &(*first.as_ptr()).key })) else {
+            return (None, None);
+        };
+        let last = match partition_point(root, past_end) {
+            Some(end) => unsafe { Node::predecessor(end) },
+            None => Some(unsafe { Node::rightmost(root) }),
+        };
+        (Some(first), last)
+    }
+
+    /// Removes the entries with the given keys and returns them sorted by key.
+    /// Keys the tree doesn't contain and repeated keys are skipped.
+    ///
+    /// The nodes are found like in [`get_batch`](Self::get_batch), before any of
+    /// them is unlinked, and then unlinked in place without splaying. The nodes
+    /// are kept for later insertions like in [`take`](Self::take).
+    ///
+    /// This operation should compute in *O*(*m log m* + *m h*) time for *m* keys,
+    /// where *h* is the height of the tree.
+    pub fn remove_keys<'q, Q, I>(&mut self, keys: I) -> Vec<(K, V)>
+        where K: Borrow<Q>, Q: ?Sized + 'q, C: Compare<Q>, I: IntoIterator<Item = &'q Q>
+    {
+        let keys: Vec<&Q> = keys.into_iter().collect();
+        let (found, order) = self.locate_batch(&keys);
+
+        let mut removed = Vec::new();
+        let mut prev_ptr = None;
+        for node_ptr in order.into_iter().filter_map(|i| found[i]) {
+            if prev_ptr != Some(node_ptr) {
+                prev_ptr = Some(node_ptr);
+                let node = unsafe { self.unlink(node_ptr) };
+                removed.push(self.spares.take_entry(node));
+            }
+        }
+        removed
+    }
+
+    /// Applies a batch of operations, with the same result as applying them one by one.
+    ///
+    /// The operations are stably sorted by key first, so operations on the same
+    /// key keep their order, and then applied one at a time in key order, each
+    /// like the corresponding single-key method. If the comparator panics while
+    /// sorting, the tree is left unchanged.
+    ///
+    /// This operation should compute in *O*(*m log m*) time for *m* operations,
+    /// plus amortized *O*(*log n*) time for each of them.
+    pub fn apply_batch<I: IntoIterator<Item = Op<K, V>>>(&mut self, ops: I) {
+        let mut ops: Vec<Op<K, V>> = ops.into_iter().collect();
+        let cmp = &self.cmp;
+        ops.sort_by(|a, b| cmp.compare(a.key(), b.key()));
+
+        for op in ops {
+            match op {
+                Op::Insert(key, value) => {
+                    self.entry(key).insert(value);
+                },
+                Op::Remove(key) => {
+                    self.remove(&key);
+                },
+                Op::Update(key, value) => {
+                    if let Occupied(mut entry) = self.entry(key) {
+                        entry.insert(value);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Runs `f` with a transaction staging changes of the tree, and applies them
+    /// with [`apply_batch`](Self::apply_batch) if `f` returns `Ok`. If `f` returns
+    /// `Err` or panics, the changes are discarded and the tree is left exactly as
+    /// it was, since it isn't even splayed while the transaction is open.
+    pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+        where F: FnOnce(&mut Transaction<'_, K, V, C>) -> Result<T, E>
+    {
+        let mut txn = Transaction::new(self);
+        let result = f(&mut txn)?;
+        let ops = txn.into_ops();
+        self.apply_batch(ops);
+        Ok(result)
+    }
+
+    /// Returns a reference to the node by a key without splaying it.
+    pub(crate) fn lookup<Q>(&self, key: &Q) -> Option<&Node<K, V>>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.lookup_ptr(key).map(|node_ptr| unsafe { &*node_ptr.as_ptr() })
+    }
+
+    /// Returns a pointer to the node by a key without splaying it.
+    fn lookup_ptr<Q>(&self, key: &Q) -> NodePtr<K, V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        match search(self.root, |node_key| self.cmp.compare(key, node_key.borrow())).0 {
+            Found(node_ptr) => Some(node_ptr),
+            _ => None,
+        }
+    }
+
+    /// Removes the root node, which holds the most recently accessed entry,
+    /// and returns it, or `None` if the tree is empty. No search is needed.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    #[inline]
+    pub fn pop_root(&mut self) -> Option<Box<Node<K, V>>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("splay_tree::pop_root", len = self.length).entered();
+        self.remove_root()
+    }
+
+    /// Moves the entries with keys greater than or equal to `key` into a new tree.
+    ///
+    /// The tree is split by splaying, so the moved entries form a single subtree
+    /// which is detached as a whole, without reallocating the nodes. Handles of
+    /// the moved nodes become stale.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time,
+    /// plus *O*(*k*) time to count the *k* moved entries.
+    pub fn detach_subtree(&mut self, key: &K) -> Self
+        where C: Clone
+    {
+        let detached = match self.find_ptr(key) {
+            NotFound => None,
+            GoDown(parent_ptr, Ordering::Greater) => unsafe {
+                self.splay(parent_ptr);
+                let right = (*parent_ptr.as_ptr()).right.take();
+                if let Some(right) = right {
+                    (*right.as_ptr()).parent = None;
+                }
+                Node::update_size(parent_ptr);
+                right
+            },
+            Found(node_ptr) | GoDown(node_ptr, _) => unsafe {
+                if self.root != Some(node_ptr) {
+                    self.splay(node_ptr);
+                }
+                let left = (*node_ptr.as_ptr()).left.take();
+                if let Some(left) = left {
+                    (*left.as_ptr()).parent = None;
+                }
+                Node::update_size(node_ptr);
+                self.root = left;
+                Some(node_ptr)
+            },
+        };
+        self.split_detached(detached)
+    }
+
+    /// Moves the entries after the `n` smallest ones into a new tree, so the tree
+    /// keeps the `n` smallest entries, like `Vec::split_off`. If the tree has no
+    /// more than `n` entries, the new tree is empty.
+    ///
+    /// The node at rank `n` is found by subtree sizes and splayed, so the moved
+    /// entries are detached as a whole, without reallocating the nodes. Handles of
+    /// the moved nodes become stale.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time,
+    /// plus *O*(*k*) time to count the *k* moved entries.
+    pub fn split_at_rank(&mut self, n: usize) -> Self
+        where C: Clone
+    {
+        let detached = select(self.root, n);
+        if let Some(node_ptr) = detached {
+            unsafe {
+                if self.root != Some(node_ptr) {
+                    self.splay(node_ptr);
+                }
+                let left = (*node_ptr.as_ptr()).left.take();
+                if let Some(left) = left {
+                    (*left.as_ptr()).parent = None;
+                }
+                Node::update_size(node_ptr);
+                self.root = left;
+            }
+        }
+        self.split_detached(detached)
+    }
+
+    /// Accounts for the subtree at `detached`, which has been cut off the tree,
+    /// as removed and makes a new tree of it.
+    fn split_detached(&mut self, detached: NodePtr<K, V>) -> Self
+        where C: Clone
+    {
+        let mut length = 0;
+        for_each_node(detached, |node_ptr| {
+            self.handles.release(node_ptr);
+            #[cfg(feature = "insertion_order")]
+            unsafe { self.order.unlink(node_ptr) };
+            length += 1;
+        });
+        self.length -= length;
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(LEN_GAUGE).decrement(length as f64);
+        self.debug_validate();
+
+        if let Some(observer) = &mut self.observer {
+            for_each_node(detached, |node_ptr| {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                observer.on_remove(&node.key, &node.value);
+            });
+        }
+
+        SplayTreeBy::from_root(detached, length, self.cmp.clone())
+    }
+
+    /// Attaches all entries of `subtree` to the tree without reallocating the nodes,
+    /// if its keys are all greater or all less than the keys of the tree.
+    /// Otherwise `subtree` is returned back unchanged.
+    ///
+    /// Handles of `subtree`'s nodes become stale.
+    ///
+    /// This operation should compute in amortized *O*(*log n* + *log m*) time.
+    // The subtree is handed back as is, however large the tree type grows with features.
+    #[allow(clippy::result_large_err)]
+    pub fn graft(&mut self, mut subtree: Self) -> Result<(), Self> {
+        let (self_ptr, subtree_ptr) = match (self.root, subtree.root) {
+            (_, None) => return Ok(()),
+            (None, Some(_)) => {
+                self.absorb(None, &mut subtree);
+                return Ok(());
+            },
+            (Some(self_ptr), Some(subtree_ptr)) => (self_ptr, subtree_ptr),
+        };
+
+        unsafe {
+            let max_ptr = Node::rightmost(self_ptr);
+            let min_ptr = Node::leftmost(subtree_ptr);
+            if self.cmp.compare(&(*max_ptr.as_ptr()).key, &(*min_ptr.as_ptr()).key) == Ordering::Less {
+                self.splay(max_ptr);
+                self.absorb(Some((max_ptr, Ordering::Greater)), &mut subtree);
+                return Ok(());
+            }
+
+            let min_ptr = Node::leftmost(self_ptr);
+            let max_ptr = Node::rightmost(subtree_ptr);
+            if self.cmp.compare(&(*max_ptr.as_ptr()).key, &(*min_ptr.as_ptr()).key) == Ordering::Less {
+                self.splay(min_ptr);
+                self.absorb(Some((min_ptr, Ordering::Less)), &mut subtree);
+                return Ok(());
+            }
+        }
+
+        Err(subtree)
+    }
+
+    /// Moves all entries of `other` into the tree, calling `resolve` with the key,
+    /// the value of the tree and the value of `other` for every key contained in both,
+    /// and keeping the value it returns. Both values are moved into `resolve`, so the
+    /// observer sees the value of the tree removed and the resolved one inserted.
+    ///
+    /// If the keys of `other` don't overlap the keys of the tree, it's grafted as
+    /// a whole. If `other` is much smaller than the tree, its entries are inserted
+    /// one by one. Otherwise both trees are merged in order and their nodes are
+    /// relinked into a balanced tree. Either way, the handles of the entries of the
+    /// tree stay valid. If `resolve` panics, the entry whose value it was called with
+    /// is removed, the tree keeps all of its other entries and the entries of `other`
+    /// merged so far, and the rest of `other` is dropped. When relinking, keys are
+    /// compared before any link is changed, so if comparing panics, the tree is left
+    /// unchanged.
+    ///
+    /// This operation should compute in amortized *O*(*log n* + *log m*) time if the
+    /// keys don't overlap, in amortized *O*(*m log n*) time if *m log n* < *n*, and in
+    /// *O*(*n* + *m*) time otherwise.
+    pub fn merge_with<F>(&mut self, other: Self, mut resolve: F)
+        where F: FnMut(&K, V, V) -> V
+    {
+        let mut other = match self.graft(other) {
+            Ok(()) => return,
+            Err(other) => other,
+        };
+
+        let log_len = (usize::BITS - self.length.leading_zeros()) as usize;
+        if other.length.saturating_mul(log_len) < self.length {
+            self.insert_resolving(other, &mut resolve);
+            return;
+        }
+
+        let mut nodes = sorted_nodes(self.root).into_iter().peekable();
+        let mut other_nodes = sorted_nodes(other.root).into_iter().peekable();
+        let mut merged = Vec::with_capacity(nodes.len() + other_nodes.len());
+        let mut added = Vec::new();
+        let mut collisions = Vec::new();
+        loop {
+            let ordering = match (nodes.peek(), other_nodes.peek()) {
+                (Some(node_ptr), Some(other_ptr)) => unsafe {
+                    self.cmp.compare(&(*node_ptr.as_ptr()).key, &(*other_ptr.as_ptr()).key)
+                },
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+
+            match ordering {
+                Ordering::Less => merged.extend(nodes.next()),
+                Ordering::Greater => {
+                    let other_ptr = other_nodes.next().expect("node was peeked");
+                    merged.push(other_ptr);
+                    added.push(other_ptr);
+                },
+                Ordering::Equal => {
+                    let node_ptr = nodes.next().expect("node was peeked");
+                    merged.push(node_ptr);
+                    collisions.push((node_ptr, other_nodes.next().expect("node was peeked")));
+                },
+            }
+        }
+
+        // Entries of `other` are inserted after the entries of the tree, and colliding
+        // ones keep the place of the entries of the tree.
+        #[cfg(feature = "insertion_order")]
+        unsafe {
+            self.order.append(mem::replace(&mut other.order, InsertionList::new()));
+            for &(_, other_ptr) in &collisions {
+                self.order.unlink(other_ptr);
+            }
+        }
+        other.take_root();
+        self.root = unsafe { link_sorted(&merged) };
+        self.length += added.len();
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(LEN_GAUGE).increment(added.len() as f64);
+        self.debug_validate();
+
+        if let Some(observer) = &mut self.observer {
+            for &node_ptr in &added {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                observer.on_insert(&node.key, &node.value);
+            }
+        }
+
+        // The colliding nodes of `other` are owned from here on, so if `resolve`
+        // panics, the ones which are left are dropped.
+        let collisions: Vec<_> = collisions.into_iter()
+            .map(|(node_ptr, other_ptr)| (node_ptr, unsafe { Box::from_raw(other_ptr.as_ptr()) }))
+            .collect();
+        for (node_ptr, other_node) in collisions {
+            let (_, value) = other_node.into_key_value();
+            unsafe { self.resolve_value(node_ptr, value, &mut resolve) };
+        }
+    }
+
+    /// Inserts the entries of `other` one by one, in the order of their insertion
+    /// with the `insertion_order` feature and in the order of keys otherwise,
+    /// resolving collisions like [`merge_with`](Self::merge_with).
+    fn insert_resolving<F>(&mut self, other: Self, resolve: &mut F)
+        where F: FnMut(&K, V, V) -> V
+    {
+        #[cfg_attr(not(feature = "insertion_order"), allow(unused_mut))]
+        let mut nodes = sorted_nodes(other.root);
+        #[cfg(feature = "insertion_order")]
+        nodes.sort_by_key(|node_ptr| unsafe { (*node_ptr.as_ptr()).seq });
+        other.into_parts();
+
+        // The nodes are owned from here on, so if `resolve` panics, the ones which
+        // are left are dropped.
+        let nodes: Vec<_> = nodes.into_iter()
+            .map(|node_ptr| unsafe { Box::from_raw(node_ptr.as_ptr()) })
+            .collect();
+        for node in nodes {
+            let (key, value) = node.into_key_value();
+            match self.entry(key) {
+                Occupied(entry) => entry.resolve(value, resolve),
+                Vacant(entry) => {
+                    entry.insert(value);
+                },
+            }
+        }
+    }
+
+    /// Replaces the value of the node at `node_ptr` with the result of `resolve`
+    /// called with its key, its value and `value`. The old value is moved into
+    /// `resolve`, so the observer sees it removed and the resolved value inserted.
+    /// If `resolve` panics, the node is removed from the tree.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a node of this tree.
+    pub(crate) unsafe fn resolve_value<F>(
+        &mut self,
+        node_ptr: NonNull<Node<K, V>>,
+        value: V,
+        resolve: &mut F
+    )
+        where F: FnMut(&K, V, V) -> V
+    {
+        let node = node_ptr.as_ptr();
+        if let Some(observer) = &mut self.observer {
+            observer.on_remove(&(*node).key, &(*node).value);
+        }
+        let guard = MovedValue { tree: self, node_ptr };
+        let resolved = resolve(&(*node).key, ptr::read(&(*node).value), value);
+        mem::forget(guard);
+        ptr::write(&mut (*node).value, resolved);
+        if let Some(observer) = &mut self.observer {
+            observer.on_insert(&(*node).key, &(*node).value);
+        }
+    }
+
+    /// Removes every entry whose key is in `other` and returns the number of
+    /// removed entries.
+    ///
+    /// Both trees are walked in the order of keys at once, without splaying, and
+    /// the remaining nodes are relinked into a balanced tree, so their handles stay
+    /// valid. Keys are compared before any link is changed, so if comparing panics,
+    /// the tree is left unchanged.
+    ///
+    /// This operation should compute in *O*(*n* + *m*) time.
+    pub fn difference_with<V2>(&mut self, other: &SplayTreeBy<K, V2, C>) -> usize {
+        self.retain_by_other(other, false)
+    }
+
+    /// Keeps only the entries whose keys are in `other` and returns the number
+    /// of removed entries.
+    ///
+    /// Both trees are walked in the order of keys at once, like in
+    /// [`difference_with`](Self::difference_with), so the remaining nodes keep
+    /// their handles and a panic while comparing leaves the tree unchanged.
+    ///
+    /// This operation should compute in *O*(*n* + *m*) time.
+    pub fn intersect_with<V2>(&mut self, other: &SplayTreeBy<K, V2, C>) -> usize {
+        self.retain_by_other(other, true)
+    }
+
+    /// Removes the entries whose presence in `other` differs from `keep_common`
+    /// by a merge walk over both trees, and returns their number.
+    fn retain_by_other<V2>(&mut self, other: &SplayTreeBy<K, V2, C>, keep_common: bool) -> usize {
+        let mut keys = other.iter().map(|(key, _)| key).peekable();
+        let keep: Vec<bool> = self.iter()
+            .map(|(key, _)| {
+                let ordering = |other_key: &&K| self.cmp.compare(other_key, key);
+                while keys.next_if(|other_key| ordering(other_key) == Ordering::Less).is_some() {}
+                let common = keys.next_if(|other_key| ordering(other_key) == Ordering::Equal).is_some();
+                common == keep_common
+            })
+            .collect();
+        self.retain_in_order(keep)
+    }
+
+    /// Drops the entries for which `f` returns `false` and relinks the rest into
+    /// a balanced tree, without comparing keys. If `f` panics, the tree is left
+    /// unchanged.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub(crate) fn rebuild_retaining<F>(&mut self, mut f: F)
+        where F: FnMut(&K, &mut V) -> bool
+    {
+        let mut keep = Vec::with_capacity(self.length);
+        let mut cur = self.root.map(|root| unsafe { Node::leftmost(root) });
+        while let Some(node_ptr) = cur {
+            let node = unsafe { &mut *node_ptr.as_ptr() };
+            keep.push(f(&node.key, &mut node.value));
+            cur = unsafe { Node::successor(node_ptr) };
+        }
+        self.retain_in_order(keep);
+    }
+
+    /// Removes the entries whose flags in `keep`, given in the order of keys, are
+    /// `false`, relinks the remaining nodes into a balanced tree, and returns the
+    /// number of removed entries. The tree is left unchanged if nothing is removed.
+    fn retain_in_order(&mut self, keep: Vec<bool>) -> usize {
+        if keep.iter().all(|&keep| keep) {
+            return 0;
+        }
+
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+        let mut cur = self.root.map(|root| unsafe { Node::leftmost(root) });
+        for keep in keep {
+            let Some(node_ptr) = cur else { break };
+            cur = unsafe { Node::successor(node_ptr) };
+            if keep {
+                kept.push(node_ptr);
+            } else {
+                removed.push(node_ptr);
+            }
+        }
+
+        for &node_ptr in &removed {
+            self.handles.release(node_ptr);
+            #[cfg(feature = "insertion_order")]
+            unsafe { self.order.unlink(node_ptr) };
+        }
+        self.root = unsafe { link_sorted(&kept) };
+        self.length = kept.len();
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(LEN_GAUGE).decrement(removed.len() as f64);
+        self.debug_validate();
+
+        for &node_ptr in &removed {
+            if let Some(observer) = &mut self.observer {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                observer.on_remove(&node.key, &node.value);
+            }
+            unsafe {
+                (*node_ptr.as_ptr()).left = None;
+                (*node_ptr.as_ptr()).right = None;
+                self.spares.recycle(Some(node_ptr));
+            }
+        }
+        removed.len()
+    }
+
+    /// Moves all nodes of `subtree` below `parent` like [`attach`](Self::attach),
+    /// leaving `subtree` empty.
+    fn absorb(&mut self, parent: Option<(NonNull<Node<K, V>>, Ordering)>, subtree: &mut Self) {
+        let length = subtree.length;
+        #[cfg(feature = "insertion_order")]
+        let order = mem::replace(&mut subtree.order, InsertionList::new());
+        let root = subtree.take_root();
+        #[cfg(feature = "insertion_order")]
+        unsafe { self.order.append(order) };
+        self.attach(parent, root, length);
+        self.observe_attached(root);
+    }
+
+    /// Links a detached subtree of `length` nodes as a child of `parent` on the side
+    /// given by the ordering, or as the root if `parent` is `None`.
+    fn attach(
+        &mut self,
+        parent: Option<(NonNull<Node<K, V>>, Ordering)>,
+        root: NodePtr<K, V>,
+        length: usize
+    ) {
+        match parent {
+            Some((parent_ptr, ordering)) => unsafe {
+                if let Some(root) = root {
+                    (*root.as_ptr()).parent = Some(parent_ptr);
+                }
+                if ordering == Ordering::Less {
+                    (*parent_ptr.as_ptr()).left = root;
+                } else {
+                    (*parent_ptr.as_ptr()).right = root;
+                }
+                Node::update_sizes_up(Some(parent_ptr));
+            },
+            None => self.root = root,
+        }
+
+        self.length += length;
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(LEN_GAUGE).increment(length as f64);
+        self.debug_validate();
+    }
+
+    /// Notifies the observer about every node of an attached subtree.
+    fn observe_attached(&mut self, root: NodePtr<K, V>) {
+        if let Some(observer) = &mut self.observer {
+            for_each_node(root, |node_ptr| {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                observer.on_insert(&node.key, &node.value);
+            });
+        }
+    }
+
+    /// Links a detached node as a child of `parent` on the side given by the ordering
+    /// of its key, or as the root if `parent` is `None`, and splays it.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a detached node, and `parent` must be the place
+    /// where a search for its key has stopped.
+    unsafe fn link(
+        &mut self,
+        parent: Option<(NonNull<Node<K, V>>, Ordering)>,
+        node_ptr: NonNull<Node<K, V>>
+    ) {
+        if let Some((parent_ptr, ordering)) = parent {
+            (*node_ptr.as_ptr()).parent = Some(parent_ptr);
+            if ordering == Ordering::Less {
+                (*parent_ptr.as_ptr()).left = Some(node_ptr);
+            } else {
+                (*parent_ptr.as_ptr()).right = Some(node_ptr);
+            }
+            Node::update_sizes_up(Some(parent_ptr));
+            self.splay(node_ptr);
+        } else {
+            self.root = Some(node_ptr);
+        }
+    }
+
+    /// Swaps the values of the entries of `a` and `b` in place, keeping the nodes
+    /// and their handles. Returns `false` and leaves the tree unchanged if it
+    /// doesn't contain both keys.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn swap_values<Q>(&mut self, a: &Q, b: &Q) -> bool
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let a_ptr = match self.find_ptr(a) {
+            Found(node_ptr) => node_ptr,
+            _ => return false,
+        };
+        let b_ptr = match self.find_ptr(b) {
+            Found(node_ptr) => node_ptr,
+            _ => return false,
+        };
+        if a_ptr == b_ptr {
+            return true;
+        }
+
+        unsafe {
+            let (a_node, b_node) = (&mut *a_ptr.as_ptr(), &mut *b_ptr.as_ptr());
+            mem::swap(&mut a_node.value, &mut b_node.value);
+            if let Some(observer) = &mut self.observer {
+                observer.on_replace(&a_node.key, &b_node.value, &a_node.value);
+                observer.on_replace(&b_node.key, &a_node.value, &b_node.value);
+            }
+        }
+        true
+    }
+
+    /// Changes the key of an entry, keeping its value and its handle. If the new key
+    /// keeps the entry between the same neighbours, the key is replaced in place,
+    /// otherwise the node is relinked at the position of the new key.
+    ///
+    /// Returns the old key, or gives `new` back if the tree doesn't contain `old`,
+    /// already contains `new` in another entry, or an inconsistent comparator
+    /// places `new` right below the entry itself.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn replace_key(&mut self, old: &K, new: K) -> Result<K, K> {
+        let node_ptr = match self.find_ptr(old) {
+            Found(node_ptr) => node_ptr,
+            _ => return Err(new),
+        };
+
+        unsafe {
+            let cmp = &self.cmp;
+            let after_prev = Node::predecessor(node_ptr)
+                .is_none_or(|prev_ptr| cmp.compare(&(*prev_ptr.as_ptr()).key, &new) == Ordering::Less);
+            let before_next = Node::successor(node_ptr)
+                .is_none_or(|next_ptr| cmp.compare(&new, &(*next_ptr.as_ptr()).key) == Ordering::Less);
+            let old = if after_prev && before_next {
+                mem::replace(&mut (*node_ptr.as_ptr()).key, new)
+            } else {
+                // Every comparison is made before the node is moved, so a panicking
+                // comparator leaves the tree as it was.
+                let cmp = &self.cmp;
+                let (result, comparisons) = search(self.root, |node_key| cmp.compare(&new, node_key));
+                let (parent_ptr, ordering) = match self.finish_find(result, comparisons) {
+                    GoDown(parent_ptr, ordering) => (parent_ptr, ordering),
+                    _ => return Err(new),
+                };
+
+                // The new key doesn't fit next to the old one, so `parent_ptr` stays its
+                // neighbour once the node is detached, even if it's one of the former
+                // descendants of the node. Only an inconsistent comparator can put the
+                // new key right below the node, which would be linked to itself.
+                if parent_ptr == node_ptr {
+                    return Err(new);
+                }
+                self.detach(node_ptr);
+                let parent = match (ordering, (*parent_ptr.as_ptr()).left, (*parent_ptr.as_ptr()).right) {
+                    (Ordering::Less, Some(left), _) => (Node::rightmost(left), Ordering::Greater),
+                    (Ordering::Greater, _, Some(right)) => (Node::leftmost(right), Ordering::Less),
+                    _ => (parent_ptr, ordering),
+                };
+                let old = mem::replace(&mut (*node_ptr.as_ptr()).key, new);
+                self.link(Some(parent), node_ptr);
+                self.debug_validate();
+                old
+            };
+
+            if let Some(observer) = &mut self.observer {
+                let node = &*node_ptr.as_ptr();
+                observer.on_remove(&old, &node.value);
+                observer.on_insert(&node.key, &node.value);
+            }
+            Ok(old)
+        }
+    }
+
+    /// Consumes the tree and returns a tree of the same shape with every value
+    /// replaced by the result of `f`, which is called in the order of keys.
+    ///
+    /// The nodes are reused in place if the new ones have the same layout. If `f`
+    /// panics, all entries are dropped, the mapped ones included.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn map_values<V2, F>(self, mut f: F) -> SplayTreeBy<K, V2, C>
+        where F: FnMut(&K, V) -> V2
+    {
+        let (root, length, cmp) = self.into_parts();
+        let root = map_nodes(root, |key, value| {
+            let value = f(&key, value);
+            (key, value)
+        });
+        SplayTreeBy::from_root(root, length, cmp)
+    }
+
+    /// Consumes the tree and splits it into a tree of the entries for which `pred`
+    /// returns `true` and a tree of the rest, reusing the nodes. Both trees are
+    /// balanced and have no observer.
+    ///
+    /// `pred` is called in the order of keys before any link is changed, so if it
+    /// panics, the tree is dropped as usual.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn partition<F>(self, mut pred: F) -> (Self, Self)
+        where F: FnMut(&K, &V) -> bool, C: Clone
+    {
+        let matches: Vec<bool> = self.iter().map(|(key, value)| pred(key, value)).collect();
+        let (root, _, cmp) = self.into_parts();
+        let mut matching = Vec::new();
+        let mut rest = Vec::new();
+        let mut cur = root.map(|r| unsafe { Node::leftmost(r) });
+        for matched in matches {
+            let Some(node_ptr) = cur else { break };
+            cur = unsafe { Node::successor(node_ptr) };
+            if matched {
+                matching.push(node_ptr);
+            } else {
+                rest.push(node_ptr);
+            }
+        }
+
+        let left = SplayTreeBy::from_root(unsafe { link_sorted(&matching) }, matching.len(), cmp.clone());
+        let right = SplayTreeBy::from_root(unsafe { link_sorted(&rest) }, rest.len(), cmp);
+        (left, right)
+    }
+
+    /// Consumes the tree and returns a tree with every key replaced by the result
+    /// of `f`, which is called in the order of keys.
+    ///
+    /// If `f` preserves the order of keys, the shape of the tree is kept. Otherwise
+    /// the nodes are sorted and relinked into a balanced tree; if several keys are
+    /// mapped to equal ones, the entry with the greatest original key is kept.
+    ///
+    /// If `f` panics, all entries are dropped, the mapped ones included.
+    ///
+    /// This operation should compute in *O*(*n*) time if `f` preserves the order
+    /// and in *O*(*n log n*) time otherwise.
+    pub fn map_keys<K2, F>(self, mut f: F) -> SplayTreeBy<K2, V, C>
+        where C: Compare<K2>, F: FnMut(K) -> K2
+    {
+        let (root, length, cmp) = self.into_parts();
+        let root = map_nodes(root, |key, value| (f(key), value));
+        let tree = SplayTreeBy::from_root_unvalidated(root, length, cmp);
+
+        let cmp = &tree.cmp;
+        let mut keys = tree.iter().map(|(key, _)| key);
+        let mut prev_key = keys.next();
+        let ordered = keys.all(|key| {
+            let ordered = prev_key.is_some_and(|prev_key| cmp.compare(prev_key, key) == Ordering::Less);
+            prev_key = Some(key);
+            ordered
+        });
+        if ordered {
+            tree.debug_validate();
+            return tree;
+        }
+
+        // The nodes are sorted while the tree still owns them, so if comparing
+        // panics, they are dropped with the tree.
+        let mut nodes = Vec::with_capacity(tree.length);
+        let mut cur = tree.root.map(|root| unsafe { Node::leftmost(root) });
+        while let Some(node_ptr) = cur {
+            nodes.push(node_ptr);
+            cur = unsafe { Node::successor(node_ptr) };
+        }
+        let key = |node_ptr: &NonNull<Node<K2, V>>| unsafe { &(*node_ptr.as_ptr()).key };
+        nodes.sort_by(|a, b| tree.cmp.compare(key(a), key(b)));
+        let mut unique: Vec<NonNull<Node<K2, V>>> = Vec::with_capacity(nodes.len());
+        let mut duplicates = Vec::new();
+        for node_ptr in nodes {
+            if unique.last().is_some_and(|last| tree.cmp.compare(key(last), key(&node_ptr)) == Ordering::Equal) {
+                duplicates.extend(unique.pop());
+            }
+            unique.push(node_ptr);
+        }
+
+        let (_, _, cmp) = tree.into_parts();
+        for node_ptr in duplicates {
+            drop(unsafe { Box::from_raw(node_ptr.as_ptr()) });
+        }
+        SplayTreeBy::from_root(unsafe { link_sorted(&unique) }, unique.len(), cmp)
+    }
+
+    /// Removes the root entry and returns it, keeping the node like [`take`](Self::take).
+    pub(crate) fn take_root_entry(&mut self) -> Option<(K, V)> {
+        let node = self.remove_root()?;
+        Some(self.spares.take_entry(node))
+    }
+
+    /// Unlinks the root node by merging its subtrees and returns it.
+    pub(crate) fn remove_root(&mut self) -> Option<Box<Node<K, V>>> {
+        let node_ptr = self.root?;
+        let node = node_ptr.as_ptr();
+
+        unsafe {
+            let left = (*node).left.take();
+            let right = (*node).right.take();
+            for child in left.into_iter().chain(right) {
+                (*child.as_ptr()).parent = None;
+            }
+            #[cfg(feature = "stats")]
+            if let Some(left) = left {
+                self.stats.record_splay(Node::depth(Node::rightmost(left)));
+            }
+            self.root = Node::merge(left, right);
+            let node = self.release(node_ptr);
+            #[cfg(feature = "snapshots")]
+            self.emit_snapshot("remove", &node.key, 0);
+            Some(node)
+        }
+    }
+
+    /// Unlinks a leaf reached by descending from the root into the larger subtree
+    /// of every node, and returns it.
+    ///
+    /// Splaying keeps recently accessed nodes near the root and their subtrees
+    /// small, so the larger subtrees hold the deep, least recently accessed region,
+    /// and a leaf found through them is a cheap approximation of the least recently
+    /// used node. The rest of the tree isn't restructured.
+    pub(crate) fn remove_deep_leaf(&mut self) -> Option<Box<Node<K, V>>> {
+        let mut cur_ptr = self.root?;
+        loop {
+            let node = cur_ptr.as_ptr();
+            let (left, right) = unsafe { ((*node).left, (*node).right) };
+            let larger = if Node::size_of(right) > Node::size_of(left) { right } else { left };
+            match larger {
+                Some(next_ptr) => cur_ptr = next_ptr,
+                None => break,
+            }
+        }
+
+        Some(unsafe { self.unlink(cur_ptr) })
+    }
+
+    /// Unlinks a node in place, putting its in-order predecessor or its only child
+    /// in its position, and returns it. Nothing is splayed.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a node of this tree.
+    unsafe fn unlink(&mut self, node_ptr: NonNull<Node<K, V>>) -> Box<Node<K, V>> {
+        #[cfg(feature = "snapshots")]
+        let snapshot_depth = self.snapshots.as_ref().map(|_| Node::depth(node_ptr));
+        self.detach(node_ptr);
+        let node = self.release(node_ptr);
+        #[cfg(feature = "snapshots")]
+        if let Some(depth) = snapshot_depth {
+            self.emit_snapshot("remove", &node.key, depth);
+        }
+        node
+    }
+
+    /// Unlinks a node in place like [`unlink`](Self::unlink), leaving it detached
+    /// but not accounted for as removed.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a node of this tree.
+    unsafe fn detach(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        let node = node_ptr.as_ptr();
+        let parent = (*node).parent.take();
+        (*node).size = 1;
+        // The lowest node whose subtree has changed, sizes are fixed from it up.
+        let mut lowest = parent;
+        let replacement = match ((*node).left.take(), (*node).right.take()) {
+            (None, child) | (child, None) => child,
+            (Some(left), Some(right)) => {
+                let pred_ptr = Node::rightmost(left);
+                let pred = pred_ptr.as_ptr();
+                lowest = Some(pred_ptr);
+                if pred_ptr != left {
+                    let pred_parent = (*pred).parent.expect("predecessor must have a parent");
+                    (*pred_parent.as_ptr()).right = (*pred).left;
+                    if let Some(pred_left) = (*pred).left {
+                        (*pred_left.as_ptr()).parent = Some(pred_parent);
+                    }
+                    (*pred).left = Some(left);
+                    (*left.as_ptr()).parent = Some(pred_ptr);
+                    lowest = Some(pred_parent);
+                }
+                (*pred).right = Some(right);
+                (*right.as_ptr()).parent = Some(pred_ptr);
+                Some(pred_ptr)
+            },
+        };
+
+        if let Some(replacement) = replacement {
+            (*replacement.as_ptr()).parent = parent;
+        }
+        match parent {
+            Some(parent_ptr) if (*parent_ptr.as_ptr()).left == Some(node_ptr) => {
+                (*parent_ptr.as_ptr()).left = replacement;
+            },
+            Some(parent_ptr) => (*parent_ptr.as_ptr()).right = replacement,
+            None => self.root = replacement,
+        }
+        Node::update_sizes_up(lowest);
+    }
+
+    /// Accounts for a node which has been unlinked from the tree and returns it.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a node unlinked from this tree.
+    unsafe fn release(&mut self, node_ptr: NonNull<Node<K, V>>) -> Box<Node<K, V>> {
+        self.account_removal(node_ptr);
+        let node = Box::from_raw(node_ptr.as_ptr());
+        if let Some(observer) = &mut self.observer {
+            observer.on_remove(&node.key, &node.value);
+        }
+        node
+    }
+
+    /// Accounts for a node which has been unlinked from the tree, without
+    /// freeing it or notifying the observer.
+    ///
+    /// # Safety
+    ///
+    /// `node_ptr` must point to a node unlinked from this tree.
+    unsafe fn account_removal(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        self.handles.release(node_ptr);
+        #[cfg(feature = "insertion_order")]
+        self.order.unlink(node_ptr);
+        self.length -= 1;
+        #[cfg(feature = "stats")]
+        {
+            self.stats.frees += 1;
+        }
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(LEN_GAUGE).decrement(1.0);
+        self.debug_validate();
+    }
+
+    /// Replaces the value of the root node, which must exist, and returns the old one.
+    pub(crate) fn replace_root_value(&mut self, value: V) -> V {
+        let node = unsafe { &mut *self.root.expect("tree must not be empty").as_ptr() };
+        let old = mem::replace(&mut node.value, value);
+        if let Some(observer) = &mut self.observer {
+            observer.on_replace(&node.key, &old, &node.value);
+        }
+        old
+    }
+
+    /// Registers an observer of the changes of the tree, replacing the previous one.
+    #[inline]
+    pub fn set_observer<O>(&mut self, observer: O)
+        where O: Observer<K, V> + Send + 'static
+    {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Unregisters the observer of the tree and returns it.
+    #[inline]
+    pub fn take_observer(&mut self) -> Option<Box<dyn Observer<K, V> + Send>> {
+        self.observer.take()
+    }
+
+    /// Sets a sink receiving a snapshot of the structure of the tree after every
+    /// splay, insertion and removal, replacing the previous one.
+    ///
+    /// Every snapshot is a single line of JSON with the name of the operation
+    /// (`"splay"`, `"insert"` or `"remove"`), the key of the affected entry, its
+    /// depth before the operation, the key of the resulting root, the number of
+    /// entries and the resulting shape, with every node written as
+    /// `[left,"key",right]` and missing children as `null`:
+    ///
+    /// ```json
+    /// {"op":"splay","key":"1","depth":1,"root":"1","len":2,"tree":[null,"1",[null,"2",null]]}
+    /// ```
+    ///
+    /// An insertion is reported after the splay of the new node. Operations on many
+    /// entries at once, such as [`clear`](Self::clear), [`remove_range`](Self::remove_range)
+    /// or merging trees, only report the splays they make, and splays made while
+    /// isolating a range show the part of the tree being restructured. Writing
+    /// a snapshot takes *O*(*n*) time, so the sink is meant for debugging and
+    /// visualizing small trees.
+    ///
+    /// Only available with the `snapshots` feature.
+    #[cfg(feature = "snapshots")]
+    #[inline]
+    pub fn set_snapshot_sink<F>(&mut self, sink: F)
+        where K: Display, F: FnMut(&str) + Send + 'static
+    {
+        self.snapshots = Some(SnapshotSink::new(sink));
+    }
+
+    /// Removes the snapshot sink of the tree. Returns `false` if it had none.
+    ///
+    /// Only available with the `snapshots` feature.
+    #[cfg(feature = "snapshots")]
+    #[inline]
+    pub fn clear_snapshot_sink(&mut self) -> bool {
+        self.snapshots.take().is_some()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    #[inline]
+    pub fn contains_key<Q>(&mut self, key: &Q) -> bool
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.get(key).is_some()
+    }
+
+    /// Gets the given key’s corresponding entry in the tree for in-place manipulation.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C> {
+        match self.find_ptr(&key) {
+            NotFound => {
+                Vacant(VacantEntry::new(self, None, key))
+            },
+            GoDown(parent_ptr, ordering) => {
+                Vacant(VacantEntry::new(self, Some((parent_ptr, ordering)), key))
+            },
+            Found(_) => {
+                Occupied(OccupiedEntry::new(self))
+            },
+        }
+    }
+
+    /// Gets the entry by a borrowed key for in-place manipulation. Unlike
+    /// [`SplayTreeBy::entry`], an owned key is only made if a vacant entry is filled.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn entry_ref<'a, 'q, Q>(&'a mut self, key: &'q Q) -> EntryRef<'a, 'q, K, Q, V, C>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        match self.find_ptr(key) {
+            NotFound => {
+                EntryRef::Vacant(VacantEntryRef::new(self, None, key))
+            },
+            GoDown(parent_ptr, ordering) => {
+                EntryRef::Vacant(VacantEntryRef::new(self, Some((parent_ptr, ordering)), key))
+            },
+            Found(_) => {
+                EntryRef::Occupied(OccupiedEntry::new(self))
+            },
+        }
+    }
+
+    /// Gets the entry found by a search closure, for lookups that can't be
+    /// expressed with `Borrow`, like searching by a part of a composite key.
+    ///
+    /// `f` is called with the keys on the search path and must return the
+    /// ordering of the sought key relative to them, consistently with the
+    /// comparator of the tree. If it never returns `Equal`, the vacant entry
+    /// is placed where the sought key would be.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn entry_by<F>(&mut self, f: F) -> RawEntry<'_, K, V, C>
+        where F: FnMut(&K) -> Ordering
+    {
+        let (result, comparisons) = search(self.root, f);
+        match self.finish_find(result, comparisons) {
+            NotFound => {
+                RawEntry::Vacant(RawVacantEntry::new(self, None))
+            },
+            GoDown(parent_ptr, ordering) => {
+                RawEntry::Vacant(RawVacantEntry::new(self, Some((parent_ptr, ordering))))
+            },
+            Found(_) => {
+                RawEntry::Occupied(OccupiedEntry::new(self))
+            },
+        }
+    }
+
+    /// Returns the handle of the node by a key, or `None` if the tree doesn't
+    /// contain that key. The handle stays valid until the node is removed.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn id_of(&mut self, key: &K) -> Option<NodeId> {
+        match self.find_ptr(key) {
+            Found(node_ptr) => Some(self.handles.id(node_ptr)),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the node of a handle, or `None` if the handle is stale.
+    /// The node isn't splayed.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn get_by_id(&self, id: NodeId) -> Option<&Node<K, V>> {
+        self.handles.get(id).map(|node_ptr| unsafe { node_ptr.as_ref() })
+    }
+
+    /// Returns mutable access to the node of a handle, or `None` if the handle
+    /// is stale. The node isn't splayed.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn get_by_id_mut(&mut self, id: NodeId) -> Option<NodeMut<'_, K, V>> {
+        self.handles.get(id).map(|node_ptr| unsafe { NodeMut::new(node_ptr) })
+    }
+
+    /// Splays the node of a handle to the root and returns mutable access to it,
+    /// or `None` if the handle is stale. This lets callers which track the hot
+    /// entries themselves, like after analysing a batch of accesses, restructure
+    /// the tree without a key search.
+    ///
+    /// This operation should compute in amortized *O*(*log n*) time.
+    pub fn splay_node(&mut self, id: NodeId) -> Option<NodeMut<'_, K, V>> {
+        let node_ptr = self.handles.get(id)?;
+        if self.root != Some(node_ptr) {
+            unsafe { self.splay(node_ptr) };
+        }
+        self.root_mut()
+    }
+
+    /// Removes the node of a handle from the tree, or returns `None` if the handle
+    /// is stale. The node is unlinked in place, without searching for its key
+    /// and without splaying anything.
+    ///
+    /// This operation should compute in *O*(*d*) time, where *d* is the height
+    /// of the node's subtree.
+    pub fn remove_node(&mut self, id: NodeId) -> Option<Box<Node<K, V>>> {
+        let node_ptr = self.handles.get(id)?;
+        Some(unsafe { self.unlink(node_ptr) })
+    }
+
+    /// Searches for a key, splaying the node if it's found.
+    #[inline]
+    fn find_ptr<Q>(&mut self, key: &Q) -> FindResult<K, V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let cmp = &self.cmp;
+        let (result, comparisons) = search(self.root, |node_key| {
+            cmp.compare(key, node_key.borrow())
+        });
+        self.finish_find(result, comparisons)
+    }
+
+    /// Finds the nodes of `keys` in a single descent which splits the sorted keys
+    /// between the subtrees. Returns the nodes in the order of `keys` together with
+    /// the order of `keys` sorted by key. Nothing is modified while keys are compared.
+    fn locate_batch<Q>(&self, keys: &[&Q]) -> (Vec<NodePtr<K, V>>, Vec<usize>)
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let cmp = &self.cmp;
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        if !keys.windows(2).all(|w| cmp.compare(w[0], w[1]) != Ordering::Greater) {
+            order.sort_by(|&a, &b| cmp.compare(keys[a], keys[b]));
+        }
+
+        let mut found: Vec<NodePtr<K, V>> = vec![None; keys.len()];
+        let mut stack: Vec<_> = self.root.map(|root| (root, 0..order.len())).into_iter().collect();
+        while let Some((node_ptr, range)) = stack.pop() {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            let pending = &order[range.clone()];
+            let less = pending.partition_point(|&i| {
+                cmp.compare(keys[i], node.key.borrow()) == Ordering::Less
+            });
+            let not_greater = pending.partition_point(|&i| {
+                cmp.compare(keys[i], node.key.borrow()) != Ordering::Greater
+            });
+            for &i in &pending[less..not_greater] {
+                found[i] = Some(node_ptr);
+            }
+            if let (Some(left), true) = (node.left, less > 0) {
+                stack.push((left, range.start..range.start + less));
+            }
+            if let (Some(right), true) = (node.right, not_greater < pending.len()) {
+                stack.push((right, range.start + not_greater..range.end));
+            }
+        }
+
+        (found, order)
+    }
+
+    /// Searches for the neighbour of a key on the side given by `side`, which is
+    /// `Greater` for the successor and `Less` for the predecessor, and splays it.
+    /// If `inclusive` is `true`, the node with the key itself is taken if present.
+    fn splay_neighbour<Q>(&mut self, key: &Q, side: Ordering, inclusive: bool) -> NodePtr<K, V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let cmp = &self.cmp;
+        let (result, comparisons) = search(self.root, |node_key| {
+            cmp.compare(key, node_key.borrow())
+        });
+        #[cfg(feature = "stats")]
+        {
+            self.stats.comparisons += comparisons;
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = comparisons;
+
+        let step = |node_ptr| unsafe {
+            match side {
+                Ordering::Less => Node::predecessor(node_ptr),
+                _ => Node::successor(node_ptr),
+            }
+        };
+        let neighbour = match result {
+            Found(node_ptr) if inclusive => Some(node_ptr),
+            Found(node_ptr) => step(node_ptr),
+            GoDown(parent_ptr, ordering) if ordering == side => step(parent_ptr),
+            GoDown(parent_ptr, _) => Some(parent_ptr),
+            NotFound => None,
+        };
+        if let Some(node_ptr) = neighbour {
+            unsafe { self.splay(node_ptr) };
+        }
+        neighbour
+    }
+
+    /// Finds the first node for which `f` returns `true`, given that it returns
+    /// `false` for the nodes before it and `true` for the nodes after it, and splays it.
+    fn splay_partition_point<F>(&mut self, f: F) -> NodePtr<K, V>
+        where F: FnMut(&K) -> bool
+    {
+        let found = partition_point(self.root?, f);
+        if let Some(node_ptr) = found {
+            unsafe { self.splay(node_ptr) };
+        }
+        found
+    }
+
+    /// Records a finished search and splays the node if it was found, or the last
+    /// node on the search path if it wasn't.
+    ///
+    /// A miss must splay too, or repeated misses in the same deep region would
+    /// cost their full depth every time. Splaying moves the empty slot of the key,
+    /// so a miss is returned as the slot next to the key's neighbour at the root.
+    #[inline]
+    fn finish_find(&mut self, result: FindResult<K, V>, comparisons: u64) -> FindResult<K, V> {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.comparisons += comparisons;
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = comparisons;
+        #[cfg(feature = "stats")]
+        if let Found(node_ptr) = result {
+            unsafe { (*node_ptr.as_ptr()).hits += 1 };
+        }
+
+        match result {
+            // A hit at the root is already in place, so runs of lookups of the
+            // same key cost a single comparison each.
+            Found(node_ptr) if self.root == Some(node_ptr) => {},
+            Found(node_ptr) => unsafe { self.splay(node_ptr) },
+            GoDown(parent_ptr, ordering) => unsafe {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(depth = Node::depth(parent_ptr) + 1, "key not found");
+                if self.root == Some(parent_ptr) {
+                    return result;
+                }
+                self.splay(parent_ptr);
+                let parent = parent_ptr.as_ptr();
+                return match (ordering, (*parent).left, (*parent).right) {
+                    (Ordering::Less, Some(left), _) => GoDown(Node::rightmost(left), Ordering::Greater),
+                    (Ordering::Greater, _, Some(right)) => GoDown(Node::leftmost(right), Ordering::Less),
+                    _ => result,
+                };
+            },
+            NotFound => {},
+        }
+        result
+    }
+
+    /// Returns the counters of the work done by the tree since it was created
+    /// or the counters were reset.
+    #[cfg(feature = "stats")]
+    #[inline]
+    pub fn stats(&self) -> TreeStats {
+        self.stats
+    }
+
+    /// Resets all counters returned by [`SplayTree::stats`] to zero.
+    #[cfg(feature = "stats")]
+    #[inline]
+    pub fn reset_stats(&mut self) {
+        self.stats = TreeStats::default();
+    }
+
+    /// Encodes the shape of the tree in about two bits per node and returns it
+    /// with an iterator over the entries in sorted order, for compact checkpoints
+    /// which keep the structure of the tree. The tree is rebuilt by
+    /// [`decode_succinct`](Self::decode_succinct).
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    #[inline]
+    pub fn encode_succinct(&self) -> (SuccinctShape, Iter<'_, K, V>) {
+        (succinct::encode(self.root, self.length), self.iter())
+    }
+
+    /// Returns an iterator over the entries of the tree, sorted by key.
+    /// Iterating doesn't splay the tree.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(self.root, self.length)
+    }
+
+    /// Returns an iterator over the entries of the tree in the order of their
+    /// insertion. Replacing the value of an entry doesn't move it, and entries
+    /// added by bulk constructors count as inserted in the order of keys.
+    /// Iterating doesn't splay the tree.
+    #[cfg(feature = "insertion_order")]
+    #[inline]
+    pub fn iter_insertion_order(&self) -> InsertionOrder<'_, K, V> {
+        InsertionOrder::new(&self.order, self.length)
+    }
+
+    /// Returns an iterator over the entries of the tree in breadth-first order,
+    /// together with the depth of their nodes. Iterating doesn't splay the tree.
+    #[inline]
+    pub fn level_order(&self) -> LevelOrder<'_, K, V> {
+        LevelOrder::new(self.root, self.length)
+    }
+
+    /// Returns an iterator over the entries of the tree, roughly from the most
+    /// recently accessed one: the root comes first, and shallower nodes come
+    /// before deeper ones. Splaying keeps recently accessed entries near the root,
+    /// so a prefix of the iterator approximates the hot set of the workload.
+    /// Iterating doesn't splay the tree.
+    #[inline]
+    pub fn access_order(&self) -> AccessOrder<'_, K, V> {
+        AccessOrder::new(self.level_order())
+    }
+
+    /// Returns an iterator over the pairs of consecutive entries of the tree,
+    /// sorted by key, like `windows(2)` on a slice. Iterating doesn't splay the tree.
+    #[inline]
+    pub fn adjacent_pairs(&self) -> AdjacentPairs<'_, K, V> {
+        AdjacentPairs::new(self.iter())
+    }
+
+    /// Returns a lending iterator over the entries of the tree, sorted by key,
+    /// which gives out mutable references to values one at a time.
+    /// Iterating doesn't splay the tree.
+    #[inline]
+    pub fn lending_iter_mut(&mut self) -> LendingIterMut<'_, K, V> {
+        LendingIterMut::new(self.root, self.length)
+    }
+
+    /// Returns an iterator over the entries of the tree in pre-order, where every
+    /// node comes before its left and right subtrees. Iterating doesn't splay the tree.
+    #[inline]
+    pub fn pre_order(&self) -> PreOrder<'_, K, V> {
+        PreOrder::new(self.root, self.length)
+    }
+
+    /// Returns an iterator over the entries of the tree in post-order, where every
+    /// node comes after its left and right subtrees. Iterating doesn't splay the tree.
+    #[inline]
+    pub fn post_order(&self) -> PostOrder<'_, K, V> {
+        PostOrder::new(self.root, self.length)
+    }
+
+    /// Calls `f` with every entry of the tree, sorted by key. The tree is walked
+    /// by parent links, so nothing is allocated and it isn't splayed.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    #[inline]
+    pub fn for_each<F: FnMut(&K, &V)>(&self, mut f: F) {
+        self.fold((), |(), key, value| f(key, value));
+    }
+
+    /// Folds every entry of the tree, sorted by key, into an accumulator. The tree
+    /// is walked by parent links, so nothing is allocated and it isn't splayed.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn fold<B, F: FnMut(B, &K, &V) -> B>(&self, init: B, mut f: F) -> B {
+        let mut acc = init;
+        let mut cur_ptr = self.root.map(|r| unsafe { Node::leftmost(r) });
+        while let Some(node_ptr) = cur_ptr {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            acc = f(acc, &node.key, &node.value);
+            cur_ptr = unsafe { Node::successor(node_ptr) };
+        }
+        acc
+    }
+
+    /// Calls `f` with the entries of the tree, sorted by key, until it breaks,
+    /// and returns the break value. Like [`fold`](Self::fold), nothing is allocated
+    /// and the tree isn't splayed.
+    ///
+    /// This operation should compute in *O*(*k*) time for the *k* visited entries.
+    pub fn visit<B, F: FnMut(&K, &V) -> ControlFlow<B>>(&self, mut f: F) -> ControlFlow<B> {
+        let mut cur_ptr = self.root.map(|r| unsafe { Node::leftmost(r) });
+        while let Some(node_ptr) = cur_ptr {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            f(&node.key, &node.value)?;
+            cur_ptr = unsafe { Node::successor(node_ptr) };
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Returns the node with the least key for which `pred` returns `true`,
+    /// scanning the entries in the order of keys. Like [`visit`](Self::visit),
+    /// nothing is allocated and the tree isn't splayed, so it suits occasional
+    /// lookups by value.
+    ///
+    /// This operation should compute in *O*(*k*) time for the *k* visited entries.
+    pub fn find_by<F: FnMut(&K, &V) -> bool>(&self, mut pred: F) -> Option<&Node<K, V>> {
+        let mut cur_ptr = self.root.map(|r| unsafe { Node::leftmost(r) });
+        while let Some(node_ptr) = cur_ptr {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            if pred(&node.key, &node.value) {
+                return Some(node);
+            }
+            cur_ptr = unsafe { Node::successor(node_ptr) };
+        }
+        None
+    }
+
+    /// Returns the first non-`None` result of `f`, scanning the entries in the
+    /// order of keys. Nothing is allocated and the tree isn't splayed.
+    ///
+    /// This operation should compute in *O*(*k*) time for the *k* visited entries.
+    pub fn find_map_value<B, F: FnMut(&K, &V) -> Option<B>>(&self, mut f: F) -> Option<B> {
+        match self.visit(|key, value| f(key, value).map_or(ControlFlow::Continue(()), ControlFlow::Break)) {
+            ControlFlow::Break(found) => Some(found),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Collects references to the entries of the tree, sorted by key, into a vector.
+    /// Collecting doesn't splay the tree.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn to_vec(&self) -> Vec<(&K, &V)> {
+        let mut entries = Vec::with_capacity(self.length);
+        entries.extend(self.iter());
+        entries
+    }
+
+    /// Consumes the tree, returning its entries sorted by key.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut entries = Vec::with_capacity(self.length);
+        entries.extend(self);
+        entries
+    }
+
+    /// Gets an iterator over the changes turning this tree into `other`, sorted by key:
+    /// keys only in `other` are added, keys only in this tree are removed, and keys
+    /// with unequal values are changed. Neither tree is splayed.
+    ///
+    /// Keys are compared with the comparator of this tree.
+    ///
+    /// Iterating through all changes should compute in *O*(*n* + *m*) time.
+    #[inline]
+    pub fn diff<'a>(&'a self, other: &'a Self) -> Diff<'a, K, V, C>
+        where V: PartialEq
+    {
+        Diff::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Re-checks the invariants of the tree if the `validate_debug` feature is
+    /// enabled in a debug build, and does nothing otherwise.
+    #[inline]
+    pub(crate) fn debug_validate(&self) {
+        #[cfg(all(feature = "validate_debug", debug_assertions))]
+        self.validate();
+    }
+
+    /// Panics if a parent link is broken, keys are out of order, or the length
+    /// or a subtree size doesn't match the number of nodes.
+    #[cfg(all(feature = "validate_debug", debug_assertions))]
+    fn validate(&self) {
+        if let Some(root_ptr) = self.root {
+            assert!(unsafe { (*root_ptr.as_ptr()).parent.is_none() }, "root has a parent");
+        }
+
+        let mut stack = Vec::new();
+        let mut cur_ptr = self.root;
+        let mut prev_key: Option<&K> = None;
+        let mut count = 0;
+
+        loop {
+            while let Some(node_ptr) = cur_ptr {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                for child_ptr in node.left.into_iter().chain(node.right) {
+                    let parent = unsafe { (*child_ptr.as_ptr()).parent };
+                    assert!(parent == Some(node_ptr), "broken parent link");
+                }
+                let size = 1 + Node::size_of(node.left) + Node::size_of(node.right);
+                assert_eq!(node.size, size, "subtree size doesn't match the number of nodes");
+                stack.push(node);
+                cur_ptr = node.left;
+            }
+
+            let node = match stack.pop() {
+                Some(node) => node,
+                None => break,
+            };
+            if let Some(prev_key) = prev_key {
+                let ordering = self.cmp.compare(prev_key, &node.key);
+                assert!(ordering == Ordering::Less, "keys are out of order");
+            }
+            prev_key = Some(&node.key);
+            count += 1;
+            cur_ptr = node.right;
+        }
+
+        assert_eq!(count, self.length, "length doesn't match the number of nodes");
+
+        #[cfg(feature = "insertion_order")]
+        {
+            let mut prev_ptr = None;
+            let mut cur_ptr = self.order.oldest();
+            let mut count = 0;
+            while let Some(node_ptr) = cur_ptr {
+                let node = unsafe { &*node_ptr.as_ptr() };
+                assert!(node.older == prev_ptr, "broken insertion order link");
+                if let Some(prev_ptr) = prev_ptr {
+                    let prev_seq = unsafe { (*prev_ptr.as_ptr()).seq };
+                    assert!(prev_seq < node.seq, "insertions are out of order");
+                }
+                prev_ptr = Some(node_ptr);
+                cur_ptr = node.newer;
+                count += 1;
+            }
+            assert!(self.order.newest() == prev_ptr, "broken insertion order link");
+            assert_eq!(count, self.length, "length doesn't match the number of insertions");
+        }
+    }
+
+    /// Returns the number of levels of the tree, i.e. the number of nodes on
+    /// the longest path from the root to a leaf. Doesn't splay the tree.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        self.for_each_depth(|depth| height = height.max(depth + 1));
+        height
+    }
+
+    /// Returns the number of edges between the root and the node by a key,
+    /// or `None` if the tree doesn't contain that key. Doesn't splay the tree.
+    ///
+    /// This operation should compute in *O*(*d*) time, where *d* is the depth.
+    pub fn depth_of(&self, key: &K) -> Option<usize> {
+        let mut cur_ptr = self.root;
+        let mut depth = 0;
+
+        while let Some(node_ptr) = cur_ptr {
+            let node = unsafe { &*node_ptr.as_ptr() };
+            cur_ptr = match self.cmp.compare(key, &node.key) {
+                Ordering::Less => node.left,
+                Ordering::Equal => return Some(depth),
+                Ordering::Greater => node.right,
+            };
+            depth += 1;
+        }
+
+        None
+    }
+
+    /// Returns the sum of depths of all nodes. Doesn't splay the tree.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn internal_path_length(&self) -> usize {
+        let mut sum = 0;
+        self.for_each_depth(|depth| sum += depth);
+        sum
+    }
+
+    /// Returns the average depth of a node, or `None` if the tree is empty.
+    /// It's about *log₂ n* for a balanced tree and grows up to *n* / 2 as the tree
+    /// degenerates into a list. Doesn't splay the tree.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn average_depth(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.internal_path_length() as f64 / self.length as f64)
+    }
+
+    /// Returns the number of nodes at every depth of the tree, with percentiles
+    /// of the depth, for monitoring how short the paths to the entries are.
+    /// Doesn't splay the tree.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    pub fn depth_histogram(&self) -> DepthHistogram {
+        let mut histogram = DepthHistogram::default();
+        self.for_each_depth(|depth| histogram.record(depth));
+        histogram
+    }
+
+    /// Writes a JSON document listing the key, depth and subtree size of every
+    /// node to `out`, for tools rendering how the tree adapts to a workload.
+    /// With the `stats` feature, the number of searches which have found every
+    /// node is written too. Doesn't splay the tree.
+    ///
+    /// The document is an object with the number of entries in `len` and the
+    /// nodes in pre-order in `nodes`. Every node is an object with the key in
+    /// `key`, written as a string, `depth`, `size`, and `hits` with the `stats`
+    /// feature, as well as the index of the parent node in `parent`, which is
+    /// `null` at the root.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    #[inline]
+    pub fn write_heat_map<W: fmt::Write>(&self, out: &mut W) -> fmt::Result
+        where K: Display
+    {
+        heat_map::write(self.root, self.length, out)
+    }
+
+    /// Calls `f` with the depth of every node, walking the tree with an explicit stack.
+    fn for_each_depth<F: FnMut(usize)>(&self, mut f: F) {
+        let mut stack = Vec::new();
+        stack.extend(self.root.map(|root_ptr| (root_ptr, 0)));
+
+        while let Some((node_ptr, depth)) = stack.pop() {
+            f(depth);
+            let node = unsafe { &*node_ptr.as_ptr() };
+            for child_ptr in node.left.into_iter().chain(node.right) {
+                stack.push((child_ptr, depth + 1));
+            }
+        }
+    }
+
+    /// Returns an approximate number of bytes of heap memory held by the nodes
+    /// of the tree, including reserved ones. Heap memory owned by keys and values themselves isn't counted.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.length * mem::size_of::<Node<K, V>>()
+            + self.handles.memory_usage()
+            + self.spares.memory_usage()
+    }
+
+    /// Returns the length of the `SplayTree`.
+    ///
+    /// This operation should compute in *O*(1) time.
+    #[inline]
+    pub fn len(&self) -> usize {
         self.length
     }
 }
 
-impl<K: Ord, V> Drop for SplayTree<K, V> {
+impl<K, V, C: Compare<K> + Default> Default for SplayTreeBy<K, V, C> {
+    #[inline]
+    fn default() -> Self {
+        SplayTreeBy::new()
+    }
+}
+
+impl<'a, K, V, C: Compare<K>> IntoIterator for &'a SplayTreeBy<K, V, C> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<K, V, C> IntoIterator for SplayTreeBy<K, V, C> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Creates a consuming iterator over the entries of the tree, sorted by key.
+    #[inline]
+    fn into_iter(mut self) -> IntoIter<K, V> {
+        let length = self.length;
+        IntoIter::new(self.take_root(), length)
+    }
+}
+
+impl<K: Debug, V: Debug, C> Debug for SplayTreeBy<K, V, C> {
+    /// Prints the entries of the tree as a map, sorted by key.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(Iter::new(self.root, self.length)).finish()
+    }
+}
+
+impl<K: Ord, V> From<BTreeMap<K, V>> for SplayTree<K, V> {
+    /// Builds a balanced tree from the already sorted entries of the map.
+    ///
+    /// This operation should compute in *O*(*n*) time.
+    #[inline]
+    fn from(map: BTreeMap<K, V>) -> Self {
+        SplayTree::from_sorted_iter(map.into_iter(), Natural)
+    }
+}
+
+impl<K: Ord, V, S> From<HashMap<K, V, S>> for SplayTree<K, V> {
+    /// Sorts the entries of the map and builds a balanced tree from them.
+    ///
+    /// This operation should compute in *O*(*n log n*) time.
+    fn from(map: HashMap<K, V, S>) -> Self {
+        let mut entries: Vec<(K, V)> = map.into_iter().collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        SplayTree::from_sorted_iter(entries.into_iter(), Natural)
+    }
+}
+
+impl<K: Ord, V, C> From<SplayTreeBy<K, V, C>> for BTreeMap<K, V> {
+    /// Collects the entries of the tree into a `BTreeMap`.
+    ///
+    /// This operation should compute in *O*(*n*) time if the comparator of the tree
+    /// agrees with `Ord`.
+    #[inline]
+    fn from(tree: SplayTreeBy<K, V, C>) -> Self {
+        tree.into_iter().collect()
+    }
+}
+
+impl<K, V, C> Drop for SplayTreeBy<K, V, C> {
     #[inline]
     fn drop(&mut self) {
-        match self.root_mut() {
-            None => {},
-            Some(r) => r.free(),
+        let root = self.take_root();
+        unsafe { self.spares.release(root) };
+    }
+}
+
+#[cfg(feature = "deepsize")]
+impl<K, V, C> deepsize::DeepSizeOf for SplayTreeBy<K, V, C>
+    where K: deepsize::DeepSizeOf, V: deepsize::DeepSizeOf, C: Compare<K>
+{
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.iter().fold(self.memory_usage(), |size, (key, value)| {
+            size + key.deep_size_of_children(context) + value.deep_size_of_children(context)
+        })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a, K, V, C> arbitrary::Arbitrary<'a> for SplayTreeBy<K, V, C>
+    where K: arbitrary::Arbitrary<'a>, V: arbitrary::Arbitrary<'a>, C: Compare<K> + Default
+{
+    /// Generates the entries of the tree and then a sequence of accesses which
+    /// splays its nodes, so the shape of the tree is generated as well.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut tree = SplayTreeBy::new();
+        for entry in u.arbitrary_iter::<(K, V)>()? {
+            let (key, value) = entry?;
+            tree.insert(key, value);
+        }
+
+        let mut nodes = Vec::with_capacity(tree.length);
+        for_each_node(tree.root, |node_ptr| nodes.push(node_ptr));
+        if !nodes.is_empty() {
+            for _ in 0..u.arbitrary_len::<u32>()? {
+                let node_ptr = nodes[u.choose_index(nodes.len())?];
+                unsafe { tree.splay(node_ptr) };
+            }
+        }
+        Ok(tree)
+    }
+}
+
+/// Removes a node whose value has been moved out if it's dropped, so that
+/// a panic while computing the new value doesn't leave the node without one.
+struct MovedValue<'a, K, V, C: Compare<K>> {
+    tree: &'a mut SplayTreeBy<K, V, C>,
+    node_ptr: NonNull<Node<K, V>>,
+}
+
+impl<'a, K, V, C: Compare<K>> Drop for MovedValue<'a, K, V, C> {
+    fn drop(&mut self) {
+        unsafe {
+            self.tree.detach(self.node_ptr);
+            self.tree.account_removal(self.node_ptr);
+            let node = self.node_ptr.as_ptr();
+            ptr::drop_in_place(&mut (*node).key);
+            drop(Box::from_raw(node.cast::<ManuallyDrop<Node<K, V>>>()));
+        }
+    }
+}
+
+/// Descends from `root` in the direction given by `f`, which compares the sought key
+/// with the key of a node, and returns where the search stopped together with the
+/// number of comparisons.
+///
+/// Nothing is modified while keys are compared, so the tree stays consistent
+/// if `f` panics.
+fn search<K, V, F>(root: NodePtr<K, V>, mut f: F) -> (FindResult<K, V>, u64)
+    where F: FnMut(&K) -> Ordering
+{
+    let mut cur_ptr = match root {
+        Some(root) => root,
+        None => return (NotFound, 0),
+    };
+    let mut comparisons = 0;
+
+    loop {
+        let node = cur_ptr.as_ptr();
+        let ordering = f(unsafe { &(*node).key });
+        comparisons += 1;
+        let next_ptr = match ordering {
+            Ordering::Less => unsafe { (*node).left },
+            Ordering::Equal => return (Found(cur_ptr), comparisons),
+            Ordering::Greater => unsafe { (*node).right },
+        };
+
+        cur_ptr = match next_ptr {
+            Some(next_ptr) => next_ptr,
+            None => return (GoDown(cur_ptr, ordering), comparisons),
+        };
+    }
+}
+
+/// Returns the first node of the subtree at `root` for which `f` returns `true`, given
+/// that it returns `false` for the nodes before it and `true` for the nodes after it.
+fn partition_point<K, V, F>(root: NonNull<Node<K, V>>, mut f: F) -> NodePtr<K, V>
+    where F: FnMut(&K) -> bool
+{
+    let mut found = None;
+    let mut cur_ptr = Some(root);
+    while let Some(node_ptr) = cur_ptr {
+        let node = unsafe { &*node_ptr.as_ptr() };
+        if f(&node.key) {
+            found = Some(node_ptr);
+            cur_ptr = node.left;
+        } else {
+            cur_ptr = node.right;
+        }
+    }
+    found
+}
+
+/// Returns the number of nodes of the subtree at `root` for which `f` returns `true`,
+/// given that it returns `true` for the nodes before some point and `false` after it,
+/// together with the last node visited while descending to that point.
+fn rank<K, V, F>(root: NodePtr<K, V>, mut f: F) -> (usize, NodePtr<K, V>)
+    where F: FnMut(&K) -> bool
+{
+    let mut count = 0;
+    let mut last = None;
+    let mut cur_ptr = root;
+    while let Some(node_ptr) = cur_ptr {
+        let node = unsafe { &*node_ptr.as_ptr() };
+        last = Some(node_ptr);
+        if f(&node.key) {
+            count += Node::size_of(node.left) + 1;
+            cur_ptr = node.right;
+        } else {
+            cur_ptr = node.left;
+        }
+    }
+    (count, last)
+}
+
+/// Returns the node of the subtree at `root` which has `n` nodes before it,
+/// or `None` if the subtree has no more than `n` nodes.
+fn select<K, V>(root: NodePtr<K, V>, mut n: usize) -> NodePtr<K, V> {
+    let mut cur_ptr = root;
+    while let Some(node_ptr) = cur_ptr {
+        let node = unsafe { &*node_ptr.as_ptr() };
+        let left = Node::size_of(node.left);
+        cur_ptr = match n.cmp(&left) {
+            Ordering::Less => node.left,
+            Ordering::Equal => return Some(node_ptr),
+            Ordering::Greater => {
+                n -= left + 1;
+                node.right
+            },
+        };
+    }
+    None
+}
+
+/// Returns pointers to the nodes of the subtree at `root` in the order of keys.
+fn sorted_nodes<K, V>(root: NodePtr<K, V>) -> Vec<NonNull<Node<K, V>>> {
+    let mut nodes = Vec::with_capacity(Node::size_of(root));
+    let mut cur = root.map(|root| unsafe { Node::leftmost(root) });
+    while let Some(node_ptr) = cur {
+        nodes.push(node_ptr);
+        cur = unsafe { Node::successor(node_ptr) };
+    }
+    nodes
+}
+
+/// Calls `f` with every node of the subtree at `root`, walking it with an explicit stack.
+fn for_each_node<K, V, F>(root: NodePtr<K, V>, mut f: F)
+    where F: FnMut(NonNull<Node<K, V>>)
+{
+    let mut stack = Vec::new();
+    stack.extend(root);
+
+    while let Some(node_ptr) = stack.pop() {
+        f(node_ptr);
+        let node = unsafe { &*node_ptr.as_ptr() };
+        stack.extend(node.left.into_iter().chain(node.right));
+    }
+}
+
+/// Replaces every entry of the subtree at `root` with the result of `f`, which is
+/// called in the order of keys, keeping the shape of the subtree.
+///
+/// The allocation of every node is reused if the new nodes have the same layout.
+/// If `f` panics, all nodes are freed, whether they have been mapped or not.
+fn map_nodes<K, V, K2, V2, F>(root: NodePtr<K, V>, mut f: F) -> NodePtr<K2, V2>
+    where F: FnMut(K, V) -> (K2, V2)
+{
+    let nodes = sorted_nodes(root);
+    // The subtree at the node at position `i` spans the positions from
+    // `i - size(left)` to `i + size(right)`, so the positions of its children
+    // follow from the sizes of their left subtrees.
+    let children: Vec<(Option<usize>, Option<usize>)> = nodes.iter()
+        .enumerate()
+        .map(|(i, node_ptr)| unsafe {
+            let node = &*node_ptr.as_ptr();
+            let left = node.left.map(|l| i - (*l.as_ptr()).size + Node::size_of((*l.as_ptr()).left));
+            let right = node.right.map(|r| i + 1 + Node::size_of((*r.as_ptr()).left));
+            (left, right)
+        })
+        .collect();
+    let root_position = root.map(|root| Node::size_of(unsafe { (*root.as_ptr()).left }));
+
+    let reuse = Layout::new::<Node<K, V>>() == Layout::new::<Node<K2, V2>>();
+    let mut guard = MapGuard { nodes: &nodes, next: 0, mapped: Vec::with_capacity(nodes.len()) };
+    for &node_ptr in &nodes {
+        guard.next += 1;
+        let node = unsafe { ptr::read(node_ptr.as_ptr()) };
+        // The entry is moved out, so the block is freed without it if `f` panics.
+        let block = unsafe { Box::from_raw(node_ptr.as_ptr().cast::<MaybeUninit<Node<K, V>>>()) };
+        #[cfg(feature = "insertion_order")]
+        let seq = node.seq;
+        #[cfg(feature = "stats")]
+        let hits = node.hits;
+        let Node { key, value, size, .. } = node;
+        let (key, value) = f(key, value);
+
+        let mut new_node = Node::new(key, value);
+        new_node.size = size;
+        #[cfg(feature = "insertion_order")]
+        {
+            new_node.seq = seq;
+        }
+        #[cfg(feature = "stats")]
+        {
+            new_node.hits = hits;
+        }
+        let new_ptr = if reuse {
+            let new_ptr = Box::into_raw(block).cast::<Node<K2, V2>>();
+            unsafe {
+                ptr::write(new_ptr, new_node);
+                NonNull::new_unchecked(new_ptr)
+            }
+        } else {
+            drop(block);
+            NonNull::from(Box::leak(Box::new(new_node)))
+        };
+        guard.mapped.push(new_ptr);
+    }
+
+    let mapped = mem::take(&mut guard.mapped);
+    drop(guard);
+    for (&node_ptr, &(left, right)) in mapped.iter().zip(&children) {
+        let node = node_ptr.as_ptr();
+        unsafe {
+            (*node).left = left.map(|l| mapped[l]);
+            (*node).right = right.map(|r| mapped[r]);
+            for child in (*node).left.into_iter().chain((*node).right) {
+                (*child.as_ptr()).parent = Some(node_ptr);
+            }
+        }
+    }
+    root_position.map(|position| mapped[position])
+}
+
+/// Frees the nodes of a subtree being mapped by [`map_nodes`] when it's dropped:
+/// the mapped ones, which aren't linked yet, and the ones after `next`.
+struct MapGuard<'a, K, V, K2, V2> {
+    nodes: &'a [NonNull<Node<K, V>>],
+    next: usize,
+    mapped: Vec<NonNull<Node<K2, V2>>>,
+}
+
+impl<'a, K, V, K2, V2> Drop for MapGuard<'a, K, V, K2, V2> {
+    fn drop(&mut self) {
+        for node_ptr in self.mapped.drain(..) {
+            drop(unsafe { Box::from_raw(node_ptr.as_ptr()) });
         }
+        for node_ptr in &self.nodes[self.next..] {
+            drop(unsafe { Box::from_raw(node_ptr.as_ptr()) });
+        }
+    }
+}
+
+/// Links nodes sorted by key into a balanced tree and returns its root.
+///
+/// # Safety
+///
+/// `nodes` must point to distinct nodes which aren't linked to any other nodes.
+unsafe fn link_sorted<K, V>(nodes: &[NonNull<Node<K, V>>]) -> NodePtr<K, V> {
+    let root = link_balanced(nodes)?;
+    (*root.as_ptr()).parent = None;
+    Some(root)
+}
+
+unsafe fn link_balanced<K, V>(nodes: &[NonNull<Node<K, V>>]) -> NodePtr<K, V> {
+    if nodes.is_empty() {
+        return None;
+    }
+
+    let mid = nodes.len() / 2;
+    let node_ptr = nodes[mid];
+    let node = node_ptr.as_ptr();
+    (*node).left = link_balanced(&nodes[..mid]);
+    (*node).right = link_balanced(&nodes[mid + 1..]);
+    (*node).size = nodes.len();
+    for child in [(*node).left, (*node).right].into_iter().flatten() {
+        (*child.as_ptr()).parent = Some(node_ptr);
     }
+    Some(node_ptr)
+}
+
+fn build_balanced<K, V, I>(iter: &mut I, length: usize) -> NodePtr<K, V>
+    where I: Iterator<Item = (K, V)>
+{
+    if length == 0 {
+        return None;
+    }
+
+    let left_length = length / 2;
+    let left = build_balanced(iter, left_length);
+    let (key, value) = iter.next().expect("iterator is shorter than its length");
+    let node_ptr = Node::alloc(key, value);
+    let right = build_balanced(iter, length - left_length - 1);
+
+    unsafe {
+        let node = node_ptr.as_ptr();
+        (*node).left = left;
+        (*node).right = right;
+        (*node).size = length;
+        for child in left.into_iter().chain(right) {
+            (*child.as_ptr()).parent = Some(node_ptr);
+        }
+    }
+
+    Some(node_ptr)
 }
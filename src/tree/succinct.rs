@@ -0,0 +1,168 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{Node, NodePtr};
+
+/// Shape of a tree in balanced-parentheses form, returned by
+/// [`SplayTreeBy::encode_succinct`].
+///
+/// Every node takes two bits: an opening parenthesis, the shape of its left
+/// subtree, a closing parenthesis and the shape of its right subtree. Closing
+/// parentheses come in the order of keys, so the entries are stored separately
+/// in sorted order. Bits are packed into bytes starting from the lowest bit.
+///
+/// [`SplayTreeBy::encode_succinct`]: crate::SplayTreeBy::encode_succinct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuccinctShape {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+/// Error of [`SplayTreeBy::decode_succinct`].
+///
+/// [`SplayTreeBy::decode_succinct`]: crate::SplayTreeBy::decode_succinct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuccinctError {
+    /// The parentheses aren't balanced, or the shape is too short. Holds the
+    /// position of the offending bit.
+    Unbalanced(usize),
+    /// The number of entries doesn't match the number of nodes of the shape.
+    LengthMismatch,
+    /// The key of the entry at this position isn't greater than the previous one.
+    Unordered(usize),
+}
+
+impl fmt::Display for SuccinctError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuccinctError::Unbalanced(pos) => write!(f, "unbalanced parenthesis at bit {}", pos),
+            SuccinctError::LengthMismatch => write!(f, "number of entries doesn't match the shape"),
+            SuccinctError::Unordered(pos) => write!(f, "entry at position {} is out of order", pos),
+        }
+    }
+}
+
+impl Error for SuccinctError {}
+
+impl SuccinctShape {
+    /// Makes a shape of `len` nodes from the bytes returned by [`as_bytes`](Self::as_bytes).
+    #[inline]
+    pub fn from_bytes(bytes: Vec<u8>, len: usize) -> Self {
+        SuccinctShape { bytes, len }
+    }
+
+    /// Returns the packed parentheses.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the number of nodes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the shape has no nodes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    fn bit(&self, pos: usize) -> Option<bool> {
+        self.bytes.get(pos / 8).map(|byte| byte >> (pos % 8) & 1 == 1)
+    }
+
+    #[inline]
+    fn push(&mut self, pos: usize, bit: bool) {
+        if pos.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().expect("byte is pushed above") |= 1 << (pos % 8);
+        }
+    }
+}
+
+/// Encodes the shape of the subtree at `root` of `len` nodes.
+pub(crate) fn encode<K, V>(root: NodePtr<K, V>, len: usize) -> SuccinctShape {
+    let mut shape = SuccinctShape { bytes: Vec::with_capacity(len.div_ceil(4)), len };
+    let mut pos = 0;
+    let mut stack = Vec::new();
+    let mut cur = root;
+    loop {
+        while let Some(node_ptr) = cur {
+            shape.push(pos, true);
+            pos += 1;
+            stack.push(node_ptr);
+            cur = unsafe { (*node_ptr.as_ptr()).left };
+        }
+        let Some(node_ptr) = stack.pop() else { break };
+        shape.push(pos, false);
+        pos += 1;
+        cur = unsafe { (*node_ptr.as_ptr()).right };
+    }
+    shape
+}
+
+/// Builds the nodes of a shape from its entries in the order of keys, and returns
+/// the root. Nothing is allocated unless the shape matches the entries.
+pub(crate) fn decode<K, V>(
+    shape: &SuccinctShape,
+    entries: Vec<(K, V)>,
+) -> Result<NodePtr<K, V>, SuccinctError> {
+    if entries.len() != shape.len {
+        return Err(SuccinctError::LengthMismatch);
+    }
+
+    // Nodes are numbered in the order of opening parentheses, which is pre-order.
+    let mut children = Vec::with_capacity(shape.len);
+    let mut in_order = vec![0; shape.len];
+    let mut stack = Vec::new();
+    let mut slot: Option<(usize, bool)> = None;
+    let mut closed = 0;
+    for pos in 0..2 * shape.len {
+        let bit = shape.bit(pos).ok_or(SuccinctError::Unbalanced(pos))?;
+        if bit {
+            if children.len() == shape.len {
+                return Err(SuccinctError::Unbalanced(pos));
+            }
+            let idx = children.len();
+            children.push((None, None));
+            match slot {
+                Some((parent, true)) => children[parent].0 = Some(idx),
+                Some((parent, false)) => children[parent].1 = Some(idx),
+                None => {},
+            }
+            stack.push(idx);
+            slot = Some((idx, true));
+        } else {
+            let idx = stack.pop().ok_or(SuccinctError::Unbalanced(pos))?;
+            in_order[idx] = closed;
+            closed += 1;
+            slot = Some((idx, false));
+        }
+    }
+    if !stack.is_empty() {
+        return Err(SuccinctError::Unbalanced(2 * shape.len));
+    }
+
+    let nodes: Vec<_> = entries.into_iter()
+        .map(|(key, value)| Node::alloc(key, value))
+        .collect();
+    for (idx, &(left, right)) in children.iter().enumerate() {
+        let node_ptr = nodes[in_order[idx]];
+        let node = unsafe { &mut *node_ptr.as_ptr() };
+        node.left = left.map(|child| nodes[in_order[child]]);
+        node.right = right.map(|child| nodes[in_order[child]]);
+        for child in [node.left, node.right].into_iter().flatten() {
+            unsafe { (*child.as_ptr()).parent = Some(node_ptr) };
+        }
+    }
+    // Children come after their parents in pre-order.
+    for idx in (0..children.len()).rev() {
+        unsafe { Node::update_size(nodes[in_order[idx]]) };
+    }
+    Ok(children.first().map(|_| nodes[in_order[0]]))
+}
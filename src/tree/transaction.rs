@@ -0,0 +1,103 @@
+use std::borrow::Borrow;
+
+use crate::{Compare, Natural, Op, SplayTreeBy};
+
+/// Staged changes of a [`SplayTreeBy::transaction`].
+///
+/// Mutations are recorded instead of being applied, and lookups see the tree
+/// as if they had been. The tree isn't splayed while the transaction is open.
+pub struct Transaction<'a, K, V, C = Natural> {
+    tree: &'a SplayTreeBy<K, V, C>,
+    /// Final state of every changed key, sorted by key: `None` for removed keys.
+    staged: Vec<(K, Option<V>)>,
+}
+
+impl<'a, K, V, C: Compare<K>> Transaction<'a, K, V, C> {
+    #[inline]
+    pub(crate) fn new(tree: &'a SplayTreeBy<K, V, C>) -> Self {
+        Transaction { tree, staged: Vec::new() }
+    }
+
+    /// Returns a reference to the value by a key as seen by the transaction,
+    /// or `None` if the key is absent or removed.
+    ///
+    /// This operation should compute in *O*(*log m* + *h*) time for *m* staged keys,
+    /// where *h* is the height of the tree.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        match self.position(key) {
+            Ok(pos) => self.staged[pos].1.as_ref(),
+            Err(_) => self.tree.lookup(key).map(|node| &node.value),
+        }
+    }
+
+    /// Returns `true` if the key is present as seen by the transaction.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        self.get(key).is_some()
+    }
+
+    /// Stages an insertion of a value with a key, replacing the value if the key
+    /// is present.
+    #[inline]
+    pub fn insert(&mut self, key: K, value: V) {
+        self.stage(key, Some(value));
+    }
+
+    /// Stages a removal of an entry with a key and returns `true` if the key was
+    /// present as seen by the transaction.
+    pub fn remove(&mut self, key: K) -> bool {
+        let present = self.contains_key(&key);
+        if present {
+            self.stage(key, None);
+        }
+        present
+    }
+
+    /// Stages a replacement of the value of a present key and returns `true`,
+    /// or returns `false` if the key is absent as seen by the transaction.
+    pub fn update(&mut self, key: K, value: V) -> bool {
+        let present = self.contains_key(&key);
+        if present {
+            self.stage(key, Some(value));
+        }
+        present
+    }
+
+    /// Returns the number of keys changed by the transaction.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /// Returns `true` if the transaction hasn't changed anything.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    fn stage(&mut self, key: K, value: Option<V>) {
+        match self.position(&key) {
+            Ok(pos) => self.staged[pos].1 = value,
+            Err(pos) => self.staged.insert(pos, (key, value)),
+        }
+    }
+
+    fn position<Q>(&self, key: &Q) -> Result<usize, usize>
+        where K: Borrow<Q>, Q: ?Sized, C: Compare<Q>
+    {
+        let cmp = self.tree.comparator();
+        self.staged.binary_search_by(|(staged, _)| cmp.compare(staged.borrow(), key))
+    }
+
+    /// Turns the staged changes into operations sorted by key.
+    pub(crate) fn into_ops(self) -> Vec<Op<K, V>> {
+        self.staged.into_iter().map(|(key, value)| match value {
+            Some(value) => Op::Insert(key, value),
+            None => Op::Remove(key),
+        }).collect()
+    }
+}
@@ -0,0 +1,139 @@
+//! JavaScript class wrapping a `SplayTree`, exported with `wasm-bindgen`.
+//!
+//! The class is exported as `SplayTree` and follows the `Map` interface where
+//! it can: keys are numbers or strings, values are any JavaScript values, and
+//! `get` returns `undefined` for a missing key. Numbers are ordered before
+//! strings, numbers by value and strings by code points, which differs from
+//! JavaScript's comparison of UTF-16 code units only outside the basic
+//! multilingual plane. Other keys make methods throw.
+//!
+//! Lookups splay the tree, so a front-end repeatedly reading the same few keys
+//! finds them near the root.
+
+use std::cmp::Ordering;
+
+use wasm_bindgen::prelude::*;
+
+use crate::SplayTree;
+
+/// Key of the JavaScript tree: a number or a string.
+#[derive(Debug, Clone, PartialEq)]
+enum JsKey {
+    Number(f64),
+    String(String),
+}
+
+impl JsKey {
+    fn from_js(key: &JsValue) -> Result<Self, JsError> {
+        if let Some(number) = key.as_f64() {
+            // Like `Map`, -0 and 0 are the same key, and so are all NaNs.
+            let number = if number == 0.0 { 0.0 } else if number.is_nan() { f64::NAN } else { number };
+            Ok(JsKey::Number(number))
+        } else if let Some(string) = key.as_string() {
+            Ok(JsKey::String(string))
+        } else {
+            Err(JsError::new("splay tree keys must be numbers or strings"))
+        }
+    }
+
+    fn to_js(&self) -> JsValue {
+        match self {
+            JsKey::Number(number) => JsValue::from_f64(*number),
+            JsKey::String(string) => JsValue::from_str(string),
+        }
+    }
+}
+
+impl Eq for JsKey {}
+
+impl Ord for JsKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (JsKey::Number(a), JsKey::Number(b)) => a.total_cmp(b),
+            (JsKey::Number(_), JsKey::String(_)) => Ordering::Less,
+            (JsKey::String(_), JsKey::Number(_)) => Ordering::Greater,
+            (JsKey::String(a), JsKey::String(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for JsKey {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Ordered map from numbers or strings to JavaScript values.
+#[wasm_bindgen(js_name = SplayTree)]
+#[derive(Default)]
+pub struct JsSplayTree {
+    tree: SplayTree<JsKey, JsValue>,
+}
+
+#[wasm_bindgen(js_class = SplayTree)]
+impl JsSplayTree {
+    /// Creates an empty tree.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        JsSplayTree::default()
+    }
+
+    /// Returns the number of entries.
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns the value by a key, or `undefined` if the tree doesn't contain it.
+    pub fn get(&mut self, key: &JsValue) -> Result<JsValue, JsError> {
+        let key = JsKey::from_js(key)?;
+        Ok(self.tree.get(&key).map_or(JsValue::UNDEFINED, |node| node.value().clone()))
+    }
+
+    /// Sets the value of a key, replacing the previous one.
+    pub fn set(&mut self, key: &JsValue, value: JsValue) -> Result<(), JsError> {
+        let key = JsKey::from_js(key)?;
+        *self.tree.entry(key).or_insert(JsValue::UNDEFINED).value_mut() = value;
+        Ok(())
+    }
+
+    /// Returns `true` if the tree contains a key.
+    pub fn has(&mut self, key: &JsValue) -> Result<bool, JsError> {
+        Ok(self.tree.contains_key(&JsKey::from_js(key)?))
+    }
+
+    /// Removes the entry of a key and returns `true` if there was one.
+    pub fn delete(&mut self, key: &JsValue) -> Result<bool, JsError> {
+        Ok(self.tree.remove(&JsKey::from_js(key)?).is_some())
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    /// Returns the keys in ascending order.
+    pub fn keys(&self) -> Vec<JsValue> {
+        self.tree.iter().map(|(key, _)| key.to_js()).collect()
+    }
+
+    /// Returns the values in the order of their keys.
+    pub fn values(&self) -> Vec<JsValue> {
+        self.tree.iter().map(|(_, value)| value.clone()).collect()
+    }
+
+    /// Returns the least key greater than or equal to `key`, or `undefined`
+    /// if there is none.
+    pub fn ceil(&mut self, key: &JsValue) -> Result<JsValue, JsError> {
+        let key = JsKey::from_js(key)?;
+        Ok(self.tree.get_ceil(&key).map_or(JsValue::UNDEFINED, |node| node.key().to_js()))
+    }
+
+    /// Returns the greatest key less than or equal to `key`, or `undefined`
+    /// if there is none.
+    pub fn floor(&mut self, key: &JsValue) -> Result<JsValue, JsError> {
+        let key = JsKey::from_js(key)?;
+        Ok(self.tree.get_floor(&key).map_or(JsValue::UNDEFINED, |node| node.key().to_js()))
+    }
+}
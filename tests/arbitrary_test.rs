@@ -0,0 +1,38 @@
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use splay_tree::SplayTree;
+
+fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..len).map(|_| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 56) as u8
+    }).collect()
+}
+
+#[test]
+fn arbitrary_tree_test() {
+    let mut total = 0;
+    for seed in 0..32 {
+        let data = random_bytes(seed, 1024);
+        let mut u = Unstructured::new(&data);
+        let mut tree = SplayTree::<u16, u8>::arbitrary(&mut u).unwrap();
+
+        let keys: Vec<u16> = tree.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys.len(), tree.len());
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        for key in keys.iter() {
+            assert!(tree.contains_key(key));
+        }
+        total += keys.len();
+    }
+    assert!(total > 0);
+}
+
+#[test]
+fn empty_input_test() {
+    let mut u = Unstructured::new(&[]);
+    let tree = SplayTree::<u16, u8>::arbitrary(&mut u).unwrap();
+    assert!(tree.is_empty());
+}
@@ -0,0 +1,43 @@
+use splay_tree::{Displaced, SplayBiMap};
+
+#[test]
+fn lookup_both_sides_test() {
+    let mut ids: SplayBiMap<u32, String> = [(3, "carol"), (1, "alice"), (2, "bob")]
+        .into_iter()
+        .map(|(id, name)| (id, name.to_string()))
+        .collect();
+    assert_eq!(ids.len(), 3);
+    assert_eq!(ids.get_by_left(&2).map(String::as_str), Some("bob"));
+    assert_eq!(ids.get_by_right("carol"), Some(&3));
+    assert!(ids.contains_left(&1) && ids.contains_right("alice"));
+    assert!(ids.get_by_left(&4).is_none() && ids.get_by_right("dave").is_none());
+
+    assert!(ids.iter_by_left().map(|(id, name)| (*id, name.as_str())).eq([(1, "alice"), (2, "bob"), (3, "carol")]));
+    assert!(ids.iter_by_right().rev().map(|(name, id)| (name.as_str(), *id)).eq([("carol", 3), ("bob", 2), ("alice", 1)]));
+}
+
+#[test]
+fn insert_remove_test() {
+    let mut ids = SplayBiMap::<u32, &str>::new();
+    let none = Displaced { by_left: None, by_right: None };
+    assert_eq!(ids.insert(1, "alice"), none);
+    assert_eq!(ids.insert(2, "bob"), none);
+
+    let displaced = ids.insert(1, "bob");
+    assert_eq!(displaced.by_left, Some((1, "alice")));
+    assert_eq!(displaced.by_right, Some((2, "bob")));
+    assert_eq!(ids.len(), 1);
+    assert!(!ids.contains_right(&"alice") && !ids.contains_left(&2));
+    assert_eq!(ids.insert(1, "bob"), Displaced { by_left: Some((1, "bob")), by_right: None });
+
+    ids.insert(5, "eve");
+    assert_eq!(ids.remove_by_right(&"bob"), Some((1, "bob")));
+    assert_eq!(ids.remove_by_left(&1), None);
+    assert_eq!(ids.remove_by_left(&5), Some((5, "eve")));
+    assert!(ids.is_empty());
+
+    ids.extend([(7, "gus"), (8, "hal")]);
+    ids.clear();
+    assert!(ids.iter_by_left().next().is_none());
+    assert_eq!(ids.get_by_right(&"gus"), None);
+}
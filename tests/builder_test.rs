@@ -0,0 +1,67 @@
+use splay_tree::{ShapeError, SplayTree, TreeBuilder};
+
+#[test]
+fn build_shape_test() {
+    //        10
+    //      /    \
+    //     5      20
+    //      \    /
+    //       7  15
+    let mut tree: SplayTree<u32, &str> = TreeBuilder::new()
+        .node(10, "a")
+        .node(5, "b").node(20, "c")
+        .hole().node(7, "d").node(15, "e")
+        .build()
+        .unwrap();
+    assert_eq!(tree.len(), 5);
+
+    let root = tree.root().unwrap();
+    assert_eq!(*root.key(), 10);
+    let left = root.left().unwrap();
+    assert_eq!(*left.key(), 5);
+    assert!(left.left().is_none());
+    assert_eq!(*left.right().unwrap().key(), 7);
+    let right = root.right().unwrap();
+    assert_eq!(*right.left().unwrap().key(), 15);
+    assert!(right.right().is_none());
+    assert_eq!(*right.left().unwrap().parent().unwrap().key(), 20);
+
+    assert!(tree.iter().map(|(k, _)| *k).eq([5, 7, 10, 15, 20]));
+    assert_eq!(tree.get(&7).map(|node| *node.value()), Some("d"));
+    assert_eq!(*tree.root().unwrap().key(), 7);
+}
+
+#[test]
+fn build_chain_test() {
+    let mut builder = TreeBuilder::new().node(0u32, 0u32);
+    for i in 1..100 {
+        builder = builder.hole().node(i, i);
+    }
+    let tree = builder.build().unwrap();
+    let mut node = tree.root();
+    let mut depth = 0;
+    while let Some(cur) = node {
+        assert!(cur.left().is_none());
+        node = cur.right();
+        depth += 1;
+    }
+    assert_eq!(depth, 100);
+}
+
+#[test]
+fn build_error_test() {
+    let empty: SplayTree<u32, u32> = TreeBuilder::new().build().unwrap();
+    assert!(empty.is_empty());
+    let holes: SplayTree<u32, u32> = TreeBuilder::new().node(1, 1).hole().hole().build().unwrap();
+    assert_eq!(holes.len(), 1);
+
+    let orphan = TreeBuilder::new().hole().node(1u32, 1u32).build();
+    assert_eq!(orphan.err(), Some(ShapeError::Orphan(1)));
+    let orphan = TreeBuilder::new().node(2u32, 2u32).hole().hole().node(1, 1).build();
+    assert_eq!(orphan.err(), Some(ShapeError::Orphan(3)));
+
+    let unordered = TreeBuilder::new().node(10u32, 0u32).node(5, 0).node(20, 0).hole().node(12, 0).build();
+    assert_eq!(unordered.err(), Some(ShapeError::Unordered(0)));
+    let duplicate = TreeBuilder::new().node(10u32, 0u32).node(10, 0).build();
+    assert_eq!(duplicate.err(), Some(ShapeError::Unordered(0)));
+}
@@ -0,0 +1,112 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use splay_tree::SplayCache;
+
+#[test]
+fn insert_get_test() {
+    let mut cache = SplayCache::new(16);
+    for i in 0..10u32 {
+        assert_eq!(cache.insert(i, i * 2), None);
+    }
+    assert_eq!(cache.len(), 10);
+
+    for i in 0..10u32 {
+        assert_eq!(cache.get(&i), Some(&(i * 2)));
+    }
+    assert_eq!(cache.insert(3, 33), Some(6));
+    assert_eq!(cache.get(&3), Some(&33));
+    assert!(cache.get(&100).is_none());
+}
+
+#[test]
+fn capacity_test() {
+    let mut cache = SplayCache::new(8);
+    for i in 0..100u32 {
+        cache.insert(i, i);
+        assert!(cache.len() <= 8);
+        assert!(cache.contains_key(&i));
+    }
+    assert_eq!(cache.len(), 8);
+}
+
+#[test]
+fn keeps_hot_entries_test() {
+    let mut cache = SplayCache::new(4);
+    cache.insert(0u32, 0u32);
+    for i in 1..50 {
+        assert_eq!(cache.get(&0), Some(&0));
+        cache.insert(i, i);
+    }
+    assert!(cache.contains_key(&0));
+}
+
+#[test]
+fn keeps_recently_accessed_low_keys_test() {
+    let mut cache = SplayCache::new(16);
+    for i in 0..16u32 {
+        cache.insert(i, i);
+    }
+    for i in 0..4 {
+        assert_eq!(cache.get(&i), Some(&i));
+    }
+    for i in 100..104 {
+        cache.insert(i, i);
+    }
+    assert_eq!(cache.len(), 16);
+    for i in 0..4 {
+        assert!(cache.contains_key(&i), "{} has been evicted", i);
+    }
+}
+
+#[test]
+fn eviction_callback_test() {
+    let evicted = Rc::new(RefCell::new(Vec::new()));
+    let evicted_clone = evicted.clone();
+    let mut cache = SplayCache::with_eviction_callback(3, move |k: u32, v: u32| {
+        evicted_clone.borrow_mut().push((k, v));
+    });
+
+    for i in 0..10 {
+        cache.insert(i, i + 1);
+    }
+    assert_eq!(evicted.borrow().len(), 7);
+    for (k, v) in evicted.borrow().iter() {
+        assert_eq!(*v, k + 1);
+        assert!(!cache.contains_key(k));
+    }
+
+    cache.evict_all();
+    assert!(cache.is_empty());
+    assert_eq!(evicted.borrow().len(), 10);
+}
+
+#[test]
+fn remove_test() {
+    let mut cache = SplayCache::new(4);
+    cache.insert(1u32, 10u32);
+    cache.insert(2, 20);
+    assert_eq!(cache.remove(&1), Some(10));
+    assert_eq!(cache.remove(&1), None);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.evict(), Some((2, 20)));
+    assert_eq!(cache.evict(), None);
+}
+
+#[test]
+fn set_capacity_test() {
+    let mut cache = SplayCache::new(10);
+    for i in 0..10u32 {
+        cache.insert(i, i);
+    }
+    cache.set_capacity(3);
+    assert_eq!(cache.capacity(), 3);
+    assert_eq!(cache.len(), 3);
+    assert!(cache.contains_key(&9));
+}
+
+#[test]
+#[should_panic]
+fn zero_capacity_test() {
+    SplayCache::<u32, u32>::new(0);
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "concurrent")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use splay_tree::{ConcurrentTree, PersistentTree};
+
+struct Counting;
+
+static LIVE: AtomicIsize = AtomicIsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(1, Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[test]
+fn no_leaks_test() {
+    let live = LIVE.load(Ordering::Relaxed);
+    for _ in 0..1000 {
+        drop(ConcurrentTree::<u32, u32>::new());
+    }
+    assert_eq!(LIVE.load(Ordering::Relaxed), live);
+
+    for _ in 0..1000 {
+        let tree: PersistentTree<u32, u32> = ConcurrentTree::new().into_inner();
+        drop(tree);
+    }
+    assert_eq!(LIVE.load(Ordering::Relaxed), live);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "concurrent")]
+
+use std::sync::Arc;
+use std::thread;
+
+use splay_tree::{ConcurrentTree, PersistentTree};
+
+#[test]
+fn insert_get_test() {
+    let tree = ConcurrentTree::new();
+    for i in [13u32, 4, 3, 89, 12] {
+        assert_eq!(tree.insert(i, i.to_string()), None);
+    }
+    assert_eq!(tree.insert(4, "four".to_string()), Some("4".to_string()));
+    assert_eq!(tree.get(&4), Some("four".to_string()));
+    assert_eq!(tree.get_with(&89, |v| v.len()), Some(2));
+    assert!(tree.get(&100).is_none());
+    assert_eq!(tree.remove(&13), Some("13".to_string()));
+    assert!(!tree.contains_key(&13));
+    assert_eq!(tree.len(), 4);
+}
+
+#[test]
+fn read_guard_test() {
+    let tree: ConcurrentTree<u32, u32> = ConcurrentTree::from((0..5).map(|i| (i, i)).collect::<PersistentTree<_, _>>());
+    let before = tree.read();
+    let snapshot = tree.snapshot();
+
+    let removed = tree.update(|tree| {
+        tree.insert(10, 10);
+        tree.remove(&0)
+    });
+    assert_eq!(removed, Some(0));
+    assert!(before.iter().map(|(k, _)| *k).eq(0..5));
+    assert!(snapshot.ptr_eq(&before));
+    drop(before);
+
+    assert!(tree.read().iter().map(|(k, _)| *k).eq([1, 2, 3, 4, 10]));
+    assert_eq!(tree.into_inner().len(), 5);
+}
+
+#[test]
+fn concurrent_readers_test() {
+    let tree = Arc::new(ConcurrentTree::new());
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                for _ in 0..200 {
+                    let version = tree.read();
+                    let keys: Vec<u32> = version.iter().map(|(k, _)| *k).collect();
+                    assert_eq!(keys, (0..version.len() as u32).collect::<Vec<_>>());
+                }
+            })
+        })
+        .collect();
+
+    for i in 0..200 {
+        tree.insert(i, i);
+    }
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    assert_eq!(tree.len(), 200);
+}
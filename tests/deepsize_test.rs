@@ -0,0 +1,46 @@
+#![cfg(feature = "deepsize")]
+
+use std::mem;
+
+use deepsize::DeepSizeOf;
+use splay_tree::{Node, PersistentTree, SplayCache, SplayTree};
+
+#[test]
+fn splay_tree_test() {
+    let mut tree = SplayTree::new();
+    assert_eq!(tree.deep_size_of(), mem::size_of::<SplayTree<u32, String>>());
+
+    tree.insert(1u32, "one".to_string());
+    tree.insert(3, "three".to_string());
+    let expected = mem::size_of::<SplayTree<u32, String>>()
+        + 2 * mem::size_of::<Node<u32, String>>()
+        + "one".len() + "three".len();
+    assert_eq!(tree.deep_size_of(), expected);
+
+    let frozen = tree.freeze();
+    assert!(frozen.deep_size_of() >= 2 * mem::size_of::<(u32, String)>() + 8);
+}
+
+#[test]
+fn cache_test() {
+    let mut cache = SplayCache::new(2);
+    cache.insert(1u32, vec![0u8; 100]);
+    cache.insert(2, vec![0u8; 100]);
+    cache.insert(3, vec![0u8; 100]);
+    let expected = mem::size_of::<SplayCache<u32, Vec<u8>>>()
+        + 2 * mem::size_of::<Node<u32, Vec<u8>>>()
+        + 200;
+    assert_eq!(cache.deep_size_of(), expected);
+}
+
+#[test]
+fn shared_versions_test() {
+    let tree: PersistentTree<u32, u64> = (0..100).map(|i| (i, i as u64)).collect();
+    let mut version = tree.clone();
+    version.insert(1000, 0);
+
+    // The new version shares all but the copied path with the old one.
+    let separate = tree.deep_size_of() + version.deep_size_of();
+    let together = (tree, version).deep_size_of();
+    assert!(together < separate * 2 / 3);
+}
@@ -1,4 +1,5 @@
-use splay_tree::SplayTree;
+use splay_tree::{Entry, RawEntry, SplayTree};
+use std::cmp::Ordering;
 
 mod common;
 
@@ -49,6 +50,26 @@ fn or_insert_with_key_test() {
     common::check_tree_structure(&tree);
 }
 
+#[test]
+fn or_try_insert_with_test() {
+    let elems = [52, 17, 8, 90, 33];
+    let mut tree = common::create_tree(&elems);
+
+    let result = tree.entry(60).or_try_insert_with(|| Err::<u32, _>("no value"));
+    assert_eq!(result.err(), Some("no value"));
+    assert_eq!(tree.len(), elems.len());
+    assert!(!tree.contains_key(&60));
+    common::check_tree_structure(&tree);
+
+    let node = tree.entry(60).or_try_insert_with(|| Ok::<_, &str>(6)).unwrap();
+    assert_eq!(*node.value(), 6);
+
+    let node = tree.entry(17).or_try_insert_with(|| Err("not called")).unwrap();
+    assert_eq!(*node.value(), 17);
+    assert_eq!(tree.len(), elems.len() + 1);
+    common::check_tree_structure(&tree);
+}
+
 #[test]
 fn and_modify_test() {
     let elems = [134, 45, 91, 34, 21, 27];
@@ -106,3 +127,99 @@ fn key_test() {
     }
     assert_eq!(tree.entry(3333).key(), &3333);
 }
+
+#[test]
+fn occupied_entry_test() {
+    let elems = [70, 12, 95, 33, 58, 4];
+    let mut tree = common::create_tree(&elems);
+
+    let Entry::Occupied(mut entry) = tree.entry(33) else { panic!("33 must be occupied") };
+    assert_eq!(entry.key(), &33);
+    assert_eq!(entry.get(), &33);
+    *entry.get_mut() += 1;
+    assert_eq!(entry.insert(100), 34);
+    *entry.into_mut() += 1;
+    assert_eq!(tree.get(&33).map(|node| *node.value()), Some(101));
+    common::check_tree_structure(&tree);
+
+    let Entry::Occupied(entry) = tree.entry(58) else { panic!("58 must be occupied") };
+    assert_eq!(entry.remove(), 58);
+    assert!(!tree.contains_key(&58));
+    common::check_tree_structure(&tree);
+
+    let Entry::Occupied(entry) = tree.entry(4) else { panic!("4 must be occupied") };
+    assert_eq!(entry.remove_entry(), (4, 4));
+    assert_eq!(tree.len(), elems.len() - 2);
+    common::check_tree_structure(&tree);
+
+    assert!(matches!(tree.entry(58), Entry::Vacant(_)));
+}
+
+#[test]
+fn insert_entry_test() {
+    let elems = [18, 7, 42, 3, 25];
+    let mut tree = common::create_tree(&elems);
+
+    let entry = tree.entry(11).insert_entry(5);
+    assert_eq!((entry.key(), entry.get()), (&11, &5));
+    assert_eq!(entry.remove(), 5);
+    assert_eq!(tree.len(), elems.len());
+    common::check_tree_structure(&tree);
+
+    let mut entry = tree.entry(42).insert_entry(0);
+    assert_eq!(entry.insert(1), 0);
+    assert_eq!(tree.get(&42).map(|node| *node.value()), Some(1));
+    common::check_tree_structure(&tree);
+
+    let mut strings = SplayTree::<String, u32>::new();
+    let entry = strings.entry_ref("key").insert_entry(1);
+    assert_eq!(entry.key(), "key");
+    assert_eq!(strings.len(), 1);
+}
+
+#[test]
+fn entry_by_test() {
+    let mut tree = SplayTree::<(u32, u32), u32>::new();
+    for key in [(1, 5), (2, 3), (2, 7), (3, 1)] {
+        tree.insert(key, key.0 + key.1);
+    }
+
+    let node = tree.entry_by(|key| (2, 7).cmp(key)).and_modify(|_, v| *v *= 2).or_insert((2, 7), 0);
+    assert_eq!(*node.value(), 18);
+
+    // Lands before every key starting with 2.
+    let RawEntry::Vacant(entry) = tree.entry_by(|key| 2.cmp(&key.0).then(Ordering::Less)) else {
+        panic!("the search closure never returns Equal")
+    };
+    let node = entry.insert((2, 0), 2);
+    assert_eq!(node.key(), &(2, 0));
+    assert_eq!(tree.len(), 5);
+    common::check_tree_structure(&tree);
+
+    let node = tree.entry_by(|key| 4.cmp(&key.0)).or_insert_with(|| ((4, 4), 8));
+    assert_eq!(node.key(), &(4, 4));
+    let keys: Vec<_> = tree.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, [(1, 5), (2, 0), (2, 3), (2, 7), (3, 1), (4, 4)]);
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn entry_ref_test() {
+    let mut tree = SplayTree::<String, u32>::new();
+    for word in "the quick brown fox jumps over the lazy dog the end".split(' ') {
+        *tree.entry_ref(word).or_default().value_mut() += 1;
+    }
+    assert_eq!(tree.len(), 9);
+    assert_eq!(tree.get(&"the".to_string()).map(|node| *node.value()), Some(3));
+    common::check_tree_structure(&tree);
+
+    let entry = tree.entry_ref("fox");
+    assert_eq!(entry.key(), "fox");
+    let node = entry.and_modify(|v| *v += 10).or_insert(0);
+    assert_eq!(*node.value(), 11);
+
+    let node = tree.entry_ref("cat").or_insert_with_key(|key| key.len() as u32);
+    assert_eq!((node.key().as_str(), *node.value()), ("cat", 3));
+    assert_eq!(tree.len(), 10);
+    common::check_tree_structure(&tree);
+}
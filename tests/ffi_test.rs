@@ -0,0 +1,39 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::c_void;
+use std::ptr;
+
+use splay_tree::ffi::*;
+
+#[test]
+fn handle_test() {
+    let mut values = [10u32, 20, 30];
+    let value_ptrs: Vec<*mut c_void> = values.iter_mut()
+        .map(|v| v as *mut u32 as *mut c_void)
+        .collect();
+
+    unsafe {
+        let tree = splay_tree_new();
+        assert!(!splay_tree_insert(tree, 1, value_ptrs[0], ptr::null_mut()));
+        assert!(!splay_tree_insert(tree, -5, ptr::null_mut(), ptr::null_mut()));
+        let mut old = ptr::null_mut();
+        assert!(splay_tree_insert(tree, 1, value_ptrs[1], &mut old));
+        assert_eq!(old, value_ptrs[0]);
+        assert_eq!(splay_tree_len(tree), 2);
+
+        let mut value = ptr::null_mut();
+        assert!(splay_tree_get(tree, 1, &mut value));
+        assert_eq!(*(value as *mut u32), 20);
+        assert!(splay_tree_get(tree, -5, &mut value));
+        assert!(value.is_null());
+        assert!(!splay_tree_get(tree, 7, ptr::null_mut()));
+
+        assert!(splay_tree_remove(tree, 1, &mut value));
+        assert_eq!(value, value_ptrs[1]);
+        assert!(!splay_tree_remove(tree, 1, ptr::null_mut()));
+        assert_eq!(splay_tree_len(tree), 1);
+
+        splay_tree_free(tree);
+        splay_tree_free(ptr::null_mut());
+    }
+}
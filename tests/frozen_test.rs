@@ -0,0 +1,64 @@
+use std::thread;
+
+use splay_tree::{SplayTree, FrozenTree};
+
+mod common;
+
+#[test]
+fn freeze_get_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let frozen = common::create_tree(&elems).freeze();
+    assert_eq!(frozen.len(), elems.len());
+
+    for i in elems.iter() {
+        assert_eq!(frozen.get(i), Some(i));
+    }
+    for i in [1, 5, 100].iter() {
+        assert!(!frozen.contains_key(i));
+    }
+    assert_eq!(frozen.get_min(), Some((&0, &0)));
+    assert_eq!(frozen.get_max(), Some((&17, &17)));
+
+    let keys: Vec<u32> = frozen.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, vec![0, 2, 3, 4, 9, 11, 13, 17]);
+}
+
+#[test]
+fn shared_readers_test() {
+    let elems: Vec<u32> = (0..1000).map(|i| i * 7919 % 1000).collect();
+    let frozen = common::create_tree(&elems).freeze();
+
+    thread::scope(|s| {
+        for t in 0..4 {
+            let frozen = &frozen;
+            s.spawn(move || {
+                for i in (t..1000).step_by(4) {
+                    assert_eq!(frozen.get(&i), Some(&i));
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn thaw_test() {
+    let elems: Vec<u32> = (0..100).rev().collect();
+    let frozen = common::create_tree(&elems).freeze();
+    let mut tree = frozen.thaw();
+    assert_eq!(tree.len(), 100);
+    common::check_tree_structure(&tree);
+
+    for i in 0..100 {
+        assert_eq!(tree.get(&i).unwrap().value(), &i);
+    }
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn empty_test() {
+    let frozen = SplayTree::<u32, u32>::new().freeze();
+    assert!(frozen.is_empty());
+    assert!(frozen.get_min().is_none());
+    let tree: SplayTree<u32, u32> = FrozenTree::from(SplayTree::new()).into();
+    assert!(tree.is_empty());
+}
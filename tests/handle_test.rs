@@ -0,0 +1,81 @@
+mod common;
+
+#[test]
+fn id_of_test() {
+    let elems = [40, 12, 77, 5, 23, 91, 60];
+    let mut tree = common::create_tree(&elems);
+    let ids: Vec<_> = elems.iter().map(|key| tree.id_of(key).unwrap()).collect();
+    assert_eq!(tree.id_of(&1000), None);
+    assert_eq!(tree.id_of(&23), Some(ids[4]));
+
+    for (key, id) in elems.iter().zip(&ids) {
+        assert_eq!(tree.get_by_id(*id).map(|node| *node.key()), Some(*key));
+    }
+
+    *tree.get_by_id_mut(ids[2]).unwrap().value_mut() = 0;
+    assert_eq!(tree.get(&77).map(|node| *node.value()), Some(0));
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn splay_node_test() {
+    let elems = [40, 12, 77, 5, 23, 91, 60];
+    let mut tree = common::create_tree(&elems);
+    let ids: Vec<_> = elems.iter().map(|key| tree.id_of(key).unwrap()).collect();
+
+    for (key, id) in elems.iter().zip(&ids).rev() {
+        *tree.splay_node(*id).unwrap().value_mut() += 1;
+        assert_eq!(tree.root().map(|node| *node.key()), Some(*key));
+        common::check_tree_structure(&tree);
+    }
+    assert!(tree.iter().all(|(key, value)| *value == key + 1));
+
+    tree.remove(&23);
+    assert!(tree.splay_node(ids[4]).is_none());
+    assert_eq!(tree.splay_node(ids[0]).map(|node| *node.key()), Some(40));
+}
+
+#[test]
+fn remove_node_test() {
+    let elems = [8, 3, 14, 1, 6, 11, 19];
+    let mut tree = common::create_tree(&elems);
+    let id = tree.id_of(&6).unwrap();
+    let other = tree.entry(11).insert_entry(110).id();
+
+    let node = tree.remove_node(id).unwrap();
+    assert_eq!(node.key(), &6);
+    assert_eq!(tree.len(), elems.len() - 1);
+    common::check_tree_structure(&tree);
+    assert!(tree.get_by_id(id).is_none());
+    assert!(tree.remove_node(id).is_none());
+
+    // The slot is reused, but the stale handle doesn't see the new node.
+    tree.insert(6, 60);
+    let new_id = tree.id_of(&6).unwrap();
+    assert_ne!(new_id, id);
+    assert!(tree.get_by_id(id).is_none());
+    assert_eq!(tree.get_by_id(new_id).map(|node| *node.value()), Some(60));
+
+    tree.remove(&11);
+    assert!(tree.get_by_id(other).is_none());
+    common::check_tree_structure(&tree);
+}
+
+
+#[test]
+fn remove_node_shapes_test() {
+    let elems = [50, 25, 75, 12, 37, 62, 87, 6, 18, 31, 43, 56, 68, 81, 93, 40];
+    for removed in elems {
+        let mut tree = common::create_tree(&elems);
+        let id = tree.id_of(&removed).unwrap();
+        tree.get(&6);
+        tree.get(&93);
+
+        let node = tree.remove_node(id).unwrap();
+        assert_eq!(node.key(), &removed);
+        common::check_tree_structure(&tree);
+        let mut expected: Vec<_> = elems.iter().copied().filter(|&key| key != removed).collect();
+        expected.sort();
+        assert_eq!(tree.iter().map(|(key, _)| *key).collect::<Vec<_>>(), expected);
+    }
+}
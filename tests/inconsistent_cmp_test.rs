@@ -0,0 +1,85 @@
+// Validation checks the order of keys with the comparator itself.
+#![cfg(not(feature = "validate_debug"))]
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+use splay_tree::{Compare, SplayTreeBy};
+
+/// Returns a comparator answering at random, seeded with `seed`.
+fn random_order(seed: u64) -> impl Fn(&u32, &u32) -> Ordering {
+    let state = Cell::new(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1);
+    move |_, _| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        [Ordering::Less, Ordering::Equal, Ordering::Greater][(x % 3) as usize]
+    }
+}
+
+/// Checks the links and subtree sizes of a tree whose keys may be out of order.
+fn check_links<K, V, C: Compare<K>>(tree: &SplayTreeBy<K, V, C>) {
+    let mut count = 0;
+    let mut stack: Vec<_> = tree.root().into_iter().collect();
+    while let Some(node) = stack.pop() {
+        let children: Vec<_> = node.left().into_iter().chain(node.right()).collect();
+        for &child in &children {
+            assert!(std::ptr::eq(child.parent().unwrap(), node));
+        }
+        let len = 1 + children.iter().map(|child| child.subtree_len()).sum::<usize>();
+        assert_eq!(node.subtree_len(), len);
+        stack.extend(children);
+        count += 1;
+    }
+    assert_eq!(count, tree.len());
+    assert_eq!(tree.iter().count(), tree.len());
+}
+
+#[test]
+fn inconsistent_remove_range_test() {
+    for seed in 0..100 {
+        let mut tree = SplayTreeBy::with_comparator(random_order(seed));
+        for i in 0..50 {
+            tree.insert(i, i);
+        }
+        let len = tree.len();
+        let (a, b) = (seed as u32 % 50, seed as u32 * 7 % 50);
+        let removed = tree.remove_range(a..=b);
+        assert_eq!(tree.len(), len - removed);
+        check_links(&tree);
+    }
+}
+
+#[test]
+fn inconsistent_splay_range_test() {
+    for seed in 0..100 {
+        let mut tree = SplayTreeBy::with_comparator(random_order(seed));
+        for i in 0..50 {
+            tree.insert(i, i);
+        }
+        let len = tree.len();
+        let (a, b) = (seed as u32 % 50, seed as u32 * 7 % 50);
+        if let Some(middle) = tree.splay_range(a..=b) {
+            assert!(middle.subtree_len() <= len);
+        }
+        assert_eq!(tree.len(), len);
+        check_links(&tree);
+    }
+}
+
+#[test]
+fn inconsistent_replace_key_test() {
+    for seed in 0..300 {
+        let mut tree = SplayTreeBy::with_comparator(random_order(seed));
+        for i in 0..50 {
+            tree.insert(i, i);
+        }
+        let len = tree.len();
+        let (a, b) = (seed as u32 % 50, seed as u32 * 7 % 50 + 50);
+        let _ = tree.replace_key(&a, b);
+        assert_eq!(tree.len(), len);
+        check_links(&tree);
+    }
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "insertion_order")]
+
+use splay_tree::SplayTree;
+
+fn inserted(keys: &[u32]) -> SplayTree<u32, u32> {
+    let mut tree = SplayTree::new();
+    for &key in keys {
+        tree.insert(key, key);
+    }
+    tree
+}
+
+fn insertion_keys(tree: &SplayTree<u32, u32>) -> Vec<u32> {
+    tree.iter_insertion_order().map(|(key, _)| *key).collect()
+}
+
+#[test]
+fn insert_remove_test() {
+    let mut tree = inserted(&[5, 1, 9, 3, 7]);
+    assert_eq!(insertion_keys(&tree), [5, 1, 9, 3, 7]);
+    assert!(tree.iter_insertion_order().rev().map(|(key, _)| *key).eq([7, 3, 9, 1, 5]));
+    assert_eq!(tree.iter_insertion_order().len(), 5);
+
+    tree.insert(1, 10);
+    tree.get(&9);
+    assert_eq!(insertion_keys(&tree), [5, 1, 9, 3, 7]);
+
+    let node = tree.remove(&9).unwrap();
+    assert_eq!(*node.key(), 9);
+    tree.insert(9, 9);
+    assert_eq!(tree.remove_range(4..=5), 1);
+    assert_eq!(insertion_keys(&tree), [1, 3, 7, 9]);
+
+    tree.clear();
+    assert!(tree.iter_insertion_order().next().is_none());
+    tree.insert(2, 2);
+    assert_eq!(insertion_keys(&tree), [2]);
+}
+
+#[test]
+fn split_and_join_test() {
+    let keys = [8, 3, 12, 1, 15, 6, 10, 0];
+    let mut tree = inserted(&keys);
+    let upper = tree.detach_subtree(&7);
+    assert_eq!(insertion_keys(&tree), [3, 1, 6, 0]);
+    assert_eq!(insertion_keys(&upper), [8, 12, 15, 10]);
+
+    tree.graft(upper).unwrap();
+    assert_eq!(insertion_keys(&tree), [3, 1, 6, 0, 8, 12, 15, 10]);
+    tree.insert(4, 4);
+
+    let (even, odd) = tree.partition(|key, _| key % 2 == 0);
+    assert_eq!(insertion_keys(&even), [6, 0, 8, 12, 10, 4]);
+    assert_eq!(insertion_keys(&odd), [3, 1, 15]);
+
+    let mut merged = odd;
+    merged.merge_with(even, |_, a, _| a);
+    merged.merge_with(inserted(&[2, 3]), |_, a, _| a);
+    assert_eq!(insertion_keys(&merged), [3, 1, 15, 6, 0, 8, 12, 10, 4, 2]);
+
+    let mut rest = merged.split_at_rank(4);
+    assert_eq!(insertion_keys(&merged), [3, 1, 0, 2]);
+    assert_eq!(rest.difference_with(&inserted(&[8, 15])), 2);
+    assert_eq!(insertion_keys(&rest), [6, 12, 10, 4]);
+}
+
+#[test]
+fn rebuilt_trees_test() {
+    let tree = inserted(&[4, 2, 9, 7]);
+    let mapped = tree.map_keys(|key| 10 - key);
+    assert_eq!(insertion_keys(&mapped), [6, 8, 1, 3]);
+
+    let folded = mapped.map_keys(|key| key % 3).map_values(|_, value| value + 1);
+    assert_eq!(insertion_keys(&folded), [0, 2, 1]);
+
+    let (root, length, cmp) = folded.into_raw_parts();
+    let tree = unsafe { SplayTree::from_raw_parts(root, length, cmp) };
+    assert_eq!(insertion_keys(&tree), [0, 2, 1]);
+
+    let built = SplayTree::from(std::collections::BTreeMap::from([(3, 3), (1, 1), (2, 2)]));
+    assert_eq!(insertion_keys(&built), [1, 2, 3]);
+}
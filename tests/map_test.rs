@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use splay_tree::SplayMap;
+
+#[test]
+fn insert_get_remove_test() {
+    let mut map: SplayMap<u32, u32> = SplayMap::new();
+    let mut expected = BTreeMap::new();
+    for i in 0..50u32 {
+        let key = (i * 7) % 31;
+        assert_eq!(map.insert(key, i), expected.insert(key, i));
+    }
+    assert_eq!(map.len(), expected.len());
+
+    for key in 0..31u32 {
+        assert_eq!(map.get(&key), expected.get(&key));
+        assert_eq!(map.contains_key(&key), expected.contains_key(&key));
+    }
+    if let Some(value) = map.get_mut(&3) {
+        *value = 100;
+    }
+    assert_eq!(map.get_key_value(&3), Some((&3, &100)));
+
+    assert_eq!(map.remove(&3), Some(100));
+    assert_eq!(map.remove(&3), None);
+    assert_eq!(map.remove_entry(&4), expected.remove_entry(&4));
+    assert!(map.get(&4).is_none());
+}
+
+#[test]
+fn borrowed_key_test() {
+    let mut map: SplayMap<String, usize> = ["one", "two", "three"].iter()
+        .map(|s| (s.to_string(), s.len()))
+        .collect();
+    assert_eq!(map.get("two"), Some(&3));
+    assert!(map.contains_key("three"));
+    assert_eq!(map.remove("one"), Some(3));
+    assert!(!map.contains_key("one"));
+}
+
+#[test]
+fn first_last_test() {
+    let mut map: SplayMap<u32, u32> = (0..10).map(|i| (i, i * 2)).collect();
+    assert_eq!(map.first_key_value(), Some((&0, &0)));
+    assert_eq!(map.last_key_value(), Some((&9, &18)));
+    assert_eq!(map.pop_first(), Some((0, 0)));
+    assert_eq!(map.pop_last(), Some((9, 18)));
+    assert_eq!(map.len(), 8);
+
+    let mut empty: SplayMap<u32, u32> = SplayMap::new();
+    assert!(empty.first_key_value().is_none());
+    assert!(empty.pop_last().is_none());
+}
+
+#[test]
+fn iter_test() {
+    let mut map: SplayMap<u32, u32> = (0..10).rev().map(|i| (i, i * 2)).collect();
+    map.extend((10..15).map(|i| (i, i * 2)));
+    assert!(map.keys().copied().eq(0..15));
+    assert!(map.values().copied().eq((0..15).map(|i| i * 2)));
+    assert!(map.keys().rev().copied().eq((0..15).rev()));
+    assert_eq!(map.values().len(), 15);
+    assert!(map.iter().map(|(k, v)| (*k, *v)).eq((0..15).map(|i| (i, i * 2))));
+    assert!(map.into_iter().eq((0..15).map(|i| (i, i * 2))));
+}
+
+#[test]
+fn clear_test() {
+    let mut map: SplayMap<u32, String> = (0..10).map(|i| (i, i.to_string())).collect();
+    map.clear();
+    assert!(map.is_empty());
+    assert!(map.get(&1).is_none());
+    map.insert(1, "1".to_string());
+    assert_eq!(map.len(), 1);
+}
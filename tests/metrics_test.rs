@@ -0,0 +1,88 @@
+#![cfg(feature = "metrics")]
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use metrics::{Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder};
+use metrics::{SharedString, Unit};
+
+use splay_tree::SplayTree;
+
+#[derive(Default)]
+struct Samples(Mutex<Vec<f64>>);
+
+impl HistogramFn for Samples {
+    fn record(&self, value: f64) {
+        self.0.lock().unwrap().push(value);
+    }
+}
+
+#[derive(Default)]
+struct TestRecorder {
+    len: Arc<AtomicU64>,
+    rotations: Arc<AtomicU64>,
+    depths: Arc<Samples>,
+}
+
+impl TestRecorder {
+    fn len(&self) -> f64 {
+        f64::from_bits(self.len.load(Ordering::Relaxed))
+    }
+}
+
+impl Recorder for TestRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        assert_eq!(key.name(), "splay_tree.rotations");
+        Counter::from_arc(self.rotations.clone())
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        assert_eq!(key.name(), "splay_tree.len");
+        Gauge::from_arc(self.len.clone())
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        assert_eq!(key.name(), "splay_tree.access_depth");
+        Histogram::from_arc(self.depths.clone())
+    }
+}
+
+#[test]
+fn recorder_test() {
+    let recorder = TestRecorder::default();
+
+    metrics::with_local_recorder(&recorder, || {
+        let mut tree = SplayTree::new();
+        for i in 0..10u32 {
+            tree.insert(i, i);
+        }
+        assert_eq!(recorder.len(), 10.0);
+
+        let mut other: SplayTree<u32, u32> = SplayTree::new();
+        other.insert(1, 1);
+        assert_eq!(recorder.len(), 11.0);
+
+        // The tree is a left spine, so 0 is 9 edges below the root.
+        assert!(tree.get(&0).is_some());
+        assert_eq!(recorder.depths.0.lock().unwrap().last(), Some(&9.0));
+
+        assert!(tree.remove(&5).is_some());
+        assert_eq!(recorder.len(), 10.0);
+
+        let mut iter = tree.into_iter();
+        iter.next();
+        assert_eq!(recorder.len(), 1.0);
+        drop(other);
+        assert_eq!(recorder.len(), 0.0);
+    });
+
+    let depths = recorder.depths.0.lock().unwrap();
+    let total: f64 = depths.iter().sum();
+    assert_eq!(recorder.rotations.load(Ordering::Relaxed), total as u64);
+}
@@ -0,0 +1,93 @@
+use std::sync::{Arc, Mutex};
+
+use splay_tree::{Observer, SplayTree};
+
+mod common;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Event {
+    Insert(u32, u32),
+    Remove(u32, u32),
+    Replace(u32, u32, u32),
+}
+
+#[derive(Default, Clone)]
+struct Recorder {
+    events: Arc<Mutex<Vec<Event>>>,
+    rotations: Arc<Mutex<usize>>,
+}
+
+impl Recorder {
+    fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+impl Observer<u32, u32> for Recorder {
+    fn on_insert(&mut self, key: &u32, value: &u32) {
+        self.events.lock().unwrap().push(Event::Insert(*key, *value));
+    }
+
+    fn on_remove(&mut self, key: &u32, value: &u32) {
+        self.events.lock().unwrap().push(Event::Remove(*key, *value));
+    }
+
+    fn on_replace(&mut self, key: &u32, old: &u32, new: &u32) {
+        self.events.lock().unwrap().push(Event::Replace(*key, *old, *new));
+    }
+
+    fn on_rotate(&mut self, rotations: usize) {
+        *self.rotations.lock().unwrap() += rotations;
+    }
+}
+
+#[test]
+fn entry_changes_test() {
+    let recorder = Recorder::default();
+    let mut tree = SplayTree::new();
+    tree.set_observer(recorder.clone());
+
+    tree.insert(1, 10);
+    tree.insert(2, 20);
+    tree.insert(1, 11);
+    tree.entry(3).or_insert(30);
+    tree.entry(3).and_modify(|v| *v += 1);
+    tree.remove(&2);
+    assert!(tree.remove(&2).is_none());
+    assert_eq!(recorder.take(), [
+        Event::Insert(1, 10),
+        Event::Insert(2, 20),
+        Event::Replace(1, 10, 11),
+        Event::Insert(3, 30),
+        Event::Remove(2, 20),
+    ]);
+    assert!(*recorder.rotations.lock().unwrap() > 0);
+
+    tree.replace_key(&3, 5).unwrap();
+    let id = tree.id_of(&1).unwrap();
+    tree.remove_node(id);
+    assert_eq!(recorder.take(), [Event::Remove(3, 31), Event::Insert(5, 31), Event::Remove(1, 11)]);
+
+    assert!(tree.take_observer().is_some());
+    tree.insert(7, 70);
+    assert!(recorder.take().is_empty());
+}
+
+#[test]
+fn bulk_changes_test() {
+    let recorder = Recorder::default();
+    let mut tree = common::create_tree(&[10, 20, 30]);
+    tree.set_observer(recorder.clone());
+
+    assert!(tree.graft(common::create_tree(&[40])).is_ok());
+    assert_eq!(recorder.take(), [Event::Insert(40, 40)]);
+
+    tree.merge_with(common::create_tree(&[15, 20]), |_, a, b| a + b);
+    assert_eq!(recorder.take(), [Event::Insert(15, 15), Event::Remove(20, 20), Event::Insert(20, 40)]);
+
+    let detached = tree.detach_subtree(&30);
+    let mut events = recorder.take();
+    events.sort_by_key(|event| format!("{:?}", event));
+    assert_eq!(events, [Event::Remove(30, 30), Event::Remove(40, 40)]);
+    assert_eq!(detached.len(), 2);
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "rayon")]
+
+use rayon::prelude::*;
+use splay_tree::SplayTree;
+
+mod common;
+
+#[test]
+fn par_iter_test() {
+    let elems: Vec<u32> = (0..10_000).map(|i| i * 7919 % 10_000).collect();
+    let tree = common::create_tree(&elems);
+
+    let sum: u64 = tree.par_iter().map(|(_, v)| *v as u64).sum();
+    assert_eq!(sum, (0..10_000u64).sum());
+
+    let keys: Vec<u32> = tree.par_iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, (0..10_000).collect::<Vec<_>>());
+    assert_eq!(tree.par_iter().find_any(|(k, _)| **k == 4321), Some((&4321, &4321)));
+}
+
+#[test]
+fn par_iter_mut_test() {
+    let elems: Vec<u32> = (0..5_000).rev().collect();
+    let mut tree = common::create_tree(&elems);
+
+    tree.par_iter_mut().for_each(|(k, v)| *v = k * 2);
+    for (k, v) in tree.iter() {
+        assert_eq!(*v, k * 2);
+    }
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn into_par_iter_test() {
+    let elems: Vec<u32> = (0..5_000).map(|i| i * 31 % 5_000).collect();
+    let tree = common::create_tree(&elems);
+
+    let entries: Vec<(u32, u32)> = tree.into_par_iter().collect();
+    assert_eq!(entries, (0..5_000).map(|i| (i, i)).collect::<Vec<_>>());
+
+    let tree = common::create_tree(&elems);
+    let found = tree.into_par_iter().find_any(|(k, _)| *k == 77);
+    assert_eq!(found, Some((77, 77)));
+}
+
+#[test]
+fn from_par_iter_test() {
+    let tree: SplayTree<u32, u32> = (0..20_000u32)
+        .into_par_iter()
+        .map(|i| (i * 7919 % 20_000, i))
+        .collect();
+    assert_eq!(tree.len(), 20_000);
+    common::check_tree_structure(&tree);
+    let keys: Vec<u32> = tree.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, (0..20_000).collect::<Vec<_>>());
+
+    let tree: SplayTree<u32, u32> = (0..100u32).into_par_iter().map(|i| (i % 10, i)).collect();
+    assert_eq!(tree.len(), 10);
+    for (k, v) in tree.iter() {
+        assert_eq!(*v, 90 + k);
+    }
+}
+
+#[test]
+fn build_parallel_test() {
+    let entries: Vec<(u32, u32)> = (0..30_000u32).map(|i| (i * 7919 % 10_000, i)).collect();
+    let tree = SplayTree::build_parallel(entries);
+    assert_eq!(tree.len(), 10_000);
+    assert!(tree.height() <= 14);
+    common::check_tree_structure(&tree);
+    for (k, v) in tree.iter() {
+        assert_eq!(v % 10_000 * 7919 % 10_000, *k);
+        assert!(*v >= 20_000);
+    }
+
+    assert!(SplayTree::<u32, u32>::build_parallel(Vec::new()).is_empty());
+}
+
+#[test]
+fn par_extend_test() {
+    let mut tree = common::create_tree(&[5, 15, 25, 35]);
+    tree.par_extend((0..40u32).into_par_iter().filter(|i| i % 10 != 5).map(|i| (i, i + 100)));
+    tree.par_extend((0..10u32).into_par_iter().map(|i| (i * 10 + 5, 0)));
+    assert_eq!(tree.len(), 46);
+    common::check_tree_structure(&tree);
+
+    for i in 0..40 {
+        let expected = if i % 10 == 5 { 0 } else { i + 100 };
+        assert_eq!(tree.get(&i).map(|n| *n.value()), Some(expected));
+    }
+    common::check_tree_structure(&tree);
+}
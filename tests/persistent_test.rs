@@ -0,0 +1,80 @@
+use splay_tree::PersistentTree;
+
+#[test]
+fn insert_get_test() {
+    let elems = [13, 4, 3, 89, 12, 11, 56, 0, 7];
+    let mut tree = PersistentTree::new();
+    for i in elems {
+        assert_eq!(tree.insert(i, i * 2), None);
+    }
+    assert_eq!(tree.len(), elems.len());
+
+    for i in elems {
+        assert_eq!(tree.get(&i), Some(&(i * 2)));
+    }
+    assert!(tree.get(&100).is_none());
+    assert_eq!(tree.insert(4, 5), Some(8));
+    assert_eq!(tree.len(), elems.len());
+}
+
+#[test]
+fn versions_test() {
+    let mut tree = PersistentTree::new();
+    for i in 0..100u32 {
+        tree.insert(i, i);
+    }
+
+    let snapshot = tree.clone();
+    assert!(snapshot.ptr_eq(&tree));
+
+    for i in 0..50 {
+        assert_eq!(tree.remove(&i), Some(i));
+    }
+    tree.insert(1000, 1000);
+    assert!(!snapshot.ptr_eq(&tree));
+
+    assert_eq!(snapshot.len(), 100);
+    for i in 0..100 {
+        assert_eq!(snapshot.get(&i), Some(&i));
+    }
+    assert!(snapshot.get(&1000).is_none());
+
+    assert_eq!(tree.len(), 51);
+    for i in 0..50 {
+        assert!(tree.get(&i).is_none());
+    }
+}
+
+#[test]
+fn remove_test() {
+    let elems = [23, 45, 12, 90, 46, 89, 78, 91];
+    let mut tree: PersistentTree<u32, u32> = elems.iter().map(|&i| (i, i)).collect();
+    for (n, i) in elems.iter().enumerate() {
+        assert_eq!(tree.remove(i), Some(*i));
+        assert_eq!(tree.remove(i), None);
+        assert_eq!(tree.len(), elems.len() - n - 1);
+    }
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn iter_test() {
+    let mut tree = PersistentTree::new();
+    for i in (0..200u32).rev() {
+        tree.insert(i * 7 % 200, i);
+    }
+    let keys: Vec<u32> = tree.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, (0..200).collect::<Vec<_>>());
+    assert_eq!(tree.iter().len(), 200);
+}
+
+#[test]
+fn min_max_test() {
+    let mut tree = PersistentTree::new();
+    assert!(tree.get_min().is_none());
+    for i in [5u32, 1, 9, 3] {
+        tree.insert(i, i);
+    }
+    assert_eq!(tree.get_min(), Some((&1, &1)));
+    assert_eq!(tree.get_max(), Some((&9, &9)));
+}
@@ -0,0 +1,79 @@
+use std::thread;
+
+use splay_tree::{Entry, NodePool, SplayTree};
+
+mod common;
+
+#[test]
+fn shared_pool_test() {
+    let pool: NodePool<u32, String> = NodePool::with_capacity(8);
+    assert_eq!(pool.len(), 8);
+
+    let mut first = SplayTree::with_pool(pool.clone());
+    let mut second = SplayTree::with_pool(pool.clone());
+    for i in 0..5 {
+        first.insert(i, i.to_string());
+    }
+    for i in 0..5 {
+        second.insert(i, i.to_string());
+    }
+    // The pool runs out after 8 nodes, the rest are allocated.
+    assert!(pool.is_empty());
+    common::check_tree_structure(&first);
+    assert!(first.pool().is_some());
+
+    drop(first);
+    assert_eq!(pool.len(), 5);
+
+    // Nodes of removed entries go back to the pool unless they are reserved.
+    second.remove_range(..3);
+    assert_eq!(pool.len(), 8);
+    second.reserve(2);
+    assert_eq!(pool.len(), 8);
+    second.shrink_to_fit();
+    assert_eq!(pool.len(), 10);
+    assert!(second.iter().map(|(k, _)| *k).eq(3..5));
+
+    pool.shrink_to_fit();
+    assert!(pool.is_empty());
+    drop(second);
+    assert_eq!(pool.len(), 2);
+}
+
+#[test]
+fn removed_keys_test() {
+    let pool: NodePool<u32, u32> = NodePool::new();
+    let mut tree = SplayTree::with_pool(pool.clone());
+    for i in 0..10 {
+        tree.insert(i, i);
+    }
+
+    // Nodes of entries removed by key or through an entry go back to the pool too.
+    assert_eq!(tree.remove_keys(&[7, 2, 7, 20]), vec![(2, 2), (7, 7)]);
+    assert_eq!(pool.len(), 2);
+    if let Entry::Occupied(entry) = tree.entry(5) {
+        assert_eq!(entry.remove_entry(), (5, 5));
+    }
+    assert_eq!(pool.len(), 3);
+    common::check_tree_structure(&tree);
+    assert!(tree.iter().map(|(k, _)| *k).eq([0, 1, 3, 4, 6, 8, 9]));
+}
+
+#[test]
+fn threads_test() {
+    let pool: NodePool<u32, u32> = NodePool::with_capacity(400);
+    let handles: Vec<_> = (0..4).map(|t| {
+        let pool = pool.clone();
+        thread::spawn(move || {
+            let mut tree = SplayTree::with_pool(pool);
+            for i in 0..100 {
+                tree.insert(i, t);
+            }
+            assert_eq!(tree.len(), 100);
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(pool.len(), 400);
+}
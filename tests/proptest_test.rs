@@ -0,0 +1,38 @@
+#![cfg(feature = "proptest")]
+
+use std::collections::BTreeMap;
+
+use proptest::prelude::*;
+use splay_tree::{operations_strategy, splay_tree_strategy, SplayTree, TreeOp};
+
+proptest! {
+    #[test]
+    fn splay_tree_strategy_test(tree in splay_tree_strategy(0..1000u32, any::<u8>(), 0..50)) {
+        prop_assert!(tree.len() < 50);
+        let keys: Vec<u32> = tree.iter().map(|(k, _)| *k).collect();
+        prop_assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn operations_test(ops in operations_strategy(0..20u32, any::<u8>(), 0..100)) {
+        let mut tree = SplayTree::new();
+        let mut model = BTreeMap::new();
+        for op in ops {
+            match op {
+                TreeOp::Insert(key, value) => {
+                    tree.insert(key, value);
+                    model.insert(key, value);
+                },
+                TreeOp::Remove(key) => {
+                    let removed = tree.remove(&key).map(|node| *node.value());
+                    prop_assert_eq!(removed, model.remove(&key));
+                },
+                TreeOp::Get(key) => {
+                    let value = tree.get(&key).map(|node| *node.value());
+                    prop_assert_eq!(value, model.get(&key).copied());
+                },
+            }
+        }
+        prop_assert_eq!(BTreeMap::from(tree), model);
+    }
+}
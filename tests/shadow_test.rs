@@ -0,0 +1,40 @@
+#![cfg(feature = "shadow")]
+
+use splay_tree::{ShadowTree, SplayTree};
+
+#[test]
+fn mirrored_ops_test() {
+    let mut tree = ShadowTree::new();
+    for i in 0..50u32 {
+        let key = (i * 17) % 23;
+        tree.insert(key, i);
+    }
+    assert_eq!(tree.len(), 23);
+    assert_eq!(tree.get(&5).map(|node| *node.value()), Some(49));
+    assert!(!tree.contains_key(&30));
+    assert_eq!(tree.update(&5, |v| { *v += 1; *v }), Some(50));
+    assert_eq!(tree.update(&30, |v| *v), None);
+    assert_eq!(tree.remove(&5).map(|node| node.into_key_value()), Some((5, 50)));
+    assert!(tree.remove(&5).is_none());
+    assert_eq!(tree.remove_range(10..15), 5);
+    tree.check();
+
+    let tree = tree.into_tree();
+    assert_eq!(tree.len(), 17);
+    let mut tree = ShadowTree::from(tree);
+    tree.clear();
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn from_tree_test() {
+    let mut inner = SplayTree::new();
+    inner.insert("b".to_string(), 2);
+    inner.insert("a".to_string(), 1);
+    let mut tree = ShadowTree::from(inner);
+    assert_eq!(tree.get("a").map(|node| *node.value()), Some(1));
+    assert!(tree.contains_key("b"));
+    assert_eq!(tree.remove_range(.."b".to_string()), 1);
+    assert!(tree.iter().map(|(k, v)| (k.as_str(), *v)).eq([("b", 2)]));
+    assert_eq!(tree.as_tree().len(), 1);
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::thread;
+
+use splay_tree::{ShardedSplayMap, SplayTree};
+
+mod common;
+
+#[test]
+fn insert_get_remove_test() {
+    let map = ShardedSplayMap::new(4);
+    for i in 0..100u32 {
+        assert_eq!(map.insert(i, i * 2), None);
+    }
+    assert_eq!(map.len(), 100);
+    assert_eq!(map.insert(7, 0), Some(14));
+
+    for i in 8..100u32 {
+        assert_eq!(map.get_with(&i, |v| *v), Some(i * 2));
+    }
+    assert_eq!(map.get_mut_with(&8, |v| { *v += 1; *v }), Some(17));
+    assert_eq!(map.remove(&8), Some(17));
+    assert!(!map.contains_key(&8));
+    assert_eq!(map.len(), 99);
+}
+
+#[test]
+fn sorted_iter_test() {
+    let map = ShardedSplayMap::new(8);
+    for i in (0..500u32).rev() {
+        map.insert(i * 7 % 500, i);
+    }
+    let keys: Vec<u32> = map.iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, (0..500).collect::<Vec<_>>());
+
+    let keys: Vec<u32> = map.into_sorted_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, (0..500).collect::<Vec<_>>());
+}
+
+#[test]
+fn concurrent_test() {
+    let map = Arc::new(ShardedSplayMap::new(16));
+    let handles: Vec<_> = (0..4u32).map(|t| {
+        let map = map.clone();
+        thread::spawn(move || {
+            for i in (t * 1000)..(t * 1000 + 1000) {
+                map.insert(i, i);
+                assert_eq!(map.get_with(&i, |v| *v), Some(i));
+            }
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let map = Arc::try_unwrap(map).ok().unwrap();
+    assert_eq!(map.shard_count(), 16);
+    let tree = SplayTree::from(map);
+    assert_eq!(tree.len(), 4000);
+    common::check_tree_structure(&tree);
+}
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use splay_tree::{Natural, SmallSplayMap};
+
+#[test]
+fn promote_test() {
+    let mut map: SmallSplayMap<u32, u32> = SmallSplayMap::new();
+    let mut expected = BTreeMap::new();
+    for i in 0..16u32 {
+        let key = (i * 7) % 16;
+        assert_eq!(map.insert(key, i), expected.insert(key, i));
+        assert!(map.is_inline());
+    }
+    assert_eq!(map.insert(3, 100), expected.insert(3, 100));
+    assert!(map.is_inline());
+    assert!(map.iter().eq(expected.iter()));
+
+    assert_eq!(map.insert(20, 20), None);
+    expected.insert(20, 20);
+    assert!(!map.is_inline());
+    assert_eq!(map.len(), 17);
+    assert!(map.iter().eq(expected.iter()));
+    assert!(map.iter().rev().eq(expected.iter().rev()));
+    for key in 0..21u32 {
+        assert_eq!(map.get(&key), expected.get(&key));
+    }
+
+    for key in 0..21u32 {
+        assert_eq!(map.remove(&key), expected.remove(&key));
+    }
+    assert!(map.is_empty());
+    assert!(map.is_inline());
+}
+
+#[test]
+fn inline_ops_test() {
+    let mut map: SmallSplayMap<String, usize, Natural, 4> = ["d", "b", "a"].iter()
+        .map(|s| (s.to_string(), s.len()))
+        .collect();
+    assert!(map.contains_key("b"));
+    if let Some(value) = map.get_mut("a") {
+        *value = 10;
+    }
+    assert_eq!(map.remove("b"), Some(1));
+    assert_eq!(map.remove("b"), None);
+    assert!(map.iter().map(|(k, v)| (k.as_str(), *v)).eq([("a", 10), ("d", 1)]));
+    assert_eq!(map.iter().len(), 2);
+
+    map.extend(["e", "f", "g"].iter().map(|s| (s.to_string(), 0)));
+    assert!(!map.is_inline());
+    map.clear();
+    assert!(map.is_empty());
+    assert!(map.is_inline());
+    assert!(map.get("a").is_none());
+}
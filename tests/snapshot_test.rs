@@ -0,0 +1,50 @@
+#![cfg(feature = "snapshots")]
+
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+
+use splay_tree::SplayTree;
+
+fn recorded<K: Display + Ord, V>(tree: &mut SplayTree<K, V>) -> Arc<Mutex<Vec<String>>> {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let sink = lines.clone();
+    tree.set_snapshot_sink(move |line| sink.lock().unwrap().push(line.to_owned()));
+    lines
+}
+
+#[test]
+fn operations_test() {
+    let mut tree = SplayTree::new();
+    let lines = recorded(&mut tree);
+    tree.insert(2, 2);
+    tree.insert(1, 1);
+    tree.get(&2);
+    tree.remove(&1);
+    tree.remove(&2);
+
+    assert_eq!(*lines.lock().unwrap(), [
+        r#"{"op":"insert","key":"2","depth":0,"root":"2","len":1,"tree":[null,"2",null]}"#,
+        r#"{"op":"splay","key":"1","depth":1,"root":"1","len":2,"tree":[null,"1",[null,"2",null]]}"#,
+        r#"{"op":"insert","key":"1","depth":1,"root":"1","len":2,"tree":[null,"1",[null,"2",null]]}"#,
+        r#"{"op":"splay","key":"2","depth":1,"root":"2","len":2,"tree":[[null,"1",null],"2",null]}"#,
+        r#"{"op":"splay","key":"1","depth":1,"root":"1","len":2,"tree":[null,"1",[null,"2",null]]}"#,
+        r#"{"op":"remove","key":"1","depth":0,"root":"2","len":1,"tree":[null,"2",null]}"#,
+        r#"{"op":"remove","key":"2","depth":0,"root":null,"len":0,"tree":null}"#,
+    ]);
+
+    assert!(tree.clear_snapshot_sink());
+    tree.insert(3, 3);
+    assert_eq!(lines.lock().unwrap().len(), 7);
+    assert!(!tree.clear_snapshot_sink());
+}
+
+#[test]
+fn escaped_keys_test() {
+    let mut tree = SplayTree::new();
+    let lines = recorded(&mut tree);
+    tree.insert("a\"b\n".to_owned(), ());
+
+    assert_eq!(*lines.lock().unwrap(), [
+        r#"{"op":"insert","key":"a\"b\n","depth":0,"root":"a\"b\n","len":1,"tree":[null,"a\"b\n",null]}"#,
+    ]);
+}
@@ -0,0 +1,165 @@
+use std::cmp::Ordering;
+
+use splay_tree::{Compare, Entry, Equivalent, SplayTree, SplayTreeBy};
+
+#[test]
+fn descending_test() {
+    let mut tree = SplayTreeBy::with_comparator(|a: &u32, b: &u32| b.cmp(a));
+    for i in [5, 1, 9, 3, 7] {
+        tree.insert(i, i * 10);
+    }
+
+    let keys: Vec<u32> = tree.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, [9, 7, 5, 3, 1]);
+    assert_eq!(tree.get_min().map(|node| *node.key()), Some(9));
+    assert_eq!(tree.get_max().map(|node| *node.key()), Some(1));
+    assert_eq!(tree.get(&3).map(|node| *node.value()), Some(30));
+    assert!(tree.remove(&9).is_some());
+    assert_eq!(tree.len(), 4);
+}
+
+#[test]
+fn non_ord_keys_test() {
+    let mut tree = SplayTreeBy::with_comparator(f64::total_cmp);
+    for x in [2.5, -1.0, 0.0, 10.25, -0.0] {
+        tree.insert(x, ());
+    }
+
+    let keys: Vec<f64> = tree.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, [-1.0, -0.0, 0.0, 2.5, 10.25]);
+    assert!(tree.contains_key(&2.5));
+    assert!(!tree.contains_key(&2.0));
+}
+
+#[test]
+fn case_insensitive_test() {
+    #[derive(Default)]
+    struct CaseInsensitive;
+
+    impl Compare<String> for CaseInsensitive {
+        fn compare(&self, a: &String, b: &String) -> Ordering {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        }
+    }
+
+    let mut tree: SplayTreeBy<String, u32, CaseInsensitive> = SplayTreeBy::new();
+    tree.insert("Apple".to_string(), 1);
+    tree.insert("banana".to_string(), 2);
+
+    match tree.entry("APPLE".to_string()) {
+        Entry::Occupied(_) => {},
+        Entry::Vacant(_) => panic!("keys should be compared case-insensitively"),
+    }
+    *tree.entry("BANANA".to_string()).or_insert(0).value_mut() += 10;
+    tree.entry("Cherry".to_string()).or_insert(3);
+
+    let entries: Vec<(&str, u32)> = tree.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    assert_eq!(entries, [("Apple", 1), ("banana", 12), ("Cherry", 3)]);
+}
+
+#[test]
+fn by_key_test() {
+    #[derive(Debug, PartialEq)]
+    struct Order {
+        id: u32,
+        price: u64,
+    }
+
+    let mut book = SplayTreeBy::by_key(|order: &Order| order.price);
+    book.insert(Order { id: 1, price: 105 }, 10);
+    book.insert(Order { id: 2, price: 99 }, 5);
+    book.insert(Order { id: 3, price: 120 }, 1);
+
+    let best = book.get_min().map(|node| node.key().id);
+    assert_eq!(best, Some(2));
+    let ids: Vec<u32> = book.iter().map(|(order, _)| order.id).collect();
+    assert_eq!(ids, [2, 1, 3]);
+
+    // Only the price is compared, so an order with the same price replaces the value.
+    book.insert(Order { id: 4, price: 105 }, 7);
+    assert_eq!(book.len(), 3);
+    assert_eq!(book.get(&Order { id: 0, price: 105 }).map(|node| *node.value()), Some(7));
+}
+
+#[test]
+fn total_order_test() {
+    let mut tree = SplayTreeBy::with_total_order();
+    for x in [1.5f64, f64::NAN, -0.0, 0.0, f64::NEG_INFINITY, -2.0] {
+        tree.insert(x, x.to_string());
+    }
+    assert_eq!(tree.len(), 6);
+    assert!(tree.get(&f64::NAN).is_some());
+    assert_eq!(tree.get_min().map(|node| *node.key()), Some(f64::NEG_INFINITY));
+    assert!(tree.get_max().unwrap().key().is_nan());
+
+    let keys: Vec<String> = tree.iter().map(|(_, v)| v.clone()).collect();
+    assert_eq!(keys, ["-inf", "-2", "-0", "0", "1.5", "NaN"]);
+}
+
+#[test]
+fn total_ord_wrapper_test() {
+    use splay_tree::{SplayTree, TotalOrd};
+
+    let mut tree = SplayTree::new();
+    tree.insert(TotalOrd(2.5f32), 'b');
+    tree.insert(TotalOrd(f32::NAN), 'n');
+    tree.insert(TotalOrd::from(-1.0f32), 'a');
+
+    let values: Vec<char> = tree.iter().map(|(_, v)| *v).collect();
+    assert_eq!(values, ['a', 'b', 'n']);
+    assert!(tree.contains_key(&TotalOrd(f32::NAN)));
+    assert_ne!(TotalOrd(0.0f32), TotalOrd(-0.0f32));
+}
+
+struct Query<'a>(u64, &'a str);
+
+impl Equivalent<(u64, String)> for Query<'_> {
+    fn compare(&self, key: &(u64, String)) -> Ordering {
+        self.0.cmp(&key.0).then_with(|| self.1.cmp(key.1.as_str()))
+    }
+}
+
+#[test]
+fn equivalent_query_test() {
+    let mut tree = SplayTree::new();
+    for (id, name) in [(2, "b"), (1, "z"), (2, "a"), (3, "c")] {
+        tree.insert((id, name.to_string()), id * 10);
+    }
+
+    assert_eq!(tree.get_equiv(&Query(2, "b")).map(|node| *node.value()), Some(20));
+    assert!(tree.contains_key_equiv(&Query(1, "z")));
+    assert!(!tree.contains_key_equiv(&Query(1, "a")));
+    *tree.get_equiv_mut(&Query(3, "c")).unwrap().value_mut() = 33;
+    assert_eq!(tree.remove_equiv(&Query(3, "c")).map(|node| node.into_key_value().1), Some(33));
+    assert_eq!(tree.len(), 3);
+
+    // Borrowed forms of the key are queries too.
+    let mut strings = SplayTree::new();
+    strings.insert("key".to_string(), 1);
+    assert!(strings.contains_key_equiv("key"));
+}
+
+#[test]
+fn bound_by_test() {
+    let mut tree = SplayTree::new();
+    for (time, id) in [(10, 3), (20, 1), (20, 7), (30, 2), (40, 5)] {
+        tree.insert((time, id), ());
+    }
+    let by_time = |time: u32| move |key: &(u32, u32)| time.cmp(&key.0);
+
+    assert_eq!(tree.lower_bound_by(by_time(20)).map(|node| *node.key()), Some((20, 1)));
+    assert_eq!(tree.root().map(|node| *node.key()), Some((20, 1)));
+    assert_eq!(tree.upper_bound_by(by_time(20)).map(|node| *node.key()), Some((30, 2)));
+    assert_eq!(tree.lower_bound_by(by_time(25)).map(|node| *node.key()), Some((30, 2)));
+    assert_eq!(tree.upper_bound_by(by_time(5)).map(|node| *node.key()), Some((10, 3)));
+    assert!(tree.lower_bound_by(by_time(41)).is_none());
+    assert!(tree.upper_bound_by(by_time(40)).is_none());
+
+    let cursor = tree.lower_bound_by(by_time(20)).unwrap();
+    let same_time: Vec<u32> = std::iter::successors(Some(cursor), |node| node.next())
+        .take_while(|node| node.key().0 == 20)
+        .map(|node| node.key().1)
+        .collect();
+    assert_eq!(same_time, [1, 7]);
+    assert!(SplayTree::<u32, ()>::new().lower_bound_by(|_| Ordering::Less).is_none());
+}
@@ -1,6 +1,8 @@
 mod common;
 
-use splay_tree::SplayTree;
+use std::collections::{BTreeMap, HashMap};
+
+use splay_tree::{Change, LendingIterator, Node, Op, SplayTree, SuccinctError, SuccinctShape, TreeBuilder};
 
 #[test]
 fn test_get() {
@@ -108,3 +110,1360 @@ fn contains_key_test() {
         assert!(!tree.contains_key(&i));
     }
 }
+
+#[test]
+fn miss_splays_test() {
+    let mut tree = SplayTree::new();
+    for i in 0..500u32 {
+        tree.insert(i * 2, i);
+    }
+    assert_eq!(tree.height(), 500);
+
+    assert!(!tree.contains_key(&501));
+    assert!(matches!(tree.root().map(|node| *node.key()), Some(500 | 502)));
+    assert!(tree.height() < 500);
+    assert!(tree.get_mut(&0).is_some());
+    assert!(!tree.contains_key(&1));
+    assert!(matches!(tree.root().map(|node| *node.key()), Some(0 | 2)));
+
+    for key in [1, 501, 999, 333] {
+        *tree.entry(key).or_insert(0).value_mut() += 1;
+        assert_eq!(tree.root().map(|node| *node.key()), Some(key));
+    }
+    assert_eq!(tree.len(), 504);
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn into_iter_test() {
+    let elems = [56, 12, 90, 1, 45, 78, 33, 2];
+    let tree = common::create_tree(&elems);
+    let mut sorted = elems.to_vec();
+    sorted.sort();
+
+    let entries: Vec<(u32, u32)> = tree.into_iter().collect();
+    assert_eq!(entries, sorted.iter().map(|&i| (i, i)).collect::<Vec<_>>());
+}
+
+#[test]
+fn into_iter_double_ended_test() {
+    let elems = [7, 3, 11, 1, 5, 9, 13, 0, 2, 4];
+    let tree = common::create_tree(&elems);
+    let mut iter = tree.into_iter();
+    assert_eq!(iter.len(), elems.len());
+    assert_eq!(iter.next(), Some((0, 0)));
+    assert_eq!(iter.next_back(), Some((13, 13)));
+    assert_eq!(iter.next_back(), Some((11, 11)));
+    assert_eq!(iter.next(), Some((1, 1)));
+
+    let rest: Vec<u32> = iter.rev().map(|(k, _)| k).collect();
+    assert_eq!(rest, vec![9, 7, 5, 4, 3, 2]);
+}
+
+#[test]
+fn into_iter_partial_drop_test() {
+    let elems: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+    let mut tree = SplayTree::new();
+    for s in elems.iter() {
+        tree.insert(s.clone(), s.clone());
+    }
+    let mut iter = tree.into_iter();
+    iter.next();
+    iter.next_back();
+    assert_eq!(iter.len(), 18);
+}
+
+#[test]
+fn send_sync_test() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+    assert_send::<SplayTree<String, Vec<u8>>>();
+    assert_sync::<SplayTree<String, Vec<u8>>>();
+    assert_send::<splay_tree::IntoIter<String, u32>>();
+    assert_sync::<splay_tree::IntoIter<String, u32>>();
+
+    let tree = std::sync::Mutex::new(common::create_tree(&[5, 3, 8]));
+    std::thread::scope(|s| {
+        for i in 10..14u32 {
+            let tree = &tree;
+            s.spawn(move || {
+                tree.lock().unwrap().insert(i, i);
+            });
+        }
+    });
+    let mut tree = tree.into_inner().unwrap();
+    assert_eq!(tree.len(), 7);
+    assert!(tree.get(&12).is_some());
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn iter_test() {
+    let elems = [56, 12, 90, 1, 45, 78, 33, 2];
+    let tree = common::create_tree(&elems);
+    let mut sorted = elems.to_vec();
+    sorted.sort();
+
+    let keys: Vec<u32> = tree.iter().map(|(k, _)| *k).collect();
+    assert_eq!(keys, sorted);
+    let keys: Vec<u32> = tree.iter().rev().map(|(k, _)| *k).collect();
+    assert_eq!(keys, sorted.iter().rev().cloned().collect::<Vec<_>>());
+
+    let mut iter = tree.iter();
+    assert_eq!(iter.len(), 8);
+    assert_eq!(iter.next(), Some((&1, &1)));
+    assert_eq!(iter.next_back(), Some((&90, &90)));
+    assert_eq!(iter.len(), 6);
+    assert_eq!((&tree).into_iter().count(), 8);
+}
+
+#[test]
+fn drop_frees_all_nodes_test() {
+    use std::rc::Rc;
+
+    let counter = Rc::new(());
+    let mut tree = SplayTree::new();
+    for i in [50u32, 20, 80, 10, 30, 70, 90, 60] {
+        tree.insert(i, counter.clone());
+    }
+    tree.get(&60);
+    assert_eq!(Rc::strong_count(&counter), 9);
+    drop(tree);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[test]
+#[cfg_attr(any(miri, feature = "validate_debug"), ignore)]
+fn drop_degenerate_tree_test() {
+    let mut tree = SplayTree::new();
+    for i in 0..500_000u32 {
+        tree.insert(i, i);
+    }
+    // Every insertion splays the maximum, so the tree is a single left spine.
+    assert!(tree.root().unwrap().right().is_none());
+    drop(tree);
+}
+
+#[test]
+fn panicking_cmp_test() {
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::panic::{self, AssertUnwindSafe};
+
+    thread_local! {
+        static POISONED: Cell<Option<u32>> = const { Cell::new(None) };
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Key(u32);
+
+    impl PartialOrd for Key {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Key {
+        fn cmp(&self, other: &Self) -> Ordering {
+            if let Some(poisoned) = POISONED.with(Cell::get) {
+                assert!(self.0 != poisoned && other.0 != poisoned, "poisoned key");
+            }
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let mut tree = SplayTree::new();
+    for i in (0..100u32).map(|i| i * 37 % 100) {
+        tree.insert(Key(i), i);
+    }
+
+    // Every search starts by comparing with the root.
+    tree.get(&Key(50));
+    POISONED.with(|p| p.set(Some(50)));
+    let mut assert_panics = |op: &dyn Fn(&mut SplayTree<Key, u32>)| {
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| op(&mut tree))).is_err());
+    };
+    assert_panics(&|tree| { tree.get(&Key(50)); });
+    assert_panics(&|tree| { tree.get(&Key(10)); });
+    assert_panics(&|tree| { tree.insert(Key(50), 0); });
+    assert_panics(&|tree| { tree.insert(Key(150), 0); });
+    assert_panics(&|tree| { tree.remove(&Key(50)); });
+    assert_panics(&|tree| { tree.entry(Key(50)).or_insert(0); });
+    assert_panics(&|tree| { tree.replace_key(&Key(10), Key(50)).ok(); });
+    POISONED.with(|p| p.set(None));
+
+    assert_eq!(tree.len(), 100);
+    common::check_tree_structure(&tree);
+    let keys: Vec<u32> = tree.iter().map(|(k, _)| k.0).collect();
+    assert_eq!(keys, (0..100).collect::<Vec<_>>());
+    assert_eq!(tree.remove(&Key(50)).map(|node| *node.value()), Some(50));
+}
+
+#[cfg(feature = "recursive_debug")]
+#[test]
+fn debug_print_test() {
+    let mut tree = SplayTree::new();
+    for i in [50u32, 20, 80, 10, 30] {
+        tree.insert(i, i);
+    }
+    tree.get(&20);
+    let expected = "\
+20(20)
+├── 10(10)
+└── 30(30)
+    └── 50(50)
+        └── 80(80)
+";
+    assert_eq!(format!("{:?}", tree.root().unwrap()), expected);
+}
+
+#[test]
+fn shape_test() {
+    let mut tree: SplayTree<u32, u32> = SplayTree::new();
+    assert_eq!(tree.height(), 0);
+    assert_eq!(tree.internal_path_length(), 0);
+    assert_eq!(tree.average_depth(), None);
+    assert_eq!(tree.depth_of(&1), None);
+
+    for i in 0..8 {
+        tree.insert(i, i);
+    }
+    // Inserting increasing keys leaves a left spine.
+    assert_eq!(tree.height(), 8);
+    assert_eq!(tree.depth_of(&7), Some(0));
+    assert_eq!(tree.depth_of(&0), Some(7));
+    assert_eq!(tree.depth_of(&8), None);
+    assert_eq!(tree.internal_path_length(), 28);
+    assert_eq!(tree.average_depth(), Some(3.5));
+
+    assert!(tree.get(&0).is_some());
+    assert_eq!(tree.depth_of(&0), Some(0));
+    assert!(tree.height() < 8);
+    assert!(tree.internal_path_length() < 28);
+}
+
+#[test]
+fn memory_usage_test() {
+    let node_size = std::mem::size_of::<splay_tree::Node<u32, u64>>();
+    let mut tree: SplayTree<u32, u64> = SplayTree::new();
+    assert_eq!(tree.memory_usage(), 0);
+
+    for i in 0..10 {
+        tree.insert(i, i as u64);
+    }
+    assert_eq!(tree.memory_usage(), 10 * node_size);
+    tree.remove(&3);
+    assert_eq!(tree.memory_usage(), 9 * node_size);
+
+    let frozen = tree.freeze();
+    assert!(frozen.memory_usage() >= 9 * std::mem::size_of::<(u32, u64)>());
+}
+
+#[test]
+fn capacity_test() {
+    let mut tree: SplayTree<u32, u64> = SplayTree::with_capacity(16);
+    assert_eq!(tree.capacity(), 16);
+    let reserved = tree.memory_usage();
+    assert!(reserved >= 16 * std::mem::size_of::<splay_tree::Node<u32, u64>>());
+
+    for i in 0..16 {
+        tree.insert(i, i as u64);
+    }
+    assert_eq!(tree.capacity(), 16);
+    assert_eq!(tree.memory_usage(), reserved);
+    common::check_tree_structure(&tree);
+
+    tree.insert(16, 16);
+    assert_eq!(tree.capacity(), 17);
+    tree.reserve(4);
+    assert_eq!(tree.capacity(), 21);
+    tree.reserve(2);
+    assert_eq!(tree.capacity(), 21);
+    assert!(tree.iter().map(|(k, _)| *k).eq(0..17));
+}
+
+#[test]
+fn shrink_to_fit_test() {
+    let node_size = std::mem::size_of::<splay_tree::Node<u32, String>>();
+    let mut tree: SplayTree<u32, String> = SplayTree::new();
+    for i in 0..20 {
+        tree.insert(i, i.to_string());
+    }
+    // Without a reservation, the nodes of removed entries are freed.
+    assert_eq!(tree.remove_range(5..15), 10);
+    assert_eq!(tree.capacity(), 10);
+    tree.clear();
+    assert_eq!(tree.capacity(), 0);
+    assert_eq!(tree.memory_usage(), 0);
+
+    // Up to the reserved number of nodes are kept for later insertions.
+    tree.reserve(5);
+    for i in 0..20 {
+        tree.insert(i, i.to_string());
+    }
+    assert_eq!(tree.capacity(), 20);
+    common::check_tree_structure(&tree);
+    assert_eq!(tree.remove_range(..10), 10);
+    assert_eq!(tree.capacity(), 15);
+    assert_eq!(tree.take(&10).as_deref(), Some("10"));
+    tree.clear();
+    assert_eq!(tree.capacity(), 5);
+    assert!(tree.memory_usage() >= 5 * node_size);
+
+    tree.shrink_to_fit();
+    assert_eq!(tree.capacity(), 0);
+    tree.insert(1, "1".to_string());
+    tree.clear();
+    assert_eq!(tree.capacity(), 0);
+}
+
+#[test]
+fn node_navigation_test() {
+    let elems = [31, 7, 54, 19, 2, 88, 40, 63, 11];
+    let mut tree = common::create_tree(&elems);
+    let mut sorted = elems;
+    sorted.sort();
+
+    let mut keys = vec![];
+    let mut node = tree.get_min();
+    while let Some(n) = node {
+        keys.push(*n.key());
+        node = n.next();
+    }
+    assert_eq!(keys, sorted);
+
+    keys.clear();
+    let mut node = tree.get_max();
+    while let Some(n) = node {
+        keys.push(*n.key());
+        node = n.prev();
+    }
+    sorted.reverse();
+    assert_eq!(keys, sorted);
+
+    let mut node = tree.get_mut(&40).unwrap();
+    *node.next_key_value_mut().unwrap().1 = 0;
+    *node.prev_key_value_mut().unwrap().1 = 0;
+    assert_eq!(node.parent_key_value_mut().map(|(key, _)| *key), node.parent().map(|n| *n.key()));
+    assert_eq!(tree.get(&54).map(|n| *n.value()), Some(0));
+    assert_eq!(tree.get(&31).map(|n| *n.value()), Some(0));
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn raw_parts_test() {
+    let mut tree = common::create_tree(&[5, 1, 9, 3, 7]);
+    tree.get(&3);
+    let (root, length, cmp) = tree.into_raw_parts();
+    assert_eq!(length, 5);
+    assert_eq!(unsafe { (*root).key() }, &3);
+
+    let mut tree = unsafe { SplayTree::from_raw_parts(root, length, cmp) };
+    common::check_tree_structure(&tree);
+    assert_eq!(tree.root().map(|node| *node.key()), Some(3));
+    assert!(tree.get(&9).is_some());
+    assert!(tree.iter().map(|(k, _)| *k).eq([1, 3, 5, 7, 9]));
+
+    let (root, length, cmp) = SplayTree::<u32, u32>::new().into_raw_parts();
+    assert!(root.is_null());
+    let tree = unsafe { SplayTree::from_raw_parts(root, length, cmp) };
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn get_deferred_test() {
+    let mut tree = common::create_tree(&[5, 1, 9, 3, 7]);
+    let root = tree.root().map(|node| *node.key());
+
+    let guard = tree.get_deferred(&3).unwrap();
+    assert_eq!(guard.key(), &3);
+    assert!(!guard.is_used());
+    drop(guard);
+    assert_eq!(tree.root().map(|node| *node.key()), root);
+    assert!(tree.get_deferred(&4).is_none());
+
+    {
+        let guard = tree.get_deferred(&3).unwrap();
+        assert_eq!(*guard, 3);
+        assert!(guard.is_used());
+    }
+    assert_eq!(tree.root().map(|node| *node.key()), Some(3));
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn succinct_test() {
+    let elems: Vec<u32> = (0..100).map(|i| i * 37 % 100).collect();
+    let mut tree = common::create_tree(&elems);
+    tree.get(&42);
+    tree.get(&7);
+    let (shape, entries) = tree.encode_succinct();
+    assert_eq!(shape.len(), 100);
+    assert_eq!(shape.as_bytes().len(), 25);
+    let entries: Vec<(u32, u32)> = entries.map(|(k, v)| (*k, *v)).collect();
+
+    let shape = SuccinctShape::from_bytes(shape.as_bytes().to_vec(), shape.len());
+    let decoded: SplayTree<u32, u32> = SplayTree::decode_succinct(&shape, entries.clone()).unwrap();
+    common::check_tree_structure(&decoded);
+    assert!(decoded.pre_order().eq(tree.pre_order()));
+
+    let result = SplayTree::decode_succinct(&shape, entries[1..].iter().copied());
+    assert_eq!(result.err(), Some(SuccinctError::LengthMismatch));
+    let mut swapped = entries.clone();
+    swapped.swap(3, 4);
+    assert_eq!(SplayTree::decode_succinct(&shape, swapped).err(), Some(SuccinctError::Unordered(4)));
+    let truncated = SuccinctShape::from_bytes(shape.as_bytes()[..24].to_vec(), 100);
+    assert_eq!(SplayTree::decode_succinct(&truncated, entries).err(), Some(SuccinctError::Unbalanced(192)));
+    // A closing parenthesis first.
+    let unbalanced = SuccinctShape::from_bytes(vec![0b10], 1);
+    assert_eq!(SplayTree::decode_succinct(&unbalanced, [(1, 1)]).err(), Some(SuccinctError::Unbalanced(0)));
+
+    let (shape, _) = SplayTree::<u32, u32>::new().encode_succinct();
+    assert!(shape.is_empty());
+    let empty: SplayTree<u32, u32> = SplayTree::decode_succinct(&shape, []).unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn split_at_rank_test() {
+    let elems: Vec<u32> = (0..40).map(|i| i * 13 % 40).collect();
+    let mut tree = common::create_tree(&elems);
+    let rest = tree.split_at_rank(15);
+    common::check_tree_structure(&tree);
+    common::check_tree_structure(&rest);
+    assert!(tree.iter().map(|(k, _)| *k).eq(0..15));
+    assert!(rest.iter().map(|(k, _)| *k).eq(15..40));
+
+    let empty = tree.split_at_rank(15);
+    assert!(empty.is_empty());
+    assert_eq!(tree.len(), 15);
+    let all = tree.split_at_rank(0);
+    assert!(tree.is_empty());
+    assert_eq!(all.len(), 15);
+}
+
+#[test]
+fn partition_test() {
+    let elems: Vec<u32> = (0..50).map(|i| i * 7 % 50).collect();
+    let tree = common::create_tree(&elems);
+    let (even, odd) = tree.partition(|k, _| k % 2 == 0);
+    common::check_tree_structure(&even);
+    common::check_tree_structure(&odd);
+    assert!(even.iter().map(|(k, _)| *k).eq((0..50).step_by(2)));
+    assert!(odd.iter().map(|(k, _)| *k).eq((1..50).step_by(2)));
+    assert!(even.height() <= 5);
+
+    let (all, none) = odd.partition(|_, _| true);
+    assert_eq!(all.len(), 25);
+    assert!(none.is_empty());
+    let (none, all) = all.partition(|_, _| false);
+    assert!(none.is_empty());
+    assert_eq!(all.len(), 25);
+}
+
+#[test]
+fn adjacent_pairs_test() {
+    let tree = common::create_tree(&[10, 1, 4, 9]);
+    let gaps: Vec<u32> = tree.adjacent_pairs().map(|((a, _), (b, _))| b - a).collect();
+    assert_eq!(gaps, [3, 5, 1]);
+    assert_eq!(tree.adjacent_pairs().len(), 3);
+
+    let single = common::create_tree(&[1]);
+    assert_eq!(single.adjacent_pairs().len(), 0);
+    assert!(single.adjacent_pairs().next().is_none());
+    assert!(SplayTree::<u32, u32>::new().adjacent_pairs().next().is_none());
+}
+
+#[test]
+fn lending_iter_mut_test() {
+    let mut tree = common::create_tree(&[5, 1, 9, 3, 7]);
+    let mut iter = tree.lending_iter_mut();
+    assert_eq!(iter.len(), 5);
+    let mut keys = vec![];
+    while let Some((key, value)) = iter.next() {
+        keys.push(*key);
+        *value *= 10;
+    }
+    assert!(iter.is_empty());
+    assert!(iter.next().is_none());
+    assert_eq!(keys, [1, 3, 5, 7, 9]);
+    assert!(tree.iter().map(|(k, v)| (*k, *v)).eq([(1, 10), (3, 30), (5, 50), (7, 70), (9, 90)]));
+    assert!(SplayTree::<u32, u32>::new().lending_iter_mut().next().is_none());
+}
+
+#[test]
+fn detach_subtree_test() {
+    let elems = [44, 12, 90, 3, 27, 61, 75, 8, 50, 33];
+    for at in [0, 3, 27, 30, 50, 90, 91] {
+        let mut tree = common::create_tree(&elems);
+        let detached = tree.detach_subtree(&at);
+
+        let mut sorted = elems;
+        sorted.sort();
+        let (below, above) = sorted.split_at(sorted.partition_point(|&key| key < at));
+        assert_eq!(tree.iter().map(|(key, _)| *key).collect::<Vec<_>>(), below);
+        assert_eq!(detached.iter().map(|(key, _)| *key).collect::<Vec<_>>(), above);
+        common::check_tree_structure(&tree);
+        common::check_tree_structure(&detached);
+    }
+}
+
+#[test]
+fn graft_test() {
+    let mut tree = common::create_tree(&[10, 20, 30]);
+    let id = tree.id_of(&20).unwrap();
+
+    assert!(tree.graft(common::create_tree(&[40, 50])).is_ok());
+    assert!(tree.graft(common::create_tree(&[1, 5])).is_ok());
+    assert!(tree.graft(SplayTree::new()).is_ok());
+    assert_eq!(tree.len(), 7);
+    assert_eq!(tree.get_by_id(id).map(|node| *node.key()), Some(20));
+    common::check_tree_structure(&tree);
+
+    let overlapping = common::create_tree(&[25, 60]);
+    let overlapping = tree.graft(overlapping).unwrap_err();
+    assert_eq!(overlapping.len(), 2);
+    assert_eq!(tree.len(), 7);
+
+    let mut empty = SplayTree::new();
+    assert!(empty.graft(overlapping).is_ok());
+    assert_eq!(empty.iter().map(|(key, _)| *key).collect::<Vec<_>>(), [25, 60]);
+
+    let detached = tree.detach_subtree(&30);
+    assert!(tree.graft(detached).is_ok());
+    let keys: Vec<_> = tree.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, [1, 5, 10, 20, 30, 40, 50]);
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn key_value_test() {
+    let mut tree = SplayTree::new();
+    tree.insert(1, "one".to_string());
+    tree.insert(2, "two".to_string());
+
+    assert_eq!(tree.get(&1).unwrap().key_value(), (&1, &"one".to_string()));
+    let (key, value) = tree.get_mut(&2).unwrap().into_key_value_mut();
+    value.push_str(&key.to_string());
+
+    let (key, value) = tree.remove(&2).unwrap().into_key_value();
+    assert_eq!((key, value.as_str()), (2, "two2"));
+    assert_eq!(tree.len(), 1);
+}
+
+#[test]
+fn replace_key_test() {
+    let elems = [40, 20, 60, 10, 30, 50, 70];
+    let mut tree = common::create_tree(&elems);
+    let id = tree.id_of(&30).unwrap();
+
+    assert_eq!(tree.replace_key(&30, 35), Ok(30));
+    assert_eq!(tree.replace_key(&35, 65), Ok(35));
+    assert_eq!(tree.get_by_id(id).map(|node| node.key_value()), Some((&65, &30)));
+    common::check_tree_structure(&tree);
+
+    assert_eq!(tree.replace_key(&65, 10), Err(10));
+    assert_eq!(tree.replace_key(&99, 1), Err(1));
+    assert_eq!(tree.len(), elems.len());
+
+    for old in [10, 20, 40, 50, 60, 65, 70] {
+        for new in (0..80).step_by(5) {
+            let mut tree = common::create_tree(&elems);
+            tree.replace_key(&30, 65).unwrap();
+            let expected: Vec<_> = tree.iter().map(|(key, _)| *key).collect();
+            let result = tree.replace_key(&old, new);
+            if new != old && expected.contains(&new) {
+                assert_eq!(result, Err(new));
+                continue;
+            }
+
+            assert_eq!(result, Ok(old));
+            let mut expected: Vec<_> = expected.into_iter().map(|key| if key == old { new } else { key }).collect();
+            expected.sort();
+            assert_eq!(tree.iter().map(|(key, _)| *key).collect::<Vec<_>>(), expected);
+            common::check_tree_structure(&tree);
+        }
+    }
+}
+
+#[test]
+fn swap_values_test() {
+    let elems = [40, 20, 60, 10, 30, 50, 70];
+    let mut tree = SplayTree::new();
+    for key in elems {
+        tree.insert(key, key.to_string());
+    }
+    let id = tree.id_of(&10).unwrap();
+
+    assert!(tree.swap_values(&10, &70));
+    assert_eq!(tree.get_by_id(id).map(|node| node.value().as_str()), Some("70"));
+    assert_eq!(tree.get(&70).map(|node| node.value().as_str()), Some("10"));
+    assert!(tree.swap_values(&30, &30));
+    assert_eq!(tree.get(&30).map(|node| node.value().as_str()), Some("30"));
+    common::check_tree_structure(&tree);
+
+    assert!(!tree.swap_values(&20, &25));
+    assert!(!tree.swap_values(&25, &20));
+    assert_eq!(tree.get(&20).map(|node| node.value().as_str()), Some("20"));
+    assert_eq!(tree.len(), elems.len());
+}
+
+#[test]
+fn take_test() {
+    let elems = [40, 20, 60, 10, 30];
+    let mut tree = SplayTree::new();
+    for key in elems {
+        tree.insert(key, key.to_string());
+    }
+
+    assert_eq!(tree.take(&20).as_deref(), Some("20"));
+    assert_eq!(tree.take(&20), None);
+    assert_eq!(tree.len(), elems.len() - 1);
+    assert_eq!(tree.capacity(), elems.len() - 1);
+    common::check_tree_structure(&tree);
+
+    // With a reservation, the node is kept for later insertions.
+    tree.reserve(1);
+    tree.insert(25, "25".to_string());
+    assert_eq!(tree.take(&40).as_deref(), Some("40"));
+    assert_eq!(tree.capacity(), elems.len());
+    tree.insert(40, "40".to_string());
+    assert_eq!(tree.capacity(), elems.len());
+    assert!(tree.iter().map(|(key, _)| *key).eq([10, 25, 30, 40, 60]));
+}
+
+#[test]
+fn map_values_test() {
+    let elems = [61, 14, 88, 3, 29, 70, 95, 42];
+    let mut tree = common::create_tree(&elems);
+    tree.get(&29);
+    let height = tree.height();
+    let depths: Vec<_> = elems.iter().map(|key| tree.depth_of(key)).collect();
+
+    let mut visited = vec![];
+    let tree = tree.map_values(|key, value| {
+        visited.push(*key);
+        format!("{}", key + value)
+    });
+    let mut sorted = elems;
+    sorted.sort();
+    assert_eq!(visited, sorted);
+    assert_eq!(tree.len(), elems.len());
+    assert_eq!(tree.height(), height);
+    assert_eq!(elems.iter().map(|key| tree.depth_of(key)).collect::<Vec<_>>(), depths);
+    assert!(tree.iter().all(|(key, value)| *value == (key * 2).to_string()));
+    common::check_tree_structure(&tree);
+
+    let empty = SplayTree::<u32, u32>::new().map_values(|_, value| value as u64);
+    assert!(empty.is_empty());
+
+    // Nodes of the same layout are reused in place.
+    let tree = common::create_tree(&elems);
+    let root = tree.root().map(|node| node as *const _ as usize);
+    let tree = tree.map_values(|_, value| value as i32);
+    assert_eq!(tree.root().map(|node| node as *const _ as usize), root);
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn map_values_panic_test() {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    let counter = Rc::new(());
+    let tree = || {
+        let mut tree = SplayTree::new();
+        for key in 0..20u32 {
+            tree.insert(key, Rc::clone(&counter));
+        }
+        tree
+    };
+
+    // Both the mapped entries and the rest are dropped, with or without reusing nodes.
+    let reused = tree();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        reused.map_values(|key, value| {
+            assert!(*key != 10, "mapping panicked");
+            value
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(Rc::strong_count(&counter), 1);
+
+    let reallocated = tree();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        reallocated.map_values(|key, value| {
+            assert!(*key != 10, "mapping panicked");
+            (value, *key)
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[test]
+fn map_keys_test() {
+    let elems = [61, 14, 88, 3, 29, 70, 95, 42];
+    let mut tree = common::create_tree(&elems);
+    tree.get(&29);
+    let depths: Vec<_> = elems.iter().map(|key| tree.depth_of(key)).collect();
+
+    // Order-preserving mappings keep the shape.
+    let tree = tree.map_keys(|key| key as u64 * 1000);
+    let keys: Vec<_> = elems.iter().map(|&key| key as u64 * 1000).collect();
+    assert_eq!(keys.iter().map(|key| tree.depth_of(key)).collect::<Vec<_>>(), depths);
+    common::check_tree_structure(&tree);
+
+    // Others rebuild the tree, keeping the entry with the greatest original key.
+    let tree = tree.map_keys(|key| (key / 1000) % 7);
+    let entries: Vec<_> = tree.iter().map(|(key, value)| (*key, *value)).collect();
+    assert_eq!(entries, [(0, 70), (1, 29), (3, 3), (4, 95), (5, 61)]);
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn merge_with_test() {
+    let mut tree = common::create_tree(&[10, 30, 50, 70]);
+    tree.merge_with(common::create_tree(&[20, 30, 70, 90]), |key, a, b| key + a + b);
+    let entries: Vec<_> = tree.iter().map(|(key, value)| (*key, *value)).collect();
+    assert_eq!(entries, [(10, 10), (20, 20), (30, 90), (50, 50), (70, 210), (90, 90)]);
+    common::check_tree_structure(&tree);
+
+    // Disjoint trees are grafted without calling the resolver.
+    tree.merge_with(common::create_tree(&[100, 110]), |_, _, _| unreachable!());
+    tree.merge_with(SplayTree::new(), |_, _, _| unreachable!());
+    assert_eq!(tree.len(), 8);
+    common::check_tree_structure(&tree);
+
+    let mut empty = SplayTree::new();
+    empty.merge_with(tree, |_, _, _| unreachable!());
+    assert_eq!(empty.len(), 8);
+}
+
+#[test]
+fn merge_with_panic_test() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut tree = common::create_tree(&[10, 30, 50, 70]);
+    let id = tree.id_of(&50).unwrap();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        tree.merge_with(common::create_tree(&[20, 30, 50, 70]), |key, a, b| {
+            assert!(*key != 50, "resolver panicked");
+            a + b
+        });
+    }));
+    assert!(result.is_err());
+
+    // The entry whose value was moved into the resolver is removed, the rest of
+    // the tree and the resolved values are kept.
+    let entries: Vec<_> = tree.iter().map(|(key, value)| (*key, *value)).collect();
+    assert_eq!(entries, [(10, 10), (20, 20), (30, 60), (70, 70)]);
+    assert!(tree.get_by_id(id).is_none());
+    common::check_tree_structure(&tree);
+
+    // The same holds when a small tree is inserted entry by entry.
+    let mut tree = common::create_tree(&(0..64).collect::<Vec<u32>>());
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        tree.merge_with(common::create_tree(&[10, 20]), |key, a, b| {
+            assert!(*key != 20, "resolver panicked");
+            a + b
+        });
+    }));
+    assert!(result.is_err());
+    assert_eq!(tree.len(), 63);
+    assert_eq!(tree.get(&10).map(|node| *node.value()), Some(20));
+    assert!(tree.get(&20).is_none());
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn merge_with_small_tree_test() {
+    let mut tree = SplayTree::new();
+    for key in 0..100 {
+        tree.insert(key * 2, vec![key]);
+    }
+    let id = tree.id_of(&40).unwrap();
+    let mut other = SplayTree::new();
+    other.insert(40, vec![1]);
+    other.insert(41, vec![2]);
+    tree.merge_with(other, |_, mut a, b| {
+        a.extend(b);
+        a
+    });
+    assert_eq!(tree.len(), 101);
+    assert_eq!(tree.get(&40).map(|node| node.value().clone()), Some(vec![20, 1]));
+    assert_eq!(tree.get(&41).map(|node| node.value().clone()), Some(vec![2]));
+    assert_eq!(tree.get_by_id(id).map(|node| *node.key()), Some(40));
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn difference_with_test() {
+    let mut tree = common::create_tree(&(0..40).collect::<Vec<u32>>());
+    let other = common::create_tree(&[1, 5, 6, 39, 45, 100]);
+    assert_eq!(tree.difference_with(&other), 4);
+    common::check_tree_structure(&tree);
+    assert_eq!(tree.len(), 36);
+    assert!(tree.iter().all(|(key, _)| ![1, 5, 6, 39].contains(key)));
+    assert!(tree.height() <= 6);
+
+    let root_id = tree.id_of(&20).unwrap();
+    assert_eq!(tree.difference_with(&SplayTree::<u32, ()>::new()), 0);
+    assert_eq!(tree.difference_with(&common::create_tree(&[2])), 1);
+    assert_eq!(tree.get_by_id(root_id).map(|node| *node.key()), Some(20));
+    assert_eq!(tree.difference_with(&common::create_tree(&(0..50).collect::<Vec<u32>>())), 35);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn intersect_with_test() {
+    let mut tree = common::create_tree(&(0..40).collect::<Vec<u32>>());
+    let other = common::create_tree(&[1, 5, 6, 39, 45, 100]);
+    assert_eq!(tree.intersect_with(&other), 36);
+    common::check_tree_structure(&tree);
+    assert!(tree.iter().map(|(key, _)| *key).eq([1, 5, 6, 39]));
+
+    assert_eq!(tree.intersect_with(&other), 0);
+    assert_eq!(tree.intersect_with(&common::create_tree(&[0, 6, 7])), 3);
+    assert_eq!(tree.iter().next(), Some((&6, &6)));
+    assert_eq!(tree.intersect_with(&SplayTree::<u32, ()>::new()), 1);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn diff_test() {
+    let old = common::create_tree(&[1, 3, 5, 7, 9]);
+    let mut new = common::create_tree(&[2, 3, 5, 9, 11]);
+    *new.get_mut(&5).unwrap().value_mut() = 50;
+
+    let changes: Vec<_> = old.diff(&new).collect();
+    assert_eq!(changes, [
+        Change::Removed(&1, &1),
+        Change::Added(&2, &2),
+        Change::Changed(&5, &5, &50),
+        Change::Removed(&7, &7),
+        Change::Added(&11, &11),
+    ]);
+    assert_eq!(old.diff(&old).next(), None);
+    assert_eq!(SplayTree::new().diff(&old).count(), old.len());
+}
+
+#[test]
+fn std_map_conversions_test() {
+    let btree: BTreeMap<u32, u32> = (0..100).map(|i| (i, i * 2)).collect();
+    let mut tree = SplayTree::from(btree.clone());
+    assert_eq!(tree.len(), 100);
+    assert_eq!(tree.get(&42).map(|node| *node.value()), Some(84));
+    assert_eq!(BTreeMap::from(tree), btree);
+
+    let hash: HashMap<u32, u32> = (0..100).rev().map(|i| (i, i * 3)).collect();
+    let tree = SplayTree::from(hash);
+    assert!(tree.iter().map(|(k, v)| (*k, *v)).eq((0..100).map(|i| (i, i * 3))));
+
+    let tree = SplayTree::from(BTreeMap::<u32, u32>::new());
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn to_vec_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let tree = common::create_tree(&elems);
+    let mut sorted = elems.to_vec();
+    sorted.sort();
+
+    let entries = tree.to_vec();
+    assert_eq!(entries.capacity(), elems.len());
+    assert!(entries.iter().map(|(k, _)| **k).eq(sorted.iter().copied()));
+
+    let entries = tree.into_sorted_vec();
+    assert_eq!(entries.capacity(), elems.len());
+    assert!(entries.iter().map(|(k, _)| *k).eq(sorted.iter().copied()));
+}
+
+#[test]
+fn debug_test() {
+    let mut tree = SplayTree::new();
+    tree.insert(2, "two");
+    tree.insert(1, "one");
+    assert_eq!(format!("{:?}", tree), r#"{1: "one", 2: "two"}"#);
+}
+
+#[test]
+fn prefix_range_test() {
+    let paths = ["a", "ab", "abc", "abd", "ac", "b", "ba", "/usr/bin", "/usr/lib", "/var"];
+    let mut tree = SplayTree::new();
+    for (i, path) in paths.iter().enumerate() {
+        tree.insert(path.to_string(), i);
+    }
+
+    let keys: Vec<&str> = tree.prefix_range("ab").map(|(k, _)| k.as_str()).collect();
+    assert_eq!(keys, ["ab", "abc", "abd"]);
+    assert_eq!(tree.root().unwrap().key(), "ab");
+
+    let keys: Vec<&str> = tree.prefix_range("/usr/").rev().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(keys, ["/usr/lib", "/usr/bin"]);
+    assert_eq!(tree.prefix_range("").count(), paths.len());
+    assert_eq!(tree.prefix_range("a").count(), 5);
+    assert_eq!(tree.prefix_range("abcd").count(), 0);
+    assert_eq!(tree.prefix_range("z").count(), 0);
+
+    let mut range = tree.prefix_range("b");
+    assert_eq!(range.next().map(|(_, v)| *v), Some(5));
+    assert_eq!(range.next_back().map(|(_, v)| *v), Some(6));
+    assert!(range.next().is_none());
+    assert!(range.next_back().is_none());
+}
+
+#[test]
+fn next_after_prev_before_test() {
+    let elems = [10, 20, 30, 40, 50];
+    let mut tree = common::create_tree(&elems);
+
+    assert_eq!(tree.next_after(&30).map(|node| *node.key()), Some(40));
+    assert_eq!(*tree.root().unwrap().key(), 40);
+    assert_eq!(tree.next_after(&35).map(|node| *node.key()), Some(40));
+    assert_eq!(tree.next_after(&0).map(|node| *node.key()), Some(10));
+    assert!(tree.next_after(&50).is_none());
+    assert!(tree.next_after(&60).is_none());
+
+    assert_eq!(tree.prev_before(&30).map(|node| *node.key()), Some(20));
+    assert_eq!(*tree.root().unwrap().key(), 20);
+    assert_eq!(tree.prev_before(&25).map(|node| *node.key()), Some(20));
+    assert_eq!(tree.prev_before(&100).map(|node| *node.key()), Some(50));
+    assert!(tree.prev_before(&10).is_none());
+    assert!(tree.prev_before(&5).is_none());
+
+    for key in 0..60 {
+        let next = elems.iter().copied().find(|&k| k > key);
+        let prev = elems.iter().rev().copied().find(|&k| k < key);
+        assert_eq!(tree.next_after(&key).map(|node| *node.key()), next);
+        assert_eq!(tree.prev_before(&key).map(|node| *node.key()), prev);
+    }
+
+    let mut empty: SplayTree<u32, u32> = SplayTree::new();
+    assert!(empty.next_after(&1).is_none());
+    assert!(empty.prev_before(&1).is_none());
+}
+
+#[test]
+fn ceil_floor_test() {
+    let elems = [10, 20, 30, 40, 50];
+    let mut tree = common::create_tree(&elems);
+
+    assert_eq!(tree.get_ceil(&30).map(|node| *node.key()), Some(30));
+    assert_eq!(tree.get_floor(&30).map(|node| *node.key()), Some(30));
+    assert_eq!(tree.get_ceil(&31).map(|node| *node.key()), Some(40));
+    assert_eq!(*tree.root().unwrap().key(), 40);
+    assert_eq!(tree.get_floor(&31).map(|node| *node.key()), Some(30));
+    assert_eq!(*tree.root().unwrap().key(), 30);
+    assert!(tree.get_ceil(&51).is_none());
+    assert!(tree.get_floor(&9).is_none());
+
+    for key in 0..60 {
+        let ceil = elems.iter().copied().find(|&k| k >= key);
+        let floor = elems.iter().rev().copied().find(|&k| k <= key);
+        assert_eq!(tree.get_ceil(&key).map(|node| *node.key()), ceil);
+        assert_eq!(tree.get_floor(&key).map(|node| *node.key()), floor);
+    }
+}
+
+#[test]
+fn remove_range_test() {
+    use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+    let elems: Vec<u32> = (0..40).map(|i| i * 5 % 41).collect();
+    let bounds = |i: u32| [Included(i), Excluded(i), Unbounded];
+    for start in (0..45).step_by(7) {
+        for end in (0..45).step_by(6) {
+            for (start, end) in bounds(start).into_iter().flat_map(|s| bounds(end).map(move |e| (s, e))) {
+                let mut tree = common::create_tree(&elems);
+                let mut expected: BTreeMap<u32, u32> = elems.iter().map(|&k| (k, k)).collect();
+                let range: (Bound<u32>, Bound<u32>) = (start, end);
+                let in_range: Vec<u32> = expected.keys().copied()
+                    .filter(|k| std::ops::RangeBounds::contains(&range, k))
+                    .collect();
+                for k in in_range.iter() {
+                    expected.remove(k);
+                }
+
+                assert_eq!(tree.remove_range(range), in_range.len());
+                assert_eq!(tree.len(), expected.len());
+                assert!(tree.iter().map(|(k, _)| *k).eq(expected.keys().copied()));
+            }
+        }
+    }
+
+    let mut tree = common::create_tree(&elems);
+    assert_eq!(tree.remove_range(10..20), 10);
+    assert_eq!(tree.remove_range(10..20), 0);
+    assert!(tree.get(&15).is_none());
+    assert!(tree.get(&20).is_some());
+    assert_eq!(tree.remove_range(..), 30);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn splay_range_test() {
+    fn subtree_keys(node: &Node<u32, u32>) -> Vec<u32> {
+        let mut keys = Vec::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            keys.push(*node.key());
+            stack.extend(node.left().into_iter().chain(node.right()));
+        }
+        keys.sort();
+        keys
+    }
+
+    let elems: Vec<u32> = (0..40).map(|i| i * 7 % 41).collect();
+    let mut tree = common::create_tree(&elems);
+    for (start, end) in [(10, 20), (0, 5), (35, 41), (0, 41), (12, 13)] {
+        let subtree = tree.splay_range(start..end).unwrap();
+        let expected: Vec<u32> = (start..end).filter(|key| elems.contains(key)).collect();
+        assert_eq!(subtree.subtree_len(), expected.len());
+        assert_eq!(subtree_keys(subtree), expected);
+        assert!(subtree.parent().is_none_or(|parent| parent.parent().is_none_or(Node::is_root)));
+        common::check_tree_structure(&tree);
+    }
+    assert_eq!(tree.root().map(|root| *root.key()), Some(13));
+    assert!(tree.splay_range(41..50).is_none());
+    assert!(tree.splay_range(20..20).is_none());
+    assert_eq!(tree.len(), elems.len());
+}
+
+#[test]
+fn len_in_range_test() {
+    use std::ops::Bound::{self, Excluded, Included, Unbounded};
+    use std::ops::RangeBounds;
+
+    fn count<R: RangeBounds<u32>>(elems: &[u32], range: R) -> usize {
+        elems.iter().filter(|k| range.contains(k)).count()
+    }
+
+    let mut elems: Vec<u32> = (0..50).map(|i| i * 7 % 101).collect();
+    let mut tree = common::create_tree(&elems);
+    for start in 0..105 {
+        for end in (start..110).step_by(9) {
+            assert_eq!(tree.len_in_range(start..end), count(&elems, start..end));
+            assert_eq!(tree.len_in_range(start..=end), count(&elems, start..=end));
+            let bounds = (Excluded(start), Included(end));
+            assert_eq!(tree.len_in_range(bounds), count(&elems, bounds));
+        }
+        assert_eq!(tree.len_in_range(start..), count(&elems, start..));
+        assert_eq!(tree.len_in_range(..start), count(&elems, ..start));
+    }
+    assert_eq!(tree.len_in_range::<u32, (Bound<u32>, Bound<u32>)>((Unbounded, Unbounded)), elems.len());
+    assert_eq!(tree.len_in_range((Included(60), Excluded(10))), 0);
+
+    tree.remove_range(20..40);
+    tree.remove(&0);
+    tree.insert(1000, 0);
+    elems.retain(|k| !(20..40).contains(k) && *k != 0);
+    elems.push(1000);
+    assert_eq!(tree.len_in_range(..), tree.len());
+    for start in (0..1010).step_by(13) {
+        assert_eq!(tree.len_in_range(start..), count(&elems, start..));
+    }
+}
+
+#[test]
+fn pop_root_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let mut tree = common::create_tree(&elems);
+
+    tree.get(&13);
+    assert_eq!(tree.pop_root().map(|node| *node.key()), Some(13));
+    assert!(!tree.contains_key(&13));
+    assert_eq!(tree.len(), elems.len() - 1);
+
+    tree.insert(20, 20);
+    assert_eq!(tree.pop_root().map(|node| node.into_key_value()), Some((20, 20)));
+
+    while tree.pop_root().is_some() {}
+    assert!(tree.is_empty());
+    assert!(tree.pop_root().is_none());
+}
+
+#[test]
+fn peek_min_max_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let tree = common::create_tree(&elems);
+    let root = *tree.root().unwrap().key();
+
+    assert_eq!(tree.peek_min().map(|node| *node.key()), Some(0));
+    assert_eq!(tree.peek_max().map(|node| *node.key()), Some(17));
+    assert_eq!(*tree.root().unwrap().key(), root);
+
+    let empty: SplayTree<u32, u32> = SplayTree::new();
+    assert!(empty.peek_min().is_none());
+    assert!(empty.peek_max().is_none());
+}
+
+#[test]
+fn for_each_fold_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let tree = common::create_tree(&elems);
+    let mut sorted = elems.to_vec();
+    sorted.sort();
+
+    let mut keys = Vec::new();
+    tree.for_each(|k, v| {
+        assert_eq!(k, v);
+        keys.push(*k);
+    });
+    assert_eq!(keys, sorted);
+
+    assert_eq!(tree.fold(0, |sum, _, v| sum + v), elems.iter().sum());
+    let ordered = tree.fold(Vec::new(), |mut acc, k, _| {
+        acc.push(*k);
+        acc
+    });
+    assert_eq!(ordered, sorted);
+
+    let empty: SplayTree<u32, u32> = SplayTree::new();
+    assert_eq!(empty.fold(7, |acc, _, _| acc + 1), 7);
+}
+
+#[test]
+fn level_order_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let tree = common::create_tree(&elems);
+
+    let levels: Vec<(usize, u32)> = tree.level_order().map(|(depth, k, _)| (depth, *k)).collect();
+    assert_eq!(levels.len(), elems.len());
+    assert_eq!(levels[0], (0, *tree.root().unwrap().key()));
+    assert!(levels.windows(2).all(|w| w[0].0 <= w[1].0));
+    assert_eq!(levels.last().unwrap().0 + 1, tree.height());
+    assert_eq!(tree.level_order().len(), elems.len());
+
+    let empty: SplayTree<u32, u32> = SplayTree::new();
+    assert!(empty.level_order().next().is_none());
+}
+
+#[test]
+fn access_order_test() {
+    let mut tree = common::create_tree(&(0..64).collect::<Vec<u32>>());
+    for key in [40, 7, 23] {
+        tree.get(&key);
+    }
+
+    let recent: Vec<u32> = tree.access_order().take(3).map(|(k, _)| *k).collect();
+    assert_eq!(recent[0], 23);
+    assert!(recent.contains(&7));
+    assert_eq!(tree.access_order().len(), 64);
+    assert_eq!(tree.root().unwrap().key(), &23);
+    assert!(SplayTree::<u32, u32>::new().access_order().next().is_none());
+}
+
+#[test]
+fn depth_histogram_test() {
+    let tree = common::create_tree(&[4, 2, 6, 1, 3, 5, 7]);
+    let histogram = tree.depth_histogram();
+    assert_eq!(histogram.len(), 7);
+    assert_eq!(histogram.counts().iter().sum::<usize>(), 7);
+    assert_eq!(histogram.count_at(0), 1);
+    assert_eq!(histogram.max_depth(), Some(tree.height() - 1));
+    assert_eq!(histogram.percentile(0.0), Some(0));
+    assert_eq!(histogram.percentile(100.0), histogram.max_depth());
+
+    let chain = common::create_tree(&(0..8).collect::<Vec<u32>>());
+    let histogram = chain.depth_histogram();
+    assert_eq!(histogram.counts(), [1; 8]);
+    assert_eq!(histogram.median(), Some(3));
+    assert_eq!(histogram.percentile(90.0), Some(7));
+    assert_eq!(histogram.count_at(8), 0);
+
+    let empty = SplayTree::<u32, u32>::new().depth_histogram();
+    assert!(empty.is_empty());
+    assert_eq!(empty.median(), None);
+    assert_eq!(empty.max_depth(), None);
+}
+
+#[test]
+fn heat_map_test() {
+    let mut tree = SplayTree::new();
+    for key in ["a", "b\"", "c\n"] {
+        tree.insert(key.to_string(), ());
+    }
+    let mut json = String::new();
+    tree.write_heat_map(&mut json).unwrap();
+    let hits = if cfg!(feature = "stats") { ",\"hits\":0" } else { "" };
+    assert_eq!(json, format!(concat!(
+        "{{\"len\":3,\"nodes\":[\n",
+        "{{\"key\":\"c\\n\",\"depth\":0,\"size\":3,\"parent\":null{0}}},\n",
+        "{{\"key\":\"b\\\"\",\"depth\":1,\"size\":2,\"parent\":0{0}}},\n",
+        "{{\"key\":\"a\",\"depth\":2,\"size\":1,\"parent\":1{0}}}\n",
+        "]}}",
+    ), hits));
+
+    json.clear();
+    SplayTree::<u32, u32>::new().write_heat_map(&mut json).unwrap();
+    assert_eq!(json, "{\"len\":0,\"nodes\":[]}");
+}
+
+#[test]
+fn pre_post_order_test() {
+    fn walk(node: Option<&Node<u32, u32>>, pre: &mut Vec<u32>, post: &mut Vec<u32>) {
+        if let Some(node) = node {
+            pre.push(*node.key());
+            walk(node.left(), pre, post);
+            walk(node.right(), pre, post);
+            post.push(*node.key());
+        }
+    }
+
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3, 25, 21, 1];
+    let mut tree = common::create_tree(&elems);
+    tree.get(&9);
+    let (mut pre, mut post) = (Vec::new(), Vec::new());
+    walk(tree.root(), &mut pre, &mut post);
+
+    assert!(tree.pre_order().map(|(k, _)| *k).eq(pre));
+    assert!(tree.post_order().map(|(k, _)| *k).eq(post));
+    assert_eq!(tree.pre_order().len(), elems.len());
+    assert_eq!(tree.post_order().len(), elems.len());
+
+    let shaped = TreeBuilder::new()
+        .node(10, 0).node(5, 0).node(20, 0).hole().node(7, 0).node(15, 0)
+        .build()
+        .unwrap();
+    assert!(shaped.pre_order().map(|(k, _)| *k).eq([10, 5, 7, 20, 15]));
+    assert!(shaped.post_order().map(|(k, _)| *k).eq([7, 5, 15, 20, 10]));
+
+    let empty: SplayTree<u32, u32> = SplayTree::new();
+    assert!(empty.pre_order().next().is_none());
+    assert!(empty.post_order().next().is_none());
+}
+
+#[test]
+fn visit_test() {
+    use std::ops::ControlFlow;
+
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let tree = common::create_tree(&elems);
+
+    let mut visited = Vec::new();
+    let found = tree.visit(|k, v| {
+        visited.push(*k);
+        if *k > 5 { ControlFlow::Break(*v) } else { ControlFlow::Continue(()) }
+    });
+    assert_eq!(found, ControlFlow::Break(9));
+    assert_eq!(visited, [0, 2, 3, 4, 9]);
+
+    let mut count = 0;
+    let result: ControlFlow<()> = tree.visit(|_, _| {
+        count += 1;
+        ControlFlow::Continue(())
+    });
+    assert_eq!(result, ControlFlow::Continue(()));
+    assert_eq!(count, elems.len());
+}
+
+#[test]
+fn find_by_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let mut tree = SplayTree::new();
+    for &key in &elems {
+        tree.insert(key, format!("v{}", key % 5));
+    }
+    let root = tree.root().map(|node| *node.key());
+
+    let mut visited = Vec::new();
+    let found = tree.find_by(|key, value| {
+        visited.push(*key);
+        value == "v2"
+    });
+    assert_eq!(found.map(|node| *node.key()), Some(2));
+    assert_eq!(visited, [0, 2]);
+    assert!(tree.find_by(|_, value| value == "v9").is_none());
+
+    assert_eq!(tree.find_map_value(|key, value| (value == "v3").then_some(*key)), Some(3));
+    assert_eq!(tree.find_map_value(|_, value| value.strip_prefix('x').map(str::len)), None);
+    assert_eq!(tree.root().map(|node| *node.key()), root);
+}
+
+#[test]
+fn get_batch_test() {
+    let elems: Vec<u32> = (0..100).map(|i| i * 37 % 101).collect();
+    let mut tree = common::create_tree(&elems);
+
+    let sorted: Vec<u32> = (0..120).step_by(3).collect();
+    let found = tree.get_batch(&sorted);
+    assert_eq!(found.len(), sorted.len());
+    for (key, node) in sorted.iter().zip(found) {
+        assert_eq!(node.map(|node| *node.key()), elems.contains(key).then_some(*key));
+    }
+    assert_eq!(*tree.root().unwrap().key(), 99);
+
+    let unsorted = [50, 3, 500, 3, 77, 0, 101];
+    let found: Vec<Option<u32>> = tree.get_batch(&unsorted).into_iter()
+        .map(|node| node.map(|node| *node.value()))
+        .collect();
+    assert_eq!(found, [Some(50), Some(3), None, Some(3), Some(77), Some(0), None]);
+    assert_eq!(*tree.root().unwrap().key(), 0);
+
+    assert!(tree.get_batch(&[] as &[u32]).is_empty());
+    let mut strings: SplayTree<String, usize> = SplayTree::new();
+    strings.insert("b".to_string(), 1);
+    strings.insert("a".to_string(), 0);
+    let found: Vec<bool> = strings.get_batch(["a", "c"]).iter().map(Option::is_some).collect();
+    assert_eq!(found, [true, false]);
+}
+
+#[test]
+fn remove_keys_test() {
+    let elems: Vec<u32> = (0..100).map(|i| i * 37 % 101).collect();
+    let mut tree = common::create_tree(&elems);
+
+    let keys = [90, 5, 500, 5, 42, 0, 101, 99];
+    let removed = tree.remove_keys(&keys);
+    assert_eq!(removed, [(0, 0), (5, 5), (42, 42), (90, 90), (99, 99)]);
+    assert_eq!(tree.len(), elems.len() - removed.len());
+    for (key, _) in removed.iter() {
+        assert!(!tree.contains_key(key));
+    }
+
+    let rest: Vec<u32> = tree.iter().map(|(k, _)| *k).collect();
+    let removed = tree.remove_keys(&rest);
+    assert_eq!(removed.len(), rest.len());
+    assert!(tree.is_empty());
+    assert!(tree.remove_keys(&keys).is_empty());
+}
+
+#[test]
+fn apply_batch_test() {
+    let elems = [11, 17, 9, 0, 13, 2, 4, 3];
+    let mut tree = common::create_tree(&elems);
+    let mut expected: BTreeMap<u32, u32> = elems.iter().map(|&k| (k, k)).collect();
+
+    let ops = vec![
+        Op::Insert(20, 1),
+        Op::Remove(9),
+        Op::Update(11, 110),
+        Op::Update(12, 120),
+        Op::Insert(5, 50),
+        Op::Remove(5),
+        Op::Insert(5, 51),
+        Op::Update(20, 2),
+        Op::Remove(100),
+    ];
+    for op in ops.iter().cloned() {
+        match op {
+            Op::Insert(k, v) => {
+                expected.insert(k, v);
+            },
+            Op::Remove(k) => {
+                expected.remove(&k);
+            },
+            Op::Update(k, v) => {
+                if let Some(value) = expected.get_mut(&k) {
+                    *value = v;
+                }
+            },
+        }
+    }
+
+    tree.apply_batch(ops);
+    assert_eq!(tree.len(), expected.len());
+    assert!(tree.iter().map(|(k, v)| (*k, *v)).eq(expected.into_iter()));
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "stats")]
+
+use splay_tree::{SplayTree, TreeStats};
+
+mod common;
+
+#[test]
+fn counters_test() {
+    let mut tree = SplayTree::new();
+    assert_eq!(tree.stats(), TreeStats::default());
+
+    tree.insert(1u32, 1u32);
+    tree.insert(2, 2);
+    tree.insert(3, 3);
+    // Every insertion appends the maximum below the root, which is one rotation away.
+    let stats = tree.stats();
+    assert_eq!(stats.allocations, 3);
+    assert_eq!(stats.comparisons, 2);
+    assert_eq!(stats.rotations, 2);
+    assert_eq!(stats.splay_steps, 2);
+
+    tree.reset_stats();
+    assert!(tree.get(&1).is_some());
+    // 1 is two left steps below the root, so it takes a single zig-zig.
+    let stats = tree.stats();
+    assert_eq!(stats.comparisons, 3);
+    assert_eq!(stats.rotations, 2);
+    assert_eq!(stats.splay_steps, 1);
+
+    tree.reset_stats();
+    assert!(tree.remove(&1).is_some());
+    assert_eq!(tree.stats().frees, 1);
+    assert_eq!(tree.stats().allocations, 0);
+    common::check_tree_structure(&tree);
+}
+
+#[test]
+fn rotations_test() {
+    let elems: Vec<u32> = (0..200).map(|i| i * 73 % 200).collect();
+    let mut tree = common::create_tree(&elems);
+    let stats = tree.stats();
+    assert_eq!(stats.allocations, 200);
+    assert!(stats.splay_steps * 2 >= stats.rotations);
+    assert!(stats.splay_steps <= stats.rotations);
+
+    tree.reset_stats();
+    tree.get_min();
+    tree.get_min();
+    // The minimum is already at the root after the first lookup.
+    let stats = tree.stats();
+    assert_eq!(stats.comparisons, 0);
+    assert!(stats.rotations > 0);
+    let rotations = stats.rotations;
+    tree.get_min();
+    assert_eq!(tree.stats().rotations, rotations);
+}
+
+#[test]
+fn root_hit_test() {
+    let mut tree = common::create_tree(&[5, 1, 9, 3, 7]);
+    assert!(tree.get(&3).is_some());
+    tree.reset_stats();
+    for _ in 0..10 {
+        assert!(tree.get(&3).is_some());
+        assert!(tree.contains_key(&3));
+    }
+    let stats = tree.stats();
+    assert_eq!(stats.comparisons, 20);
+    assert_eq!(stats.rotations, 0);
+    assert_eq!(stats.splay_steps, 0);
+}
+
+#[test]
+fn hits_test() {
+    let mut tree = common::create_tree(&[5, 3, 8]);
+    for key in [3, 3, 8, 42] {
+        tree.get(&key);
+    }
+    assert_eq!(tree.get(&3).map(|node| node.hits()), Some(3));
+    assert_eq!(tree.peek_max().map(|node| node.hits()), Some(1));
+
+    let mut json = String::new();
+    tree.write_heat_map(&mut json).unwrap();
+    assert!(json.contains("{\"key\":\"3\",\"depth\":0,\"size\":3,\"parent\":null,\"hits\":3}"));
+}
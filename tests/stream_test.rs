@@ -0,0 +1,34 @@
+#![cfg(feature = "async")]
+
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures_core::{FusedStream, Stream};
+use splay_tree::SplayTree;
+
+mod common;
+
+#[test]
+fn chunks_test() {
+    let tree = common::create_tree(&(0..10).rev().collect::<Vec<u32>>());
+    let mut stream = tree.into_stream(4);
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(stream.size_hint(), (3, Some(3)));
+
+    let mut chunks = Vec::new();
+    let mut pending = 0;
+    loop {
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Ready(Some(chunk)) => chunks.push(chunk),
+            Poll::Ready(None) => break,
+            Poll::Pending => pending += 1,
+        }
+    }
+    assert_eq!(pending, 2);
+    assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), [4, 4, 2]);
+    assert!(chunks.concat().into_iter().eq((0..10).map(|i| (i, i))));
+    assert!(stream.is_terminated());
+
+    let mut empty = SplayTree::<u32, u32>::new().into_stream(1);
+    assert_eq!(Pin::new(&mut empty).poll_next(&mut cx), Poll::Ready(None));
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::thread;
+
+use splay_tree::SyncSplayTree;
+
+mod common;
+
+#[test]
+fn insert_get_test() {
+    let tree = SyncSplayTree::new();
+    for i in [13u32, 4, 3, 89, 12] {
+        assert_eq!(tree.insert(i, i.to_string()), None);
+    }
+    assert_eq!(tree.insert(4, "four".to_string()), Some("4".to_string()));
+    assert_eq!(tree.get(&4), Some("four".to_string()));
+    assert_eq!(tree.get_with(&89, |v| v.len()), Some(2));
+    assert!(tree.get(&100).is_none());
+    assert_eq!(tree.len(), 5);
+}
+
+#[test]
+fn get_mut_with_remove_test() {
+    let tree = SyncSplayTree::from(common::create_tree(&[1, 2, 3]));
+    assert_eq!(tree.get_mut_with(&2, |v| { *v *= 10; *v }), Some(20));
+    assert_eq!(tree.remove(&2), Some(20));
+    assert_eq!(tree.remove(&2), None);
+    assert!(!tree.contains_key(&2));
+    common::check_tree_structure(&tree.lock());
+}
+
+#[test]
+fn concurrent_test() {
+    let tree = Arc::new(SyncSplayTree::new());
+    let handles: Vec<_> = (0..4u32).map(|t| {
+        let tree = tree.clone();
+        thread::spawn(move || {
+            for i in (t * 100)..(t * 100 + 100) {
+                tree.insert(i, i);
+                assert_eq!(tree.get(&i), Some(i));
+            }
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let tree = Arc::try_unwrap(tree).ok().unwrap().into_inner();
+    assert_eq!(tree.len(), 400);
+    common::check_tree_structure(&tree);
+}
@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+
+use splay_tree::TombstoneTree;
+
+#[test]
+fn lazy_remove_test() {
+    let mut tree: TombstoneTree<u32, u32> = (0..10).map(|i| (i, i * 2)).collect();
+    tree.set_purge_ratio(1.0);
+    assert_eq!(tree.remove(&3), Some(6));
+    assert_eq!(tree.remove(&3), None);
+    assert_eq!(tree.remove(&42), None);
+    assert_eq!(tree.tombstones(), 1);
+    assert_eq!(tree.len(), 9);
+    assert!(tree.get(&3).is_none());
+    assert!(!tree.contains_key(&3));
+    assert!(tree.iter().map(|(k, _)| *k).eq((0..10).filter(|&i| i != 3)));
+    assert!(tree.iter().rev().map(|(k, _)| *k).eq((0..10).rev().filter(|&i| i != 3)));
+    assert_eq!(tree.iter().len(), 9);
+
+    assert_eq!(tree.insert(3, 30), None);
+    assert_eq!(tree.tombstones(), 0);
+    assert_eq!(tree.get(&3), Some(&30));
+
+    for i in 0..10 {
+        tree.remove(&i);
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.tombstones(), 10);
+    tree.purge();
+    assert_eq!(tree.tombstones(), 0);
+    assert_eq!(tree.memory_usage(), 0);
+}
+
+#[test]
+fn purge_ratio_test() {
+    let mut tree: TombstoneTree<u32, u32> = TombstoneTree::new();
+    let mut expected = BTreeMap::new();
+    for i in 0..100u32 {
+        tree.insert(i, i);
+        expected.insert(i, i);
+    }
+    for i in 0..60u32 {
+        let key = (i * 37) % 100;
+        assert_eq!(tree.remove(&key), expected.remove(&key));
+        assert!(tree.tombstones() * 2 <= tree.len() + tree.tombstones());
+    }
+    assert_eq!(tree.len(), expected.len());
+    assert!(tree.iter().eq(expected.iter()));
+
+    tree.set_purge_ratio(0.1);
+    assert_eq!(tree.tombstones(), 0);
+    assert!(tree.iter().eq(expected.iter()));
+}
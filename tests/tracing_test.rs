@@ -0,0 +1,90 @@
+#![cfg(feature = "tracing")]
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use splay_tree::SplayTree;
+
+#[derive(Default)]
+struct Recorder {
+    spans: Mutex<Vec<String>>,
+    depths: Mutex<Vec<(String, u64)>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    depth: Option<u64>,
+}
+
+impl Visit for EventVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "depth" {
+            self.depth = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        }
+    }
+}
+
+struct Shared(Arc<Recorder>);
+
+impl Subscriber for Shared {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.0.spans.lock().unwrap().push(span.metadata().name().to_string());
+        Id::from_u64(self.0.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        if let Some(depth) = visitor.depth {
+            self.0.depths.lock().unwrap().push((visitor.message, depth));
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn spans_and_events_test() {
+    let recorder = Arc::new(Recorder::default());
+
+    tracing::subscriber::with_default(Shared(recorder.clone()), || {
+        let mut tree = SplayTree::new();
+        tree.insert(1u32, 1u32);
+        tree.insert(2, 2);
+        tree.insert(3, 3);
+        assert!(tree.get(&1).is_some());
+        assert!(tree.get(&10).is_none());
+        assert!(tree.remove(&2).is_some());
+    });
+
+    let spans = recorder.spans.lock().unwrap();
+    assert_eq!(spans.iter().filter(|name| *name == "splay_tree::insert").count(), 3);
+    assert_eq!(spans.iter().filter(|name| *name == "splay_tree::remove").count(), 1);
+
+    let depths = recorder.depths.lock().unwrap();
+    assert!(depths.contains(&("splay".to_string(), 2)));
+    assert!(depths.contains(&("key not found".to_string(), 3)));
+}
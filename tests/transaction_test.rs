@@ -0,0 +1,43 @@
+mod common;
+
+use splay_tree::SplayTree;
+
+#[test]
+fn commit_test() {
+    let mut tree = common::create_tree(&[1, 2, 3, 4]);
+    let moved: Result<u32, ()> = tree.transaction(|txn| {
+        let value = *txn.get(&2).ok_or(())?;
+        assert!(txn.remove(2));
+        txn.insert(20, value);
+        assert!(!txn.contains_key(&2));
+        assert_eq!(txn.get(&20), Some(&2));
+        assert!(txn.update(3, 30));
+        assert!(!txn.update(5, 50));
+        assert!(!txn.remove(6));
+        assert_eq!(txn.len(), 3);
+        Ok(value)
+    });
+    assert_eq!(moved, Ok(2));
+    assert!(tree.iter().map(|(k, v)| (*k, *v)).eq([(1, 1), (3, 30), (4, 4), (20, 2)]));
+}
+
+#[test]
+fn rollback_test() {
+    let mut tree = common::create_tree(&[5, 1, 9, 3, 7]);
+    let shape: Vec<u32> = tree.pre_order().map(|(k, _)| *k).collect();
+
+    let result: Result<(), &str> = tree.transaction(|txn| {
+        txn.insert(100, 100);
+        txn.remove(5);
+        assert_eq!(txn.get(&1), Some(&1));
+        Err("abort")
+    });
+    assert_eq!(result, Err("abort"));
+    assert!(tree.pre_order().map(|(k, _)| *k).eq(shape));
+
+    let empty: Result<(), ()> = SplayTree::<u32, u32>::new().transaction(|txn| {
+        assert!(txn.is_empty());
+        Ok(())
+    });
+    assert!(empty.is_ok());
+}